@@ -1,16 +1,62 @@
-use std::path::{Path, PathBuf};
+use std::{
+    collections::{HashMap, HashSet},
+    iter,
+    path::{Path, PathBuf},
+};
 
 use tracing::{debug, info, warn};
 use tree_sitter::{Node, Query, QueryCursor, StreamingIterator, Tree};
 
-use super::preprocess::preprocess_code;
-use super::splitter::{add_chunk_context, split_large_chunk};
+use super::components::mask;
+use super::imports::{ImportContext, collect_imports, imports_for};
+use super::license::{LicenseHeaders, license_header};
+use super::preprocess::{CommentPolicy, leading_doc_comments, preprocess_code};
+use super::queries::Queries;
+use super::sizing::ChunkSizer;
+use super::splitter::{
+    RUBY_SCOPES, add_chunk_context, add_config_context, add_elixir_context, add_fence_language,
+    add_go_context, add_hcl_context, add_impl_context, add_markup_context, add_mixin_context,
+    chunk_node, enclosing, split_large_chunk,
+};
+use super::test_code::{is_test_node, is_test_path};
 use super::types::CodeChunk;
 
 use crate::utils::parsers::SupportedParsers;
 
 const DEFAULT_MAX_CHUNK_SIZE: usize = 4096;
 const DEFAULT_OVERLAP_PERCENTAGE: usize = 10;
+/// Chunks spanning fewer lines than this are merged with their small neighbours
+const SMALL_CHUNK_LINES: usize = 5;
+/// Lines of the body kept after the signature in a signature chunk
+const SIGNATURE_BODY_LINES: usize = 3;
+/// Node types that get a signature chunk when they have to be split
+const FUNCTION_KINDS: &[&str] = &[
+    "function_item",
+    "function_definition",
+    "function_declaration",
+    "method_definition",
+    "method_declaration",
+    "decorated_definition",
+];
+
+/// How files are cut into chunks
+#[derive(Debug, Clone, Default)]
+pub struct ChunkerConfig {
+    pub max_chunk_size: Option<usize>,
+    pub overlap_percentage: Option<usize>,
+    /// Which comments are kept in chunk content
+    pub comments: CommentPolicy,
+    /// Unit `max_chunk_size` and the overlap are measured in
+    pub sizer: ChunkSizer,
+    /// Also chunk control flow statements such as Python's `if`, `for` and `while`
+    pub statement_chunks: bool,
+    /// Which nodes become chunks for each language
+    pub queries: Queries,
+    /// Which of the file's imports are prepended to each chunk
+    pub imports: ImportContext,
+    /// Whether license headers are stripped, and kept in metadata
+    pub license_headers: LicenseHeaders,
+}
 
 pub struct Chunker {
     tree: Tree,
@@ -19,6 +65,13 @@ pub struct Chunker {
     language: SupportedParsers,
     max_chunk_size: usize,
     overlap_percentage: usize,
+    comments: CommentPolicy,
+    sizer: ChunkSizer,
+    statement_chunks: bool,
+    queries: Queries,
+    imports: ImportContext,
+    license: Option<String>,
+    small_chunk_lines: usize,
 }
 
 impl Chunker {
@@ -27,19 +80,82 @@ impl Chunker {
         source: &str,
         path: &Path,
         language: &SupportedParsers,
-        max_chunk_size: Option<usize>,
-        overlap_percentage: Option<usize>,
+        config: ChunkerConfig,
     ) -> Self {
+        let header = match config.license_headers {
+            LicenseHeaders::Keep => None,
+            _ => license_header(tree.root_node(), source),
+        };
+
+        // Blanking the header keeps every node's position in the source
+        let (source, license) = match header {
+            Some(header) => (
+                mask(source, |byte| !header.contains(&byte)),
+                (config.license_headers == LicenseHeaders::Metadata)
+                    .then(|| source[header].to_string()),
+            ),
+            None => (source.to_string(), None),
+        };
+
         Self {
             tree: tree.clone(),
-            source: source.to_string(),
+            source,
             path: path.to_path_buf(),
             language: language.clone(),
-            max_chunk_size: max_chunk_size.unwrap_or(DEFAULT_MAX_CHUNK_SIZE),
-            overlap_percentage: overlap_percentage.unwrap_or(DEFAULT_OVERLAP_PERCENTAGE),
+            max_chunk_size: config.max_chunk_size.unwrap_or(DEFAULT_MAX_CHUNK_SIZE),
+            overlap_percentage: config.overlap_percentage.unwrap_or(DEFAULT_OVERLAP_PERCENTAGE),
+            comments: config.comments,
+            sizer: config.sizer,
+            statement_chunks: config.statement_chunks,
+            queries: config.queries,
+            imports: config.imports,
+            license,
+            small_chunk_lines: SMALL_CHUNK_LINES,
         }
     }
 
+    /// Merge every run of sibling chunks up to the size limit, not just the small ones
+    pub fn merge_all(mut self) -> Self {
+        self.small_chunk_lines = usize::MAX;
+        self
+    }
+
+    /// Fixed-size overlapping windows over the whole file, ignoring its syntax
+    pub fn sliding_windows(&self) -> Vec<CodeChunk> {
+        if self.source.is_empty() {
+            return Vec::new();
+        }
+
+        let file = CodeChunk {
+            content: self.source.clone(),
+            node_type: "window".to_string(),
+            start_line: 0,
+            end_line: self.tree.root_node().end_position().row,
+            start_byte: 0,
+            end_byte: self.source.len(),
+            path: self.path.clone(),
+            language: self.language.to_string(),
+            parent_id: None,
+            is_test: is_test_path(&self.path),
+            ..Default::default()
+        };
+
+        let mut windows = split_large_chunk(
+            &file,
+            None,
+            &self.source,
+            self.max_chunk_size,
+            self.overlap_percentage,
+            &self.sizer,
+        );
+        for window in &mut windows {
+            window.license = self.license.clone();
+            window.hash_content();
+        }
+
+        windows
+    }
+
     pub fn extract_chunks(&self) -> Vec<CodeChunk> {
         let mut chunks = Vec::new();
         let root_node = self.tree.root_node();
@@ -47,6 +163,10 @@ impl Chunker {
 
         let structured_chunks = self.extract_structured_chunks(root_node);
         if !structured_chunks.is_empty() {
+            chunks.push(self.file_summary(&structured_chunks, root_node));
+
+            let mut structured_chunks = self.merge_small_chunks(structured_chunks);
+            self.prepend_imports(&mut structured_chunks, root_node);
             chunks.extend(structured_chunks);
         }
 
@@ -61,26 +181,61 @@ impl Chunker {
                 self.path.display()
             );
             chunks.push(CodeChunk {
-                content: preprocess_code(&root_node, &self.source),
+                content: preprocess_code(&root_node, &self.source, self.comments),
                 node_type: "file".to_string(),
                 start_line: 0,
                 end_line: root_node.end_position().row,
+                start_byte: 0,
+                end_byte: self.source.len(),
                 path: self.path.clone(),
                 language: self.language.to_string(),
+                parent_id: None,
+                ..Default::default()
             });
         }
 
         // Split large chunks if needed
         let mut final_chunks = Vec::new();
+        let mut split_ids = HashMap::new();
         for chunk in chunks {
-            if chunk.content.len() > self.max_chunk_size {
-                let split = split_large_chunk(&chunk, self.max_chunk_size, self.overlap_percentage);
+            if self.sizer.size(&chunk.content) > self.max_chunk_size {
+                let split = split_large_chunk(
+                    &chunk,
+                    chunk_node(root_node, &chunk),
+                    &self.source,
+                    self.max_chunk_size,
+                    self.overlap_percentage,
+                    &self.sizer,
+                );
+                if let Some(first) = split.first() {
+                    split_ids.insert(chunk.id(), first.id());
+                }
+                if let Some(signature) = signature_chunk(&chunk) {
+                    final_chunks.push(signature);
+                }
                 final_chunks.extend(split);
             } else {
                 final_chunks.push(chunk);
             }
         }
 
+        let test_file = is_test_path(&self.path);
+
+        for chunk in &mut final_chunks {
+            // Children of a split parent point at its first part
+            if let Some(first) = chunk.parent_id.and_then(|id| split_ids.get(&id)) {
+                chunk.parent_id = Some(*first);
+            }
+
+            chunk.is_test |= test_file;
+
+            if chunk.parent_id.is_none() {
+                chunk.license = self.license.clone();
+            }
+
+            chunk.hash_content();
+        }
+
         debug!(
             "Extracted {} chunks from {}",
             final_chunks.len(),
@@ -89,59 +244,123 @@ impl Chunker {
         final_chunks
     }
 
+    /// A file-level chunk listing the first line of each top-level chunk, which top-level chunks
+    /// use as their parent
+    fn file_summary(&self, chunks: &[CodeChunk], root_node: Node) -> CodeChunk {
+        let mut summary = CodeChunk {
+            content: format!("// File: {}\n", self.path.display()),
+            node_type: "file".to_string(),
+            start_line: 0,
+            end_line: root_node.end_position().row,
+            start_byte: 0,
+            end_byte: self.source.len(),
+            path: self.path.clone(),
+            language: self.language.to_string(),
+            parent_id: None,
+            ..Default::default()
+        };
+        let id = summary.id();
+
+        for chunk in chunks.iter().filter(|chunk| chunk.parent_id == Some(id)) {
+            if let Some(line) = chunk.content.lines().find(|line| !line.trim().is_empty()) {
+                summary.content.push_str(line);
+                summary.content.push('\n');
+            }
+        }
+
+        summary
+    }
+
+    /// Put the file's imports the chunk uses in front of it, so its embedding reflects the
+    /// libraries it works with
+    fn prepend_imports(&self, chunks: &mut [CodeChunk], root_node: Node) {
+        if self.imports == ImportContext::None {
+            return;
+        }
+
+        let imports = collect_imports(root_node, &self.source);
+
+        // The Rust `use` chunk is the imports themselves
+        for chunk in chunks.iter_mut().filter(|chunk| chunk.node_type != "use_declarations") {
+            if let Some(imports) = imports_for(&imports, &chunk.content, self.imports) {
+                chunk.content = format!("{imports}\n{}", chunk.content);
+            }
+        }
+    }
+
+    /// Greedily merge runs of small sibling chunks up to the size limit, so a file of one-line
+    /// constants doesn't become one low-signal point each. The merged chunk's type lists the
+    /// symbols it holds
+    fn merge_small_chunks(&self, mut chunks: Vec<CodeChunk>) -> Vec<CodeChunk> {
+        let parents: HashSet<u64> = chunks.iter().filter_map(|chunk| chunk.parent_id).collect();
+        let is_small = |chunk: &CodeChunk| {
+            chunk.end_line - chunk.start_line < self.small_chunk_lines
+                && !parents.contains(&chunk.id())
+        };
+
+        chunks.sort_by_key(|chunk| chunk.start_line);
+
+        let mut merged: Vec<CodeChunk> = Vec::with_capacity(chunks.len());
+        let mut run: Vec<CodeChunk> = Vec::new();
+
+        for chunk in chunks {
+            let fits = run.first().is_some_and(|first| {
+                let size: usize = run.iter().map(|chunk| self.sizer.size(&chunk.content)).sum();
+
+                first.parent_id == chunk.parent_id
+                    && size + self.sizer.size(&chunk.content) <= self.max_chunk_size
+            });
+
+            if !run.is_empty() && (!fits || !is_small(&chunk)) {
+                merged.push(merge_run(std::mem::take(&mut run)));
+            }
+
+            match is_small(&chunk) {
+                true => run.push(chunk),
+                false => merged.push(chunk),
+            }
+        }
+
+        if !run.is_empty() {
+            merged.push(merge_run(run));
+        }
+
+        merged
+    }
+
+    /// Point each chunk at the chunk of its nearest captured ancestor, or at the file summary
+    fn link_parents(&self, captured: Vec<(Node, CodeChunk)>) -> Vec<CodeChunk> {
+        let file_id = self.file_summary(&[], self.tree.root_node()).id();
+        let ids: HashMap<usize, u64> =
+            captured.iter().map(|(node, chunk)| (node.id(), chunk.id())).collect();
+
+        captured
+            .into_iter()
+            .map(|(node, mut chunk)| {
+                let mut ancestor = node.parent();
+
+                while let Some(parent) = ancestor {
+                    if let Some(id) = ids.get(&parent.id()) {
+                        chunk.parent_id = Some(*id);
+                        break;
+                    }
+                    ancestor = parent.parent();
+                }
+
+                chunk.parent_id.get_or_insert(file_id);
+                chunk
+            })
+            .collect()
+    }
+
     // Extract chunks using structured, language-specific queries
     fn extract_structured_chunks(&self, root_node: Node) -> Vec<CodeChunk> {
-        let mut chunks = Vec::new();
+        let mut captured = Vec::new();
 
-        let query_str = match self.language {
-            SupportedParsers::Rust => {
-                "(
-                (function_item) @function
-                (struct_item) @struct
-                (impl_item
-                    body: (declaration_list
-                        (function_item) @impl_method))
-                (trait_item
-                    body: (declaration_list
-                        (function_item) @trait_method))
-                (enum_item) @enum
-                (mod_item) @mod
-                (macro_definition) @macro
-                )"
-            },
-            SupportedParsers::Python => {
-                "(
-                (function_definition) @function
-                (class_definition) @class
-                (decorated_definition) @decorated
-                (if_statement) @if
-                (for_statement) @for
-                (while_statement) @while
-                )"
-            },
-            SupportedParsers::JavaScript | SupportedParsers::TypeScript | SupportedParsers::TSX => {
-                "(
-                (function_declaration) @function
-                (method_definition) @method
-                (class_declaration) @class
-                (arrow_function) @arrow_function
-                (export_statement) @export
-                (lexical_declaration) @declaration
-                )"
-            },
-            SupportedParsers::Go => {
-                "(
-                (function_declaration) @function
-                (method_declaration) @method
-                (type_declaration) @type
-                (struct_type) @struct
-                (interface_type) @interface
-                )"
-            },
-        };
+        let query_str = self.queries.get(&self.language, self.statement_chunks);
 
         // Execute the query
-        match Query::new(&self.language.language(), query_str) {
+        match Query::new(&self.language.language(), &query_str) {
             Ok(query) => {
                 let mut query_cursor = QueryCursor::new();
                 let mut matches = query_cursor.matches(&query, root_node, self.source.as_bytes());
@@ -149,8 +368,20 @@ impl Chunker {
                 // Process each match directly - no recursion
                 while let Some(match_result) = matches.next() {
                     for capture in match_result.captures {
+                        // Captures starting with `_` only feed predicates
+                        if query.capture_names()[capture.index as usize].starts_with('_') {
+                            continue;
+                        }
+
                         let node = capture.node;
-                        let kind = node.kind();
+                        let kind = match node.kind() {
+                            // Elixir definitions are calls to `def`, `defmodule` and the like
+                            "call" => node
+                                .child_by_field_name("target")
+                                .and_then(|target| self.source.get(target.byte_range()))
+                                .unwrap_or("call"),
+                            kind => kind,
+                        };
 
                         // Skip very small nodes
                         if node.start_position().row == node.end_position().row
@@ -159,25 +390,164 @@ impl Chunker {
                             continue;
                         }
 
+                        // Decorated definitions and class properties already cover the function or
+                        // class inside them
+                        if node.parent().is_some_and(|parent| {
+                            matches!(
+                                parent.kind(),
+                                "decorated_definition"
+                                    | "field_definition"
+                                    | "public_field_definition"
+                            )
+                        }) {
+                            continue;
+                        }
+
+                        // The cursor already visits nested items, so methods are captured on
+                        // their own and only need the impl or trait they belong to
                         info!("Kind: {}", kind);
-                        // Capture child chunks
-                        if kind == "impl_item" || kind == "trait_item" {
-                            self.extract_structured_chunks(node);
+
+                        // Haskell functions have one binding per equation, with the type signature
+                        // above them, and Dart functions keep their body beside the signature
+                        let nodes = match self.language {
+                            SupportedParsers::Haskell => {
+                                match haskell_binding(node, &self.source) {
+                                    Some(nodes) => nodes,
+                                    None => continue,
+                                }
+                            },
+                            SupportedParsers::Dart => node
+                                .next_named_sibling()
+                                .filter(|body| body.kind() == "function_body")
+                                .map_or_else(|| vec![node], |body| vec![node, body]),
+                            _ => vec![node],
+                        };
+                        let mut start = nodes[0];
+                        let end = nodes[nodes.len() - 1];
+
+                        let mut content = nodes
+                            .iter()
+                            .map(|node| preprocess_code(node, &self.source, self.comments))
+                            .collect::<Vec<_>>()
+                            .join("\n");
+
+                        if let Some((decorators, first_decorator)) =
+                            leading_decorators(&start, &self.source)
+                        {
+                            content = format!("{decorators}{content}");
+                            start = first_decorator;
+                        }
+
+                        // Doc comments sit above the item rather than inside it
+                        if let Some((docs, first_doc)) =
+                            leading_doc_comments(&start, &self.source, self.comments)
+                        {
+                            content = format!("{docs}{content}");
+                            start = first_doc;
                         }
 
                         // Create the chunk
                         let mut chunk = CodeChunk {
-                            content: preprocess_code(&node, &self.source),
+                            content,
                             node_type: kind.to_string(),
-                            start_line: node.start_position().row,
-                            end_line: node.end_position().row,
+                            start_line: start.start_position().row,
+                            end_line: end.end_position().row,
+                            start_byte: start.start_byte(),
+                            end_byte: end.end_byte(),
                             path: self.path.clone(),
                             language: self.language.to_string(),
+                            parent_id: None,
+                            is_test: is_test_node(node, &self.source),
+                            ..Default::default()
+                        };
+
+                        // Methods sit in the class body, so look past it for the class
+                        let context = match (&self.language, kind) {
+                            (SupportedParsers::Python, _) => {
+                                enclosing(node, &["class_definition"]).or(node.parent())
+                            },
+                            (
+                                SupportedParsers::JavaScript
+                                | SupportedParsers::TypeScript
+                                | SupportedParsers::TSX,
+                                "method_definition"
+                                | "abstract_method_signature"
+                                | "field_definition"
+                                | "public_field_definition",
+                            ) => enclosing(
+                                node,
+                                &["class_declaration", "abstract_class_declaration"],
+                            )
+                            .or(node.parent()),
+                            (SupportedParsers::Php, "method_declaration") => enclosing(
+                                node,
+                                &[
+                                    "class_declaration",
+                                    "interface_declaration",
+                                    "trait_declaration",
+                                    "enum_declaration",
+                                ],
+                            )
+                            .or(node.parent()),
+                            (SupportedParsers::Dart, "method_signature") => enclosing(
+                                node,
+                                &[
+                                    "class_definition",
+                                    "mixin_declaration",
+                                    "extension_declaration",
+                                    "enum_declaration",
+                                ],
+                            )
+                            .or(node.parent()),
+                            // Nested messages sit in their parent's body
+                            (SupportedParsers::Proto, _) => {
+                                enclosing(node, &["message", "service"]).or(node.parent())
+                            },
+                            (SupportedParsers::Markdown, _) => {
+                                enclosing(node, &["section"]).or(node.parent())
+                            },
+                            (SupportedParsers::Ruby, _) => {
+                                enclosing(node, RUBY_SCOPES).or(node.parent())
+                            },
+                            _ => node.parent(),
                         };
 
-                        add_chunk_context(&mut chunk, node, &self.source, node.parent());
+                        if let SupportedParsers::Ruby = self.language {
+                            add_mixin_context(&mut chunk, node, &self.source);
+                        }
+
+                        match self.language {
+                            SupportedParsers::Elixir => {
+                                add_elixir_context(&mut chunk, node, &self.source)
+                            },
+                            SupportedParsers::Hcl => {
+                                add_hcl_context(&mut chunk, node, &self.source)
+                            },
+                            SupportedParsers::Html
+                            | SupportedParsers::Css
+                            | SupportedParsers::Scss
+                                if matches!(kind, "element" | "rule_set") =>
+                            {
+                                add_markup_context(&mut chunk, node, &self.source)
+                            },
+                            SupportedParsers::Yaml
+                            | SupportedParsers::Json
+                            | SupportedParsers::Toml => {
+                                add_config_context(&mut chunk, node, &self.source)
+                            },
+                            _ => add_chunk_context(&mut chunk, node, &self.source, context),
+                        }
+                        add_impl_context(&mut chunk, node, &self.source);
 
-                        chunks.push(chunk);
+                        if let SupportedParsers::Go = self.language {
+                            add_go_context(&mut chunk, node, &self.source);
+                        }
+
+                        if let SupportedParsers::Markdown = self.language {
+                            add_fence_language(&mut chunk, node, &self.source);
+                        }
+
+                        captured.push((node, chunk));
                     }
                 }
             },
@@ -189,7 +559,102 @@ impl Chunker {
             },
         }
 
-        chunks
+        if let SupportedParsers::Rust = self.language {
+            captured.extend(self.use_group(root_node));
+        }
+
+        if let SupportedParsers::Bash = self.language {
+            captured.extend(self.command_sections(root_node));
+        }
+
+        self.link_parents(captured)
+    }
+
+    /// The file's top-level `use` declarations as one chunk
+    fn use_group<'tree>(&self, root_node: Node<'tree>) -> Option<(Node<'tree>, CodeChunk)> {
+        let mut cursor = root_node.walk();
+        let uses: Vec<Node> = root_node
+            .named_children(&mut cursor)
+            .filter(|child| child.kind() == "use_declaration")
+            .collect();
+
+        let (first, last) = (*uses.first()?, *uses.last()?);
+        let content = uses
+            .iter()
+            .map(|node| preprocess_code(node, &self.source, self.comments))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        Some((
+            first,
+            CodeChunk {
+                content,
+                node_type: "use_declarations".to_string(),
+                start_line: first.start_position().row,
+                end_line: last.end_position().row,
+                start_byte: first.start_byte(),
+                end_byte: last.end_byte(),
+                path: self.path.clone(),
+                language: self.language.to_string(),
+                parent_id: None,
+                ..Default::default()
+            },
+        ))
+    }
+
+    /// Runs of top-level shell commands outside functions, split at blank lines, so the steps of
+    /// a build or deploy script are found on their own
+    fn command_sections<'tree>(&self, root_node: Node<'tree>) -> Vec<(Node<'tree>, CodeChunk)> {
+        let mut sections: Vec<Vec<Node>> = Vec::new();
+        let mut cursor = root_node.walk();
+
+        for child in root_node.named_children(&mut cursor) {
+            if child.kind() == "function_definition" {
+                sections.push(Vec::new());
+                continue;
+            }
+
+            match sections.last_mut() {
+                Some(section)
+                    if section.last().is_some_and(|last| {
+                        child.start_position().row <= last.end_position().row + 1
+                    }) =>
+                {
+                    section.push(child)
+                },
+                _ => sections.push(vec![child]),
+            }
+        }
+
+        sections
+            .into_iter()
+            // A comment on its own isn't a step
+            .filter(|section| section.iter().any(|node| node.kind() != "comment"))
+            .map(|section| {
+                let (first, last) = (section[0], section[section.len() - 1]);
+                let content = section
+                    .iter()
+                    .map(|node| preprocess_code(node, &self.source, self.comments))
+                    .collect::<Vec<_>>()
+                    .join("\n");
+
+                (
+                    first,
+                    CodeChunk {
+                        content,
+                        node_type: format!("commands:{}", first.start_position().row + 1),
+                        start_line: first.start_position().row,
+                        end_line: last.end_position().row,
+                        start_byte: first.start_byte(),
+                        end_byte: last.end_byte(),
+                        path: self.path.clone(),
+                        language: self.language.to_string(),
+                        parent_id: None,
+                        ..Default::default()
+                    },
+                )
+            })
+            .collect()
     }
 
     // Extract chunks using a general approach when language-specific queries fail
@@ -219,14 +684,18 @@ impl Chunker {
                         }
 
                         // Create the chunk
-                        let chunk_text = preprocess_code(&node, &self.source);
+                        let chunk_text = preprocess_code(&node, &self.source, self.comments);
                         chunks.push(CodeChunk {
                             content: chunk_text,
                             node_type: node.kind().to_string(),
                             start_line: node.start_position().row,
                             end_line: node.end_position().row,
+                            start_byte: node.start_byte(),
+                            end_byte: node.end_byte(),
                             path: self.path.clone(),
                             language: self.language.to_string(),
+                            parent_id: None,
+                            ..Default::default()
                         });
                     }
                 }
@@ -253,6 +722,13 @@ impl Chunker {
             return chunks;
         }
 
+        // Byte offset each line starts at, with the end of the source after the last one
+        let line_starts: Vec<usize> = iter::once(0)
+            .chain(self.source.match_indices('\n').map(|(i, _)| i + 1))
+            .take(lines.len())
+            .chain(iter::once(self.source.len()))
+            .collect();
+
         let mut section_start = 0;
         let mut blank_line_count = 0;
         let mut in_comment_block = false;
@@ -287,8 +763,12 @@ impl Chunker {
                         node_type: "section".to_string(),
                         start_line: section_start,
                         end_line: i,
+                        start_byte: line_starts[section_start],
+                        end_byte: line_starts[i],
                         path: self.path.clone(),
                         language: self.language.to_string(),
+                        parent_id: None,
+                        ..Default::default()
                     });
                 }
                 section_start = i + 1;
@@ -305,8 +785,12 @@ impl Chunker {
                     node_type: "section".to_string(),
                     start_line: section_start,
                     end_line: lines.len(),
+                    start_byte: line_starts[section_start],
+                    end_byte: self.source.len(),
                     path: self.path.clone(),
                     language: self.language.to_string(),
+                    parent_id: None,
+                    ..Default::default()
                 });
             }
         }
@@ -315,22 +799,286 @@ impl Chunker {
     }
 }
 
-/// Extract chunks from a tree-sitter parse tree
-pub fn extract_chunks(
-    tree: &Tree,
-    source: &str,
-    path: &Path,
-    language: &SupportedParsers,
-    max_chunk_size: Option<usize>,
-    overlap_percentage: Option<usize>,
-) -> Vec<CodeChunk> {
-    Chunker::new(
-        tree,
-        source,
-        path,
-        language,
-        max_chunk_size,
-        overlap_percentage,
-    )
-    .extract_chunks()
+/// One chunk covering a run of adjacent chunks, typed as `merged:` followed by their symbols
+fn merge_run(mut run: Vec<CodeChunk>) -> CodeChunk {
+    if run.len() == 1 {
+        return run.remove(0);
+    }
+
+    let symbols: Vec<&str> = run
+        .iter()
+        .map(|chunk| match chunk.node_type.split_once(':') {
+            Some((_, name)) => name,
+            None => chunk.node_type.as_str(),
+        })
+        .collect();
+    let content: Vec<&str> = run.iter().map(|chunk| chunk.content.as_str()).collect();
+
+    CodeChunk {
+        content: content.join("\n"),
+        node_type: format!("merged:{}", symbols.join(",")),
+        start_line: run[0].start_line,
+        end_line: run[run.len() - 1].end_line,
+        start_byte: run[0].start_byte,
+        end_byte: run[run.len() - 1].end_byte,
+        path: run[0].path.clone(),
+        language: run[0].language.clone(),
+        parent_id: run[0].parent_id,
+        content_hash: String::new(),
+        file_hash: run[0].file_hash.clone(),
+        symbol: None,
+        parent_symbol: run[0].parent_symbol.clone(),
+        is_test: run.iter().all(|chunk| chunk.is_test),
+        license: None,
+        cell: run[0].cell,
+        encoding: run[0].encoding.clone(),
+        package: run[0].package.clone(),
+        author: run[0].author.clone(),
+        last_modified: run[0].last_modified,
+        tags: run[0].tags.clone(),
+    }
+}
+
+/// A compact stand-in for a function too large for one chunk: its context, doc comment,
+/// signature and the first lines of its body, so it can still be found by what it is
+fn signature_chunk(chunk: &CodeChunk) -> Option<CodeChunk> {
+    let (kind, name) = chunk.node_type.split_once(':').unwrap_or((&chunk.node_type, ""));
+
+    if !FUNCTION_KINDS.contains(&kind) {
+        return None;
+    }
+
+    let lines: Vec<&str> = chunk.content.lines().collect();
+
+    // The signature ends where the body opens, skipping the context and doc comment lines
+    let signature_end = lines
+        .iter()
+        .position(|line| {
+            let line = line.trim();
+            !line.starts_with("//")
+                && !line.starts_with('#')
+                && (line.ends_with('{') || line.ends_with(':'))
+        })
+        .unwrap_or(0);
+    let end = (signature_end + 1 + SIGNATURE_BODY_LINES).min(lines.len());
+
+    Some(CodeChunk {
+        content: lines[..end].join("\n"),
+        node_type: format!("signature:{name}"),
+        start_line: chunk.start_line,
+        end_line: chunk.start_line + end,
+        start_byte: chunk.start_byte,
+        end_byte: chunk.end_byte,
+        path: chunk.path.clone(),
+        language: chunk.language.clone(),
+        parent_id: chunk.parent_id,
+        content_hash: String::new(),
+        file_hash: chunk.file_hash.clone(),
+        symbol: chunk.symbol.clone(),
+        parent_symbol: chunk.parent_symbol.clone(),
+        is_test: chunk.is_test,
+        license: None,
+        cell: chunk.cell,
+        encoding: chunk.encoding.clone(),
+        package: chunk.package.clone(),
+        author: chunk.author.clone(),
+        last_modified: chunk.last_modified,
+        tags: chunk.tags.clone(),
+    })
+}
+
+/// The nodes making up a Haskell binding: its type signature, if any, then every equation of a
+/// function defined by pattern matching. `None` when `node` continues an earlier equation, which
+/// the first one's chunk already covers
+fn haskell_binding<'tree>(node: Node<'tree>, source: &str) -> Option<Vec<Node<'tree>>> {
+    if !matches!(node.kind(), "function" | "bind") {
+        return Some(vec![node]);
+    }
+
+    let name = |node: Node| {
+        node.child_by_field_name("name").and_then(|name| source.get(name.byte_range()))
+    };
+    let binding_name = name(node);
+
+    let previous = node.prev_named_sibling();
+    if previous.is_some_and(|previous| {
+        matches!(previous.kind(), "function" | "bind") && name(previous) == binding_name
+    }) {
+        return None;
+    }
+
+    let mut nodes = Vec::new();
+    nodes.extend(
+        previous
+            .filter(|previous| previous.kind() == "signature" && name(*previous) == binding_name),
+    );
+    nodes.push(node);
+
+    let mut current = node;
+    while let Some(next) = current.next_named_sibling() {
+        if !matches!(next.kind(), "function" | "bind") || name(next) != binding_name {
+            break;
+        }
+        nodes.push(next);
+        current = next;
+    }
+
+    Some(nodes)
+}
+
+/// Decorators of a TypeScript class member, which are parsed as the siblings in front of it
+/// rather than as its children. Returns their text and the first of them
+fn leading_decorators<'tree>(node: &Node<'tree>, source: &str) -> Option<(String, Node<'tree>)> {
+    let mut decorators = Vec::new();
+    let mut current = *node;
+
+    while let Some(previous) = current.prev_sibling().filter(|prev| prev.kind() == "decorator") {
+        decorators.push(format!("{}\n", &source[previous.byte_range()]));
+        current = previous;
+    }
+
+    decorators.reverse();
+    (!decorators.is_empty()).then(|| (decorators.concat(), current))
+}
+
+#[cfg(test)]
+mod tests {
+    use tree_sitter::Parser;
+
+    use super::*;
+
+    fn chunks(source: &str, language: SupportedParsers, path: &str) -> Vec<CodeChunk> {
+        let mut parser = Parser::new();
+        parser.set_language(&language.language()).unwrap();
+        let tree = parser.parse(source, None).unwrap();
+
+        Chunker::new(
+            &tree,
+            source,
+            Path::new(path),
+            &language,
+            ChunkerConfig::default(),
+        )
+        .extract_chunks()
+    }
+
+    const NEST_SERVICE: &str = r#"
+import { Injectable, Get } from '@nestjs/common';
+
+export interface User {
+    id: number;
+    name: string;
+    email: string;
+    roles: Role[];
+}
+
+export type UserId = User['id'] | string;
+
+export enum Role {
+    Admin = 'admin',
+    Editor = 'editor',
+    Viewer = 'viewer',
+}
+
+@Injectable()
+export class UsersController {
+    private readonly users: User[] = [];
+
+    @Get(':id')
+    findOne(id: UserId): User | undefined {
+        return this.users.find((user) => user.id === id);
+    }
+
+    handleError = (error: Error): void => {
+        console.error(error);
+        throw error;
+    };
+}
+"#;
+
+    const REACT_COMPONENT: &str = r#"
+import { useState } from 'react';
+
+interface ButtonProps {
+    label: string;
+    disabled?: boolean;
+    onClick: () => void;
+    variant: Variant;
+}
+
+type Variant = 'primary' | 'secondary' | 'danger' | 'link';
+
+export function Counter({ label }: ButtonProps) {
+    const [count, setCount] = useState(0);
+
+    return (
+        <button onClick={() => setCount(count + 1)}>
+            {label}: {count}
+        </button>
+    );
+}
+"#;
+
+    fn chunk<'a>(chunks: &'a [CodeChunk], node_type: &str) -> Option<&'a CodeChunk> {
+        chunks.iter().find(|chunk| chunk.node_type == node_type)
+    }
+
+    #[test]
+    fn typescript_declarations_become_chunks() {
+        let chunks = chunks(
+            NEST_SERVICE,
+            SupportedParsers::TypeScript,
+            "users.controller.ts",
+        );
+
+        for node_type in [
+            "interface_declaration:User",
+            "type_alias_declaration:UserId",
+            "enum_declaration:Role",
+            "class_declaration:UsersController",
+        ] {
+            assert!(chunk(&chunks, node_type).is_some(), "no {node_type} chunk");
+        }
+    }
+
+    #[test]
+    fn decorated_members_keep_their_decorators() {
+        let chunks = chunks(
+            NEST_SERVICE,
+            SupportedParsers::TypeScript,
+            "users.controller.ts",
+        );
+        let method = chunk(&chunks, "method_definition:UsersController.findOne").unwrap();
+
+        assert!(method.content.contains("@Get(':id')\nfindOne(id: UserId)"));
+        assert_eq!(method.parent_symbol.as_deref(), Some("UsersController"));
+    }
+
+    #[test]
+    fn class_property_methods_become_chunks() {
+        let chunks = chunks(
+            NEST_SERVICE,
+            SupportedParsers::TypeScript,
+            "users.controller.ts",
+        );
+        let property = chunk(
+            &chunks,
+            "public_field_definition:UsersController.handleError",
+        );
+
+        assert!(property.is_some_and(|property| property.content.contains("console.error")));
+    }
+
+    #[test]
+    fn tsx_components_and_their_props_become_chunks() {
+        let chunks = chunks(REACT_COMPONENT, SupportedParsers::TSX, "counter.tsx");
+
+        for node_type in [
+            "interface_declaration:ButtonProps",
+            "type_alias_declaration:Variant",
+            "function_declaration:Counter",
+        ] {
+            assert!(chunk(&chunks, node_type).is_some(), "no {node_type} chunk");
+        }
+    }
 }
@@ -0,0 +1,167 @@
+use serde::Deserialize;
+use tree_sitter::{Node, Parser};
+
+use crate::{prelude::*, utils::parsers::SupportedParsers};
+
+/// One block of a single-file component, like a Svelte `<script lang="ts">`, or one notebook cell
+pub struct ComponentBlock {
+    /// `script`, `style` or `markup`, or the cell type
+    pub role: &'static str,
+    pub language: SupportedParsers,
+    /// The first line of the block in the component
+    pub start_line: usize,
+    /// The whole component with everything outside the block blanked out, so positions in the
+    /// block's syntax tree are positions in the component file. A cell's own source
+    pub source: String,
+    pub cell: Option<usize>,
+}
+
+impl ComponentBlock {
+    /// Prefix for the types of the block's chunks, unique within the component
+    pub fn label(&self) -> String {
+        match self.cell {
+            Some(cell) => f!("cell{cell}"),
+            None => f!("{}:{}", self.role, self.start_line + 1),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct Notebook {
+    cells: Vec<Cell>,
+}
+
+#[derive(Deserialize)]
+struct Cell {
+    cell_type: String,
+    source: CellSource,
+}
+
+/// nbformat allows a cell's source as one string or as a list of lines
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum CellSource {
+    Text(String),
+    Lines(Vec<String>),
+}
+
+/// The code and markdown cells of a Jupyter notebook, skipping empty and raw cells
+pub fn notebook_cells(content: &str) -> Result<Vec<ComponentBlock>> {
+    let notebook: Notebook = serde_json::from_str(content)?;
+
+    Ok(notebook
+        .cells
+        .into_iter()
+        .enumerate()
+        .filter_map(|(index, cell)| {
+            let (role, language) = match cell.cell_type.as_str() {
+                "code" => ("code", SupportedParsers::Python),
+                "markdown" => ("markdown", SupportedParsers::Markdown),
+                _ => return None,
+            };
+
+            let source = match cell.source {
+                CellSource::Text(text) => text,
+                CellSource::Lines(lines) => lines.concat(),
+            };
+
+            (!source.trim().is_empty()).then_some(ComponentBlock {
+                role,
+                language,
+                start_line: 0,
+                source,
+                cell: Some(index),
+            })
+        })
+        .collect())
+}
+
+/// Split a component into its script and style blocks and the markup around them
+pub fn component_blocks(source: &str) -> Vec<ComponentBlock> {
+    let mut parser = Parser::new();
+    if parser.set_language(&SupportedParsers::Html.language()).is_err() {
+        return Vec::new();
+    }
+    let Some(tree) = parser.parse(source, None) else {
+        return Vec::new();
+    };
+
+    let root_node = tree.root_node();
+    let mut blocks = Vec::new();
+    let mut embedded = Vec::new();
+    let mut cursor = root_node.walk();
+
+    for element in root_node.named_children(&mut cursor) {
+        let (role, language) = match (element.kind(), lang_attribute(element, source)) {
+            ("script_element", Some("ts" | "typescript")) => {
+                ("script", SupportedParsers::TypeScript)
+            },
+            ("script_element", _) => ("script", SupportedParsers::JavaScript),
+            ("style_element", Some("scss")) => ("style", SupportedParsers::Scss),
+            ("style_element", _) => ("style", SupportedParsers::Css),
+            _ => continue,
+        };
+
+        embedded.push(element.byte_range());
+
+        let mut element_cursor = element.walk();
+        let Some(raw_text) = element
+            .named_children(&mut element_cursor)
+            .find(|child| child.kind() == "raw_text")
+        else {
+            continue;
+        };
+
+        blocks.push(ComponentBlock {
+            role,
+            language,
+            start_line: raw_text.start_position().row,
+            source: mask(source, |byte| raw_text.byte_range().contains(&byte)),
+            cell: None,
+        });
+    }
+
+    blocks.push(ComponentBlock {
+        role: "markup",
+        language: SupportedParsers::Html,
+        start_line: 0,
+        source: mask(source, |byte| {
+            !embedded.iter().any(|range| range.contains(&byte))
+        }),
+        cell: None,
+    });
+
+    blocks
+}
+
+/// The `lang` attribute of a script or style element
+fn lang_attribute<'a>(element: Node, source: &'a str) -> Option<&'a str> {
+    let start_tag = element.named_child(0)?;
+    let mut cursor = start_tag.walk();
+
+    start_tag
+        .named_children(&mut cursor)
+        .filter(|child| child.kind() == "attribute")
+        .find(|attribute| {
+            attribute
+                .named_child(0)
+                .and_then(|name| source.get(name.byte_range()))
+                .is_some_and(|name| name == "lang")
+        })
+        .and_then(|attribute| attribute.named_child(1))
+        .and_then(|value| source.get(value.byte_range()))
+        .map(|value| value.trim_matches(['"', '\'']))
+}
+
+/// `source` with every character outside `keep` replaced by spaces of the same byte length,
+/// keeping line breaks so lines and bytes still line up
+pub fn mask(source: &str, keep: impl Fn(usize) -> bool) -> String {
+    source
+        .char_indices()
+        .map(|(byte, c)| match c {
+            '\n' => "\n".to_string(),
+            c if keep(byte) => c.to_string(),
+            c => " ".repeat(c.len_utf8()),
+        })
+        .collect()
+}
@@ -0,0 +1,151 @@
+use std::collections::HashSet;
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use tree_sitter::Node;
+
+/// Top-level node kinds holding imports, across the supported languages
+const IMPORT_KINDS: &[&str] = &[
+    "use_declaration",
+    "import_statement",
+    "import_from_statement",
+    "future_import_statement",
+    "import_declaration",
+    "namespace_use_declaration",
+];
+
+/// Which of the file's imports are prepended to its chunks
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ImportContext {
+    None,
+    /// Only imports whose names appear in the chunk, plus wildcard imports
+    #[default]
+    Referenced,
+    All,
+}
+
+/// One import statement and the names it brings into scope
+#[derive(Debug, Clone)]
+pub struct Import {
+    text: String,
+    names: Vec<String>,
+    wildcard: bool,
+}
+
+/// The file's top-level imports. Go import blocks are split into one import per spec
+pub fn collect_imports(root_node: Node, source: &str) -> Vec<Import> {
+    let mut imports = Vec::new();
+    let mut cursor = root_node.walk();
+
+    for node in root_node.named_children(&mut cursor) {
+        if !IMPORT_KINDS.contains(&node.kind()) {
+            continue;
+        }
+
+        let specs = go_import_specs(node);
+
+        if specs.is_empty() {
+            imports.push(Import::new(node, text(node, source).to_string(), source));
+        } else {
+            for spec in specs {
+                imports.push(Import::new(
+                    spec,
+                    format!("import {}", text(spec, source)),
+                    source,
+                ));
+            }
+        }
+    }
+
+    imports
+}
+
+/// The imports to prepend to `content`, one per line, or `None` when there are none
+pub fn imports_for(imports: &[Import], content: &str, context: ImportContext) -> Option<String> {
+    let words: HashSet<&str> = content
+        .split(|c: char| !c.is_alphanumeric() && c != '_')
+        .filter(|word| !word.is_empty())
+        .collect();
+
+    let selected: Vec<&str> = imports
+        .iter()
+        .filter(|import| match context {
+            ImportContext::None => false,
+            ImportContext::Referenced => {
+                import.wildcard || import.names.iter().any(|name| words.contains(name.as_str()))
+            },
+            ImportContext::All => true,
+        })
+        .map(|import| import.text.as_str())
+        .collect();
+
+    (!selected.is_empty()).then(|| selected.join("\n"))
+}
+
+impl Import {
+    fn new(node: Node, text: String, source: &str) -> Self {
+        let mut names = Vec::new();
+        let mut wildcard = false;
+        collect_names(node, source, &mut names, &mut wildcard);
+
+        Self {
+            text,
+            names,
+            wildcard,
+        }
+    }
+}
+
+/// Identifiers bound by an import. A Go import without an alias binds the last segment of its path
+fn collect_names(node: Node, source: &str, names: &mut Vec<String>, wildcard: &mut bool) {
+    match node.kind() {
+        "use_wildcard" | "wildcard_import" => *wildcard = true,
+        "interpreted_string_literal"
+            if node.parent().is_some_and(|spec| {
+                spec.kind() == "import_spec" && spec.child_by_field_name("name").is_none()
+            }) =>
+        {
+            let path = text(node, source).trim_matches('"');
+            names.extend(path.rsplit('/').next().map(str::to_string));
+        },
+        // PHP names are plain `name` nodes
+        kind if (kind.ends_with("identifier") || kind == "name")
+            && node.named_child_count() == 0 =>
+        {
+            names.push(text(node, source).to_string());
+        },
+        _ => {},
+    }
+
+    let mut cursor = node.walk();
+    for child in node.named_children(&mut cursor) {
+        collect_names(child, source, names, wildcard);
+    }
+}
+
+fn go_import_specs(node: Node) -> Vec<Node> {
+    let mut specs = Vec::new();
+    let mut cursor = node.walk();
+
+    for child in node.named_children(&mut cursor) {
+        match child.kind() {
+            "import_spec" => specs.push(child),
+            "import_spec_list" => {
+                let mut list_cursor = child.walk();
+                specs.extend(
+                    child
+                        .named_children(&mut list_cursor)
+                        .filter(|spec| spec.kind() == "import_spec"),
+                );
+            },
+            _ => {},
+        }
+    }
+
+    specs
+}
+
+fn text<'a>(node: Node, source: &'a str) -> &'a str {
+    source.get(node.byte_range()).unwrap_or("")
+}
@@ -0,0 +1,35 @@
+use std::ops::Range;
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use tree_sitter::Node;
+
+/// Words that mark a comment block at the top of a file as a license header
+const LICENSE_MARKERS: &[&str] =
+    &["copyright", "license", "licensed under", "spdx-license-identifier"];
+
+/// What happens to license headers at the top of files
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LicenseHeaders {
+    /// Leave them out of chunks, so identical boilerplate doesn't skew embeddings
+    #[default]
+    Strip,
+    /// Leave them out of chunks, but keep the text in the file chunks' metadata
+    Metadata,
+    Keep,
+}
+
+/// Byte range of the comments opening the file, when they hold a license
+pub fn license_header(root_node: Node, source: &str) -> Option<Range<usize>> {
+    let mut cursor = root_node.walk();
+    let comments: Vec<Node> = root_node
+        .children(&mut cursor)
+        .take_while(|child| child.kind().contains("comment"))
+        .collect();
+
+    let range = comments.first()?.start_byte()..comments.last()?.end_byte();
+    let text = source.get(range.clone())?.to_lowercase();
+
+    LICENSE_MARKERS.iter().any(|marker| text.contains(marker)).then_some(range)
+}
@@ -1,8 +1,23 @@
 mod chunker;
+mod components;
+mod imports;
 mod languages;
+mod license;
 mod preprocess;
+mod queries;
+mod sizing;
 mod splitter;
+mod strategy;
+mod test_code;
 mod types;
 
-pub use chunker::extract_chunks;
-pub use types::CodeChunk;
+pub use chunker::ChunkerConfig;
+pub use components::{component_blocks, notebook_cells};
+pub use imports::ImportContext;
+pub use license::LicenseHeaders;
+pub use preprocess::CommentPolicy;
+pub use queries::Queries;
+pub use sizing::ChunkSizer;
+pub use strategy::{ChunkStrategy, ChunkStrategyKind};
+pub use test_code::is_test_path;
+pub use types::{CodeChunk, sha256_hex};
@@ -1,26 +1,108 @@
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
 use tree_sitter::{Node, TreeCursor};
 
+/// Which comments are kept in chunk content
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CommentPolicy {
+    /// Keep doc comments and docstrings, drop everything else
+    #[default]
+    KeepDocComments,
+    KeepAll,
+    StripAll,
+}
+
+impl CommentPolicy {
+    fn keeps(&self, node: Node, source: &str) -> bool {
+        match self {
+            Self::KeepAll => true,
+            Self::StripAll => false,
+            Self::KeepDocComments => is_doc_comment(node, source),
+        }
+    }
+}
+
 /// Preprocesses code to reduce unnecessary content while preserving semantics
-pub fn preprocess_code(node: &Node, source: &str) -> String {
+pub fn preprocess_code(node: &Node, source: &str, comments: CommentPolicy) -> String {
     let mut result = String::new();
     let mut cursor = node.walk();
+    let mut last_end = node.start_byte();
 
-    preprocess_node(&mut cursor, source, &mut result);
+    preprocess_node(&mut cursor, source, comments, &mut last_end, &mut result);
 
     result
 }
 
-fn preprocess_node(cursor: &mut TreeCursor, source: &str, result: &mut String) {
+/// Doc comments written directly above `node`, which tree-sitter parses as its siblings.
+/// Returns the comment text and the first comment node
+pub fn leading_doc_comments<'tree>(
+    node: &Node<'tree>,
+    source: &str,
+    comments: CommentPolicy,
+) -> Option<(String, Node<'tree>)> {
+    let mut leading = Vec::new();
+    let mut row = node.start_position().row;
+    let mut current = *node;
+    let mut first = None;
+
+    while let Some(previous) = current.prev_sibling() {
+        // Rust attributes and Dart annotations go between an item and its docs
+        if matches!(
+            previous.kind(),
+            "attribute_item" | "annotation" | "marker_annotation"
+        ) {
+            row = previous.start_position().row;
+            current = previous;
+            continue;
+        }
+
+        // Stop at anything that isn't a comment touching the block below it
+        if !previous.kind().contains("comment") || previous.end_position().row + 1 < row {
+            break;
+        }
+
+        if !comments.keeps(previous, source) {
+            break;
+        }
+
+        leading.push(comment_text(previous, source));
+        row = previous.start_position().row;
+        current = previous;
+        first = Some(previous);
+    }
+
+    leading.reverse();
+    first.map(|first| (leading.concat(), first))
+}
+
+fn preprocess_node(
+    cursor: &mut TreeCursor,
+    source: &str,
+    comments: CommentPolicy,
+    last_end: &mut usize,
+    result: &mut String,
+) {
     let node = cursor.node();
 
-    // Skip comment nodes entirely
+    // Comments are kept whole, their children are only markers
     if node.kind().contains("comment") {
+        if comments.keeps(node, source) {
+            push_gap(source, last_end, node, result);
+            result.push_str(node_text(node, source));
+        }
+        return;
+    }
+
+    // Docstrings are plain string statements, so they're only dropped when stripping everything
+    if comments == CommentPolicy::StripAll && is_docstring(node) {
         return;
     }
 
     // Process node based on its type
     if node.named_child_count() == 0 {
         // Terminal node - include its text with minimal whitespace
+        push_gap(source, last_end, node, result);
         let node_text = node_text(node, source);
 
         // Preserve certain whitespace but collapse excessive spaces
@@ -29,10 +111,10 @@ fn preprocess_node(cursor: &mut TreeCursor, source: &str, result: &mut String) {
     } else {
         // Non-terminal node - process its children
         if cursor.goto_first_child() {
-            preprocess_node(cursor, source, result);
+            preprocess_node(cursor, source, comments, last_end, result);
 
             while cursor.goto_next_sibling() {
-                preprocess_node(cursor, source, result);
+                preprocess_node(cursor, source, comments, last_end, result);
             }
 
             cursor.goto_parent();
@@ -40,6 +122,109 @@ fn preprocess_node(cursor: &mut TreeCursor, source: &str, result: &mut String) {
     }
 }
 
+/// Keep the whitespace between the last emitted token and `node`, so tokens don't run together
+/// and lines stay lines. A gap holding dropped comments collapses to a single separator
+fn push_gap(source: &str, last_end: &mut usize, node: Node, result: &mut String) {
+    let gap = source.get(*last_end..node.start_byte()).unwrap_or("");
+
+    if gap.chars().all(char::is_whitespace) {
+        // Normalizing drops leading spaces, but tokens still need one between them
+        match normalize_whitespace(gap) {
+            normalized if normalized.is_empty() && !gap.is_empty() => result.push(' '),
+            normalized => result.push_str(&normalized),
+        }
+    } else if gap.contains('\n') {
+        result.push('\n');
+    } else {
+        result.push(' ');
+    }
+
+    *last_end = node.end_byte();
+}
+
+/// Whether a comment node documents the item after it, rather than explaining the code around it
+fn is_doc_comment(node: Node, source: &str) -> bool {
+    let text = node_text(node, source);
+
+    // Rust `///`, `//!`, `/** */` and `/*! */`, JSDoc/TSDoc `/** */`, Haddock `-- |` and
+    // roxygen `#'`
+    let marked = (text.starts_with("///") && !text.starts_with("////"))
+        || text.starts_with("//!")
+        || (text.starts_with("/**") && !text.starts_with("/***") && text != "/**/")
+        || text.starts_with("/*!")
+        || text.starts_with("-- |")
+        || text.starts_with("{- |")
+        || text.starts_with("#'");
+
+    marked || is_attached_doc_comment(node)
+}
+
+/// Go and Ruby have no doc comment syntax, a comment directly above a declaration documents it
+fn is_attached_doc_comment(node: Node) -> bool {
+    let mut current = node;
+
+    while let Some(next) = current.next_sibling() {
+        if next.start_position().row > current.end_position().row + 1 {
+            return false;
+        }
+
+        match next.kind() {
+            kind if kind.contains("comment") => current = next,
+            "function_declaration"
+            | "method_declaration"
+            | "type_declaration"
+            | "const_declaration"
+            | "var_declaration"
+            | "package_clause"
+            | "method"
+            | "singleton_method"
+            | "class"
+            | "module" => return true,
+            _ => return false,
+        }
+    }
+
+    false
+}
+
+/// A Python docstring: a bare string as the first statement of a module, class or function
+fn is_docstring(node: Node) -> bool {
+    if node.kind() != "expression_statement"
+        || node.named_child(0).is_none_or(|child| child.kind() != "string")
+    {
+        return false;
+    }
+
+    let Some(parent) = node.parent() else {
+        return false;
+    };
+
+    // Comments, like a shebang, can come before the docstring
+    let first_statement = (0..parent.named_child_count())
+        .filter_map(|i| parent.named_child(i))
+        .find(|child| !child.kind().contains("comment"))
+        .is_some_and(|first| first.id() == node.id());
+    let documented = match parent.kind() {
+        "module" => true,
+        "block" => parent.parent().is_some_and(|owner| {
+            matches!(owner.kind(), "function_definition" | "class_definition")
+        }),
+        _ => false,
+    };
+
+    first_statement && documented
+}
+
+/// Comment text ending in a newline, so it doesn't run into the code after it
+fn comment_text(node: Node, source: &str) -> String {
+    let text = node_text(node, source);
+
+    match text.ends_with('\n') {
+        true => text.to_string(),
+        false => format!("{text}\n"),
+    }
+}
+
 /// Extracts text for a node from source, handling byte offsets correctly
 fn node_text<'a>(node: Node, source: &'a str) -> &'a str {
     let start_byte = node.start_byte();
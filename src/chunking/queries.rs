@@ -0,0 +1,122 @@
+use std::{collections::HashMap, fs, path::Path, sync::Arc};
+
+use tracing::info;
+
+use crate::{prelude::*, utils::parsers::SupportedParsers};
+
+/// Queries built into the binary, keyed by the file name an override replaces them with
+const BUILTIN: &[(&str, &str)] = &[
+    ("rust", include_str!("queries/rust.scm")),
+    ("python", include_str!("queries/python.scm")),
+    (
+        "python-statements",
+        include_str!("queries/python-statements.scm"),
+    ),
+    ("javascript", include_str!("queries/javascript.scm")),
+    ("typescript", include_str!("queries/typescript.scm")),
+    ("go", include_str!("queries/go.scm")),
+    ("ruby", include_str!("queries/ruby.scm")),
+    ("php", include_str!("queries/php.scm")),
+    ("elixir", include_str!("queries/elixir.scm")),
+    ("haskell", include_str!("queries/haskell.scm")),
+    ("dart", include_str!("queries/dart.scm")),
+    ("bash", include_str!("queries/bash.scm")),
+    ("sql", include_str!("queries/sql.scm")),
+    ("hcl", include_str!("queries/hcl.scm")),
+    ("proto", include_str!("queries/proto.scm")),
+    ("graphql", include_str!("queries/graphql.scm")),
+    ("yaml", include_str!("queries/yaml.scm")),
+    ("json", include_str!("queries/json.scm")),
+    ("toml", include_str!("queries/toml.scm")),
+    ("markdown", include_str!("queries/markdown.scm")),
+    ("r", include_str!("queries/r.scm")),
+    ("make", include_str!("queries/make.scm")),
+    ("html", include_str!("queries/html.scm")),
+    ("css", include_str!("queries/css.scm")),
+    ("scss", include_str!("queries/scss.scm")),
+];
+
+/// Tree-sitter queries choosing which nodes become chunks, with any `<name>.scm` files from
+/// the queries directory used instead of the built-in ones
+#[derive(Debug, Clone, Default)]
+pub struct Queries {
+    overrides: Arc<HashMap<String, String>>,
+}
+
+impl Queries {
+    /// Load overrides from `dir`, or from code-sherpa/queries in the user config directory when
+    /// `dir` is `None`. A missing default directory is not an error
+    pub fn load(dir: Option<&Path>) -> Result<Self> {
+        let default_dir = dirs::config_dir().map(|dir| dir.join("code-sherpa").join("queries"));
+
+        let dir = match (dir, &default_dir) {
+            (Some(dir), _) => dir,
+            (None, Some(dir)) if dir.is_dir() => dir.as_path(),
+            (None, _) => return Ok(Self::default()),
+        };
+
+        let mut overrides = HashMap::new();
+
+        for (name, _) in BUILTIN {
+            let path = dir.join(f!("{name}.scm"));
+
+            if path.is_file() {
+                info!("Using chunking query {}", path.display());
+                overrides.insert(name.to_string(), fs::read_to_string(&path)?);
+            }
+        }
+
+        Ok(Self {
+            overrides: Arc::new(overrides),
+        })
+    }
+
+    /// The query for `language`, with control flow statements when `statements` is set
+    pub fn get(&self, language: &SupportedParsers, statements: bool) -> String {
+        let name = match language {
+            SupportedParsers::Rust => "rust",
+            SupportedParsers::Python | SupportedParsers::Jupyter => "python",
+            SupportedParsers::JavaScript => "javascript",
+            SupportedParsers::TypeScript | SupportedParsers::TSX => "typescript",
+            SupportedParsers::Go => "go",
+            SupportedParsers::Ruby => "ruby",
+            SupportedParsers::Php => "php",
+            SupportedParsers::Elixir => "elixir",
+            SupportedParsers::Haskell => "haskell",
+            SupportedParsers::Dart => "dart",
+            SupportedParsers::Bash => "bash",
+            SupportedParsers::Sql => "sql",
+            SupportedParsers::Hcl => "hcl",
+            SupportedParsers::Proto => "proto",
+            SupportedParsers::GraphQL => "graphql",
+            SupportedParsers::Yaml => "yaml",
+            SupportedParsers::Json => "json",
+            SupportedParsers::Toml => "toml",
+            SupportedParsers::Markdown => "markdown",
+            SupportedParsers::R => "r",
+            SupportedParsers::Make => "make",
+            SupportedParsers::Html | SupportedParsers::Svelte => "html",
+            SupportedParsers::Css => "css",
+            SupportedParsers::Scss => "scss",
+        };
+
+        let mut query = self.source(name).to_string();
+
+        if statements && name == "python" {
+            query.push('\n');
+            query.push_str(self.source("python-statements"));
+        }
+
+        query
+    }
+
+    fn source(&self, name: &str) -> &str {
+        self.overrides.get(name).map(String::as_str).unwrap_or_else(|| {
+            BUILTIN
+                .iter()
+                .find(|(builtin, _)| *builtin == name)
+                .map(|(_, source)| *source)
+                .unwrap_or_default()
+        })
+    }
+}
@@ -0,0 +1,4 @@
+; Top-level commands between functions are grouped into sections by the chunker
+[
+  (function_definition) @function
+]
@@ -0,0 +1,5 @@
+[
+  (rule_set) @rule
+  (media_statement) @media
+  (keyframes_statement) @keyframes
+]
@@ -0,0 +1,9 @@
+; Functions and methods are a signature followed by a sibling body, the chunker joins the two
+[
+  (class_definition) @class
+  (mixin_declaration) @mixin
+  (extension_declaration) @extension
+  (enum_declaration) @enum
+  (program (function_signature) @function)
+  (method_signature) @method
+]
@@ -0,0 +1,6 @@
+; Everything in Elixir is a call, so definitions are told apart by the macro they call
+((call target: (identifier) @_keyword) @module
+  (#any-of? @_keyword "defmodule" "defprotocol" "defimpl"))
+
+((call target: (identifier) @_keyword) @function
+  (#any-of? @_keyword "def" "defp" "defmacro" "defmacrop" "defguard" "defguardp" "defdelegate"))
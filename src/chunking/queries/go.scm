@@ -0,0 +1,8 @@
+; Only top-level const and var blocks, locals are noise
+[
+  (function_declaration) @function
+  (method_declaration) @method
+  (type_declaration) @type
+  (source_file (const_declaration) @const)
+  (source_file (var_declaration) @var)
+]
@@ -0,0 +1,10 @@
+; Schema types, and the queries, mutations and fragments written against them
+[
+  (object_type_definition) @type
+  (input_object_type_definition) @input
+  (interface_type_definition) @interface
+  (enum_type_definition) @enum
+  (union_type_definition) @union
+  (operation_definition) @operation
+  (fragment_definition) @fragment
+]
@@ -0,0 +1,10 @@
+; Only top-level bindings, the chunker gathers each function's equations and signature
+[
+  (header) @module
+  (declarations (function) @function)
+  (declarations (bind) @bind)
+  (declarations (data_type) @data)
+  (declarations (newtype) @newtype)
+  (declarations (class) @class)
+  (declarations (instance) @instance)
+]
@@ -0,0 +1,4 @@
+; Top-level blocks: resource, data, module, variable, output, provider, locals
+[
+  (config_file (body (block) @block))
+]
@@ -0,0 +1,8 @@
+; Templates and landmark sections rather than every element
+((element (start_tag (tag_name) @_tag)) @element
+  (#any-of? @_tag "template" "section" "main" "header" "footer" "nav" "form" "article" "aside" "dialog"))
+
+[
+  (script_element) @script
+  (style_element) @style
+]
@@ -0,0 +1,9 @@
+[
+  (function_declaration) @function
+  (method_definition) @method
+  (class_declaration) @class
+  (arrow_function) @arrow_function
+  (export_statement) @export
+  (lexical_declaration) @declaration
+  (field_definition value: [(arrow_function) (function_expression)]) @property_method
+]
@@ -0,0 +1,3 @@
+[
+  (document (object (pair) @pair))
+]
@@ -0,0 +1,5 @@
+; Each target's recipe, and multi-line variable definitions
+[
+  (rule) @rule
+  (define_directive) @define
+]
@@ -0,0 +1,5 @@
+; Sections nest by heading level, so each one's parent is the section above it
+[
+  (section) @section
+  (fenced_code_block) @code
+]
@@ -0,0 +1,8 @@
+[
+  (class_declaration) @class
+  (interface_declaration) @interface
+  (trait_declaration) @trait
+  (enum_declaration) @enum
+  (function_definition) @function
+  (method_declaration) @method
+]
@@ -0,0 +1,6 @@
+[
+  (message) @message
+  (enum) @enum
+  (service) @service
+  (rpc) @rpc
+]
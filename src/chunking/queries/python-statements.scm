@@ -0,0 +1,6 @@
+; Control flow blocks, only chunked with --statement-chunks
+[
+  (if_statement) @if
+  (for_statement) @for
+  (while_statement) @while
+]
@@ -0,0 +1,7 @@
+; `async def` is a function_definition too
+[
+  (function_definition) @function
+  (class_definition) @class
+  (decorated_definition) @decorated
+  (module (expression_statement (assignment) @assignment))
+]
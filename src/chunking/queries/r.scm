@@ -0,0 +1,11 @@
+; Functions are assigned like any other value
+((binary_operator
+  lhs: (identifier)
+  rhs: (function_definition)) @function)
+
+[
+  (program (for_statement) @loop)
+  (program (while_statement) @loop)
+  (program (if_statement) @if)
+  (program (braced_expression) @block)
+]
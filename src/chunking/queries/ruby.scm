@@ -0,0 +1,8 @@
+; `def self.name` is a singleton_method, `class << self` a singleton_class
+[
+  (module) @module
+  (class) @class
+  (singleton_class) @singleton_class
+  (method) @method
+  (singleton_method) @singleton_method
+]
@@ -0,0 +1,15 @@
+; Nodes that become chunks. Patterns sit in one alternation so each matches on its own rather
+; than as a run of siblings
+[
+  (function_item) @function
+  (function_signature_item) @function_signature
+  (struct_item) @struct
+  (impl_item) @impl
+  (trait_item) @trait
+  (enum_item) @enum
+  (mod_item) @mod
+  (macro_definition) @macro
+  (const_item) @const
+  (static_item) @static
+  (type_item) @type
+]
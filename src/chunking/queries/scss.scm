@@ -0,0 +1,8 @@
+; Nested rules are captured too, and linked to the rule they sit in
+[
+  (rule_set) @rule
+  (media_statement) @media
+  (keyframes_statement) @keyframes
+  (mixin_statement) @mixin
+  (function_statement) @function
+]
@@ -0,0 +1,11 @@
+; Definitions from migrations and schema files, and the queries that use them
+[
+  (create_table) @table
+  (create_view) @view
+  (create_function) @function
+  (create_index) @index
+  (statement (select)) @query
+  (statement (insert)) @query
+  (statement (update)) @query
+  (statement (delete)) @query
+]
@@ -0,0 +1,6 @@
+; `[section]` and `[[array]]` tables, and keys set before the first table
+[
+  (table) @table
+  (table_array_element) @table
+  (document (pair) @pair)
+]
@@ -0,0 +1,17 @@
+; Shared by .ts and .tsx. Class decorators are children of the class, member decorators are
+; picked up by the chunker from the siblings in front of the member
+[
+  (function_declaration) @function
+  (method_definition) @method
+  (abstract_method_signature) @method_signature
+  (class_declaration) @class
+  (abstract_class_declaration) @class
+  (interface_declaration) @interface
+  (type_alias_declaration) @type_alias
+  (enum_declaration) @enum
+  (arrow_function) @arrow_function
+  (export_statement) @export
+  (lexical_declaration) @declaration
+  (public_field_definition
+    value: [(arrow_function) (function_expression)]) @property_method
+]
@@ -0,0 +1,5 @@
+; Each document of a multi-document file (one Kubernetes object each) and its top-level keys
+[
+  (stream (document) @document)
+  (document (block_node (block_mapping (block_mapping_pair) @pair)))
+]
@@ -0,0 +1,80 @@
+use crate::embedding::Tokenizer;
+
+/// How chunk sizes and overlap are measured
+#[derive(Debug, Clone, Default)]
+pub enum ChunkSizer {
+    #[default]
+    Bytes,
+    /// Measure in the embedding model's tokens, so chunks fit its context window
+    Tokens(Tokenizer),
+}
+
+impl ChunkSizer {
+    pub fn size(&self, text: &str) -> usize {
+        match self {
+            Self::Bytes => text.len(),
+            Self::Tokens(tokenizer) => tokenizer.count(text),
+        }
+    }
+
+    /// The furthest character boundary `end` where `content[start..end]` fits in `limit`
+    pub fn fit_end(&self, content: &str, start: usize, limit: usize) -> usize {
+        match self {
+            Self::Bytes => floor_char_boundary(content, start + limit),
+            Self::Tokens(_) => {
+                let (mut low, mut high) = (start, content.len());
+
+                // Token counts grow with the text, so the longest fitting slice can be bisected
+                while low < high {
+                    let middle = ceil_char_boundary(content, low + (high - low).div_ceil(2));
+
+                    if self.size(&content[start..middle]) > limit {
+                        high = floor_char_boundary(content, middle - 1);
+                    } else {
+                        low = middle;
+                    }
+                }
+
+                low
+            },
+        }
+    }
+
+    /// The earliest character boundary `start` where `content[start..end]` fits in `limit`
+    pub fn fit_start(&self, content: &str, end: usize, limit: usize) -> usize {
+        match self {
+            Self::Bytes => ceil_char_boundary(content, end.saturating_sub(limit)),
+            Self::Tokens(_) => {
+                let (mut low, mut high) = (0, end);
+
+                while low < high {
+                    let middle = floor_char_boundary(content, low + (high - low) / 2);
+
+                    if self.size(&content[middle..end]) > limit {
+                        low = ceil_char_boundary(content, middle + 1);
+                    } else {
+                        high = middle;
+                    }
+                }
+
+                high
+            },
+        }
+    }
+}
+
+/// The largest character boundary at or before `index`
+pub fn floor_char_boundary(content: &str, index: usize) -> usize {
+    if index >= content.len() {
+        return content.len();
+    }
+
+    (0..=index).rev().find(|&i| content.is_char_boundary(i)).unwrap_or(0)
+}
+
+/// The smallest character boundary at or after `index`
+pub fn ceil_char_boundary(content: &str, index: usize) -> usize {
+    (index..content.len())
+        .find(|&i| content.is_char_boundary(i))
+        .unwrap_or(content.len())
+}
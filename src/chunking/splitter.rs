@@ -1,28 +1,58 @@
 use tree_sitter::Node;
 
+use super::sizing::{ChunkSizer, ceil_char_boundary};
 use crate::chunking::CodeChunk;
 
-/// Split large chunks into smaller ones with semantic boundaries and overlap
+/// Ruby nodes that methods and nested classes are declared in
+pub const RUBY_SCOPES: &[&str] = &["class", "module", "singleton_class"];
+
+/// Split large chunks into smaller ones with semantic boundaries and overlap, with sizes measured
+/// by `sizer`. With the chunk's syntax `node`, parts end between its statements or members and
+/// overlap by repeating the last whole one
 pub fn split_large_chunk(
     chunk: &CodeChunk,
+    node: Option<Node>,
+    source: &str,
     max_size: usize,
     overlap_percentage: usize,
+    sizer: &ChunkSizer,
 ) -> Vec<CodeChunk> {
-    if chunk.content.len() <= max_size {
+    if sizer.size(&chunk.content) <= max_size {
         return vec![chunk.clone()];
     }
 
-    // Calculate overlap size (in bytes)
+    // Calculate overlap size (in the sizer's unit)
     let overlap_size = (max_size * overlap_percentage) / 100;
     let effective_chunk_size = max_size - overlap_size;
 
+    let boundaries = node.map(|node| child_boundaries(chunk, node, source)).unwrap_or_default();
+
     // Split content along semantic boundaries
     let mut chunks = Vec::new();
     let mut current_pos = 0;
 
     while current_pos < chunk.content.len() {
-        let end_pos =
-            find_semantic_boundary(&chunk.content, current_pos + effective_chunk_size, max_size);
+        let max_end = sizer.fit_end(&chunk.content, current_pos, max_size);
+
+        // The last child that fits, or the best text boundary when none do
+        let end_pos = match boundaries
+            .iter()
+            .rev()
+            .find(|&&boundary| boundary > current_pos && boundary <= max_end)
+        {
+            Some(&boundary) if max_end < chunk.content.len() => boundary,
+            _ => find_semantic_boundary(
+                &chunk.content,
+                sizer.fit_end(&chunk.content, current_pos, effective_chunk_size),
+                max_end,
+            ),
+        };
+
+        // A character wider than the whole chunk still has to go somewhere
+        let end_pos = match end_pos > current_pos {
+            true => end_pos,
+            false => ceil_char_boundary(&chunk.content, current_pos + 1),
+        };
 
         // Create a new chunk with the split content
         let split_content = &chunk.content[current_pos..end_pos];
@@ -33,30 +63,171 @@ pub fn split_large_chunk(
 
         chunks.push(CodeChunk {
             content: split_content.to_string(),
-            node_type: format!("{}_part", chunk.node_type),
+            // Numbered so each part gets its own id
+            node_type: format!("{}_part{}", chunk.node_type, chunks.len() + 1),
             start_line: chunk.start_line + start_line_offset,
             end_line: chunk.start_line + start_line_offset + chunk_lines,
-            path: chunk.path.clone(),
-            language: chunk.language.clone(),
+            start_byte: (chunk.start_byte + current_pos).min(chunk.end_byte),
+            end_byte: (chunk.start_byte + end_pos).min(chunk.end_byte),
+            ..chunk.clone()
         });
 
-        // Move position with overlap
+        // Move position with overlap, always making progress
         current_pos = if end_pos >= chunk.content.len() {
             chunk.content.len()
         } else {
-            end_pos - overlap_size
+            let overlapped = overlap_start(
+                &chunk.content,
+                &boundaries,
+                current_pos,
+                end_pos,
+                sizer.fit_start(&chunk.content, end_pos, overlap_size),
+            );
+
+            match overlapped > current_pos {
+                true => overlapped,
+                false => end_pos,
+            }
         };
     }
 
     chunks
 }
 
-fn find_semantic_boundary(content: &str, target_pos: usize, max_pos: usize) -> usize {
-    let end_pos = std::cmp::min(target_pos, content.len());
-    let max_pos = std::cmp::min(max_pos, content.len());
+/// Where the next part starts: the last whole child before `end_pos` if it fits in the overlap
+/// budget starting at `budget_start`, otherwise the first line start inside the budget, so the
+/// overlap never begins mid-identifier
+fn overlap_start(
+    content: &str,
+    boundaries: &[usize],
+    current_pos: usize,
+    end_pos: usize,
+    budget_start: usize,
+) -> usize {
+    let last_child = boundaries
+        .iter()
+        .rev()
+        .find(|&&boundary| boundary > current_pos && boundary < end_pos);
+
+    match last_child {
+        Some(&boundary) if boundary >= budget_start => boundary,
+        Some(_) => end_pos,
+        None => content[budget_start..end_pos]
+            .find('\n')
+            .map_or(end_pos, |newline| budget_start + newline + 1),
+    }
+}
+
+/// Offsets in the chunk's content where each child of `node` starts: the statements of a
+/// function body, the members of a class, or the items of a merged chunk. Children are found by
+/// the first words of their first line, since preprocessing changes the text around them
+fn child_boundaries(chunk: &CodeChunk, node: Node, source: &str) -> Vec<usize> {
+    let mut boundaries = Vec::new();
+    let mut search_from = 0;
+
+    for child in split_units(chunk, node).into_iter().skip(1) {
+        let first_line = source
+            .get(child.start_byte()..child.end_byte())
+            .and_then(|text| text.lines().next())
+            .unwrap_or_default();
+        let needle = first_line.split_whitespace().take(3).collect::<Vec<_>>().join(" ");
+
+        if needle.is_empty() {
+            continue;
+        }
+
+        let Some(found) = chunk.content.get(search_from..).and_then(|rest| rest.find(&needle))
+        else {
+            continue;
+        };
+        let found = search_from + found;
+
+        // Start at the beginning of the line, keeping its indentation
+        let line_start = chunk.content[..found].rfind('\n').map_or(0, |newline| newline + 1);
+
+        if line_start > boundaries.last().copied().unwrap_or(0) {
+            boundaries.push(line_start);
+        }
+        search_from = found + needle.len();
+    }
+
+    boundaries
+}
+
+/// The nodes a chunk is split between. Several items merged into one chunk split between the
+/// items, a single item between the children of its body
+fn split_units<'tree>(chunk: &CodeChunk, node: Node<'tree>) -> Vec<Node<'tree>> {
+    if let Some(parent) = node.parent() {
+        let mut cursor = parent.walk();
+        let items: Vec<Node> = parent
+            .named_children(&mut cursor)
+            .filter(|sibling| {
+                sibling.start_byte() >= chunk.start_byte
+                    && sibling.end_byte() <= chunk.end_byte
+                    && !sibling.kind().contains("comment")
+            })
+            .collect();
+
+        if items.len() > 1 {
+            return items;
+        }
+    }
+
+    // Step into the child taking up most of the node, like a function's body
+    let mut container = node;
+    loop {
+        let mut cursor = container.walk();
+        let widest = container
+            .named_children(&mut cursor)
+            .max_by_key(|child| child.byte_range().len());
+
+        match widest {
+            Some(widest)
+                if widest.byte_range().len() * 2 > container.byte_range().len()
+                    && widest.named_child_count() > 1 =>
+            {
+                container = widest
+            },
+            _ => break,
+        }
+    }
+
+    let mut cursor = container.walk();
+    container.named_children(&mut cursor).collect()
+}
+
+/// The outermost node ending where `chunk` ends without starting before it, so an item under its
+/// doc comments is still found
+pub fn chunk_node<'tree>(root_node: Node<'tree>, chunk: &CodeChunk) -> Option<Node<'tree>> {
+    if chunk.end_byte == 0 || chunk.end_byte <= chunk.start_byte {
+        return None;
+    }
+
+    let mut node = root_node.descendant_for_byte_range(chunk.end_byte - 1, chunk.end_byte)?;
+    let mut found = None;
 
+    loop {
+        if node.start_byte() < chunk.start_byte || node.end_byte() > chunk.end_byte {
+            break;
+        }
+        if node.end_byte() == chunk.end_byte {
+            found = Some(node);
+        }
+
+        match node.parent() {
+            Some(parent) => node = parent,
+            None => break,
+        }
+    }
+
+    found
+}
+
+/// Byte offset of the best place to end a chunk between `target_pos` and `max_pos`, both
+/// character boundaries
+fn find_semantic_boundary(content: &str, target_pos: usize, max_pos: usize) -> usize {
     // Ensure end_pos doesn't exceed max_pos
-    let end_pos = std::cmp::min(end_pos, max_pos);
+    let end_pos = std::cmp::min(target_pos, max_pos);
 
     // Try to find a good boundary (blank line, end of statement, etc.)
     let search_range = &content[end_pos..max_pos];
@@ -101,29 +272,383 @@ pub fn add_chunk_context(
     source: &str,
     parent_node: Option<Node>,
 ) {
-    // Extract node name if available
-    if let Some(name_node) = find_node_name(node) {
-        if let Some(name) = node_text(name_node, source) {
-            chunk.node_type = format!("{}:{}", chunk.node_type, name);
-        }
+    let name = find_node_name(node).and_then(|name_node| node_text(name_node, source));
+    let parent_name = parent_node
+        .and_then(find_node_name)
+        .and_then(|name_node| node_text(name_node, source));
+
+    chunk.symbol = name.map(str::to_string);
+    chunk.parent_symbol = parent_name.map(str::to_string);
+
+    // Extract node name if available, qualified by its parent's so methods of different classes
+    // don't share a type
+    match (name, parent_name) {
+        (Some(name), Some(parent_name)) => {
+            chunk.node_type = format!("{}:{}.{}", chunk.node_type, parent_name, name);
+        },
+        (Some(name), None) => chunk.node_type = format!("{}:{}", chunk.node_type, name),
+        // Unnamed chunks like SQL queries are told apart by where they start
+        (None, _) => chunk.node_type = format!("{}:{}", chunk.node_type, chunk.start_line + 1),
     }
 
     // Add parent context if available
-    if let Some(parent) = parent_node {
-        if let Some(parent_name_node) = find_node_name(parent) {
-            if let Some(parent_name) = node_text(parent_name_node, source) {
-                chunk.content = format!(
-                    "// In {}: {}\n{}",
-                    parent.kind(),
-                    parent_name,
-                    chunk.content
-                );
+    if let (Some(parent), Some(parent_name)) = (parent_node, parent_name) {
+        chunk.content = format!(
+            "// In {}: {}\n{}",
+            parent.kind(),
+            parent_name,
+            chunk.content
+        );
+    }
+}
+
+/// The nearest ancestor of `node` with one of the given kinds
+pub fn enclosing<'tree>(node: Node<'tree>, kinds: &[&str]) -> Option<Node<'tree>> {
+    let mut ancestor = node.parent();
+
+    while let Some(parent) = ancestor {
+        if kinds.contains(&parent.kind()) {
+            return Some(parent);
+        }
+        ancestor = parent.parent();
+    }
+
+    None
+}
+
+/// Prefix Rust methods with the header of the impl or trait they're declared in, e.g.
+/// `impl Display for Foo`, so they can be found by the type they belong to
+pub fn add_impl_context(chunk: &mut CodeChunk, node: Node, source: &str) {
+    let mut ancestor = node.parent();
+
+    while let Some(parent) = ancestor {
+        if parent.kind() == "impl_item" || parent.kind() == "trait_item" {
+            let header_end = parent
+                .child_by_field_name("body")
+                .map(|body| body.start_byte())
+                .unwrap_or(parent.end_byte());
+
+            // The implemented type, or the trait's own name
+            chunk.parent_symbol = parent
+                .child_by_field_name("type")
+                .or(parent.child_by_field_name("name"))
+                .and_then(|name| node_text(name, source))
+                .map(str::to_string);
+
+            if let Some(header) = source.get(parent.start_byte()..header_end) {
+                let header = header.split_whitespace().collect::<Vec<_>>().join(" ");
+                chunk.content = format!("// In {header}\n{}", chunk.content);
             }
+            return;
+        }
+
+        // Items nested in a function body don't belong to the impl around it
+        if parent.kind() == "function_item" {
+            return;
         }
+
+        ancestor = parent.parent();
     }
 }
 
+/// Note the modules a Ruby class or module mixes in above its members, as `include Enumerable`
+/// changes what a method can call
+pub fn add_mixin_context(chunk: &mut CodeChunk, node: Node, source: &str) {
+    let Some(owner) = enclosing(node, RUBY_SCOPES) else {
+        return;
+    };
+    let body = owner.child_by_field_name("body").unwrap_or(owner);
+
+    let mut cursor = body.walk();
+    let mixins: Vec<String> = body
+        .named_children(&mut cursor)
+        .filter(|child| child.kind() == "call" && child.child_by_field_name("receiver").is_none())
+        .filter(|call| {
+            call.child_by_field_name("method")
+                .and_then(|method| node_text(method, source))
+                .is_some_and(|method| matches!(method, "include" | "extend" | "prepend"))
+        })
+        .filter_map(|call| node_text(call, source))
+        .map(|call| call.split_whitespace().collect::<Vec<_>>().join(" "))
+        .collect();
+
+    if !mixins.is_empty() {
+        chunk.content = format!("// With {}\n{}", mixins.join(", "), chunk.content);
+    }
+}
+
+/// Qualify Go names with the method receiver, so `(*Server).Handle` is told apart from a free
+/// `Handle`, and with the type parameters of generic functions and types
+pub fn add_go_context(chunk: &mut CodeChunk, node: Node, source: &str) {
+    let Some(name) = find_node_name(node).and_then(|name_node| node_text(name_node, source)) else {
+        return;
+    };
+    let mut qualified = name.to_string();
+
+    let receiver_type = node
+        .child_by_field_name("receiver")
+        .and_then(|receiver| receiver.named_child(0))
+        .and_then(|parameter| parameter.child_by_field_name("type"))
+        .and_then(|receiver_type| node_text(receiver_type, source));
+
+    if let Some(receiver_type) = receiver_type {
+        qualified = format!("({receiver_type}).{qualified}");
+        chunk.parent_symbol = Some(receiver_type.to_string());
+    }
+
+    // Types keep their parameters on the spec inside the declaration
+    let type_parameters = node
+        .child_by_field_name("type_parameters")
+        .or_else(|| {
+            node.named_child(0)
+                .filter(|spec| spec.kind() == "type_spec")
+                .and_then(|spec| spec.child_by_field_name("type_parameters"))
+        })
+        .and_then(|parameters| node_text(parameters, source));
+
+    if let Some(type_parameters) = type_parameters {
+        qualified.push_str(type_parameters);
+    }
+
+    chunk.node_type = format!("{}:{}", node.kind(), qualified);
+}
+
+/// Name HCL blocks after their type and labels, so `resource "aws_instance" "web"` has the
+/// symbol `aws_instance.web` and the type `resource:aws_instance.web`
+pub fn add_hcl_context(chunk: &mut CodeChunk, node: Node, source: &str) {
+    let Some(block_type) = node
+        .named_child(0)
+        .filter(|child| child.kind() == "identifier")
+        .and_then(|child| node_text(child, source))
+    else {
+        return;
+    };
+
+    let mut cursor = node.walk();
+    let labels: Vec<&str> = node
+        .named_children(&mut cursor)
+        .filter(|child| matches!(child.kind(), "string_lit" | "identifier"))
+        .skip(1)
+        .filter_map(|label| node_text(label, source))
+        .map(|label| label.trim_matches('"'))
+        .collect();
+
+    if labels.is_empty() {
+        // Blocks like `locals` and `terraform` can repeat in a file
+        chunk.node_type = format!("{block_type}:{}", chunk.start_line + 1);
+    } else {
+        let name = labels.join(".");
+        chunk.node_type = format!("{block_type}:{name}");
+        chunk.symbol = Some(name);
+    }
+}
+
+/// Name configuration chunks after their key or table header, and YAML documents after the
+/// Kubernetes object they hold, as `Deployment/api`
+pub fn add_config_context(chunk: &mut CodeChunk, node: Node, source: &str) {
+    let name = match node.kind() {
+        "pair" | "block_mapping_pair" => node
+            .child_by_field_name("key")
+            .and_then(|key| node_text(key, source))
+            .map(|key| key.trim_matches(['"', '\'']).to_string()),
+        "table" | "table_array_element" => node
+            .named_child(0)
+            .filter(|header| header.kind().ends_with("key"))
+            .and_then(|header| node_text(header, source))
+            .map(str::to_string),
+        "document" => kubernetes_name(node, source),
+        _ => None,
+    };
+
+    match name {
+        Some(name) => {
+            chunk.node_type = format!("{}:{}", chunk.node_type, name);
+            chunk.symbol = Some(name);
+        },
+        None => chunk.node_type = format!("{}:{}", chunk.node_type, chunk.start_line + 1),
+    }
+}
+
+/// `kind/metadata.name` of a YAML document describing a Kubernetes object
+fn kubernetes_name(document: Node, source: &str) -> Option<String> {
+    let pairs = yaml_pairs(document.named_child(0)?);
+
+    let kind = yaml_value(&pairs, "kind", source).and_then(|kind| node_text(kind, source))?;
+    let name = yaml_value(&pairs, "metadata", source)
+        .map(yaml_pairs)
+        .and_then(|metadata| yaml_value(&metadata, "name", source))
+        .and_then(|name| node_text(name, source));
+
+    Some(match name {
+        Some(name) => format!("{kind}/{}", name.trim_matches(['"', '\''])),
+        None => kind.to_string(),
+    })
+}
+
+/// The value of the pair with `key`
+fn yaml_value<'tree>(pairs: &[Node<'tree>], key: &str, source: &str) -> Option<Node<'tree>> {
+    pairs
+        .iter()
+        .find(|pair| {
+            pair.child_by_field_name("key")
+                .and_then(|pair_key| node_text(pair_key, source))
+                .is_some_and(|pair_key| pair_key == key)
+        })
+        .and_then(|pair| pair.child_by_field_name("value"))
+}
+
+/// The key/value pairs of a YAML block mapping node
+fn yaml_pairs(block_node: Node) -> Vec<Node> {
+    let Some(mapping) =
+        block_node.named_child(0).filter(|mapping| mapping.kind() == "block_mapping")
+    else {
+        return Vec::new();
+    };
+
+    let mut cursor = mapping.walk();
+    mapping
+        .named_children(&mut cursor)
+        .filter(|pair| pair.kind() == "block_mapping_pair")
+        .collect()
+}
+
+/// Name HTML sections after their tag and id, as `section#pricing`, and CSS rules after their
+/// selectors
+pub fn add_markup_context(chunk: &mut CodeChunk, node: Node, source: &str) {
+    let name = match node.kind() {
+        "element" => node.named_child(0).and_then(|start_tag| {
+            let mut cursor = start_tag.walk();
+            let tag = start_tag
+                .named_children(&mut cursor)
+                .find(|child| child.kind() == "tag_name")
+                .and_then(|tag| node_text(tag, source))?;
+
+            let id = start_tag
+                .named_children(&mut cursor)
+                .filter(|child| child.kind() == "attribute")
+                .find(|attribute| {
+                    attribute
+                        .named_child(0)
+                        .and_then(|name| node_text(name, source))
+                        .is_some_and(|name| name == "id")
+                })
+                .and_then(|attribute| attribute.named_child(1))
+                .and_then(|value| node_text(value, source))
+                .map(|value| value.trim_matches(['"', '\'']));
+
+            Some(match id {
+                Some(id) => format!("{tag}#{id}"),
+                None => tag.to_string(),
+            })
+        }),
+        "rule_set" => node
+            .named_child(0)
+            .filter(|selectors| selectors.kind() == "selectors")
+            .and_then(|selectors| node_text(selectors, source))
+            .map(|selectors| selectors.split_whitespace().collect::<Vec<_>>().join(" ")),
+        _ => None,
+    };
+
+    let Some(name) = name else {
+        return;
+    };
+
+    // Without an id the same tag can appear many times in a file
+    chunk.node_type = if name.contains('#') || node.kind() == "rule_set" {
+        format!("{}:{}", chunk.node_type, name)
+    } else {
+        format!("{}:{}:{}", chunk.node_type, name, chunk.start_line + 1)
+    };
+    chunk.symbol = Some(name);
+}
+
+/// Label a fenced Markdown code block with the language declared after its opening fence, so
+/// examples are filtered and shown as the language they're written in
+pub fn add_fence_language(chunk: &mut CodeChunk, node: Node, source: &str) {
+    if node.kind() != "fenced_code_block" {
+        return;
+    }
+
+    let mut cursor = node.walk();
+    let language = node
+        .named_children(&mut cursor)
+        .find(|child| child.kind() == "info_string")
+        .and_then(|info| info.named_child(0))
+        .and_then(|language| node_text(language, source));
+
+    if let Some(language) = language {
+        chunk.language = language.to_string();
+    }
+}
+
+/// Name Elixir definitions after their first argument, qualified by the module they're defined
+/// in, as `def:MyApp.Accounts.get_user`
+pub fn add_elixir_context(chunk: &mut CodeChunk, node: Node, source: &str) {
+    let name = elixir_definition_name(node).and_then(|name| node_text(name, source));
+    let module = ancestors(node).find_map(|ancestor| {
+        let keyword = node_text(ancestor.child_by_field_name("target")?, source)?;
+        let name = node_text(elixir_definition_name(ancestor)?, source)?;
+
+        matches!(keyword, "defmodule" | "defprotocol" | "defimpl").then_some((keyword, name))
+    });
+
+    chunk.symbol = name.map(str::to_string);
+    chunk.parent_symbol = module.map(|(_, module)| module.to_string());
+
+    match (name, module) {
+        (Some(name), Some((_, module))) => {
+            chunk.node_type = format!("{}:{}.{}", chunk.node_type, module, name);
+        },
+        (Some(name), None) => chunk.node_type = format!("{}:{}", chunk.node_type, name),
+        _ => {},
+    }
+
+    if let Some((keyword, module)) = module {
+        chunk.content = format!("// In {keyword}: {module}\n{}", chunk.content);
+    }
+}
+
+/// The name a `def`-style call defines: the module alias, or the function name in the head,
+/// looking through guards (`def name(x) when ...`)
+fn elixir_definition_name(call: Node) -> Option<Node> {
+    let mut cursor = call.walk();
+    let mut head = call
+        .named_children(&mut cursor)
+        .find(|child| child.kind() == "arguments")?
+        .named_child(0)?;
+
+    loop {
+        match head.kind() {
+            "binary_operator" => head = head.child_by_field_name("left")?,
+            "call" => return head.child_by_field_name("target"),
+            _ => return Some(head),
+        }
+    }
+}
+
+fn ancestors<'tree>(node: Node<'tree>) -> impl Iterator<Item = Node<'tree>> {
+    std::iter::successors(node.parent(), |node| node.parent())
+}
+
 fn find_node_name(node: Node) -> Option<Node> {
+    // Decorators wrap the definition that holds the name
+    if let Some(definition) = node.child_by_field_name("definition") {
+        return find_node_name(definition);
+    }
+
+    // Markdown sections are named by their heading
+    if node.kind() == "section" {
+        return node
+            .named_child(0)
+            .filter(|heading| heading.kind().ends_with("_heading"))
+            .and_then(|heading| heading.child_by_field_name("heading_content"));
+    }
+
+    // Dart method signatures wrap the function, getter or constructor signature
+    if node.kind() == "method_signature" {
+        return node.named_child(0).and_then(find_node_name);
+    }
+
     // Different node types store their names in different child nodes
     let child_count = node.named_child_count();
     for i in 0..child_count {
@@ -133,6 +658,11 @@ fn find_node_name(node: Node) -> Option<Node> {
         // Common name patterns
         if child_type == "identifier"
             || child_type == "name"
+            || child_type == "constant"
+            || child_type == "variable"
+            || child_type == "object_reference"
+            || child_type == "targets"
+            || child_type == "scope_resolution"
             || child_type.ends_with("_name")
             || child_type.ends_with("_identifier")
         {
@@ -140,7 +670,10 @@ fn find_node_name(node: Node) -> Option<Node> {
         }
     }
 
-    None
+    // Go declarations are named by their first spec, e.g. `type_spec` or `const_spec`
+    node.named_child(0)
+        .filter(|child| child.kind().ends_with("_spec"))
+        .and_then(find_node_name)
 }
 
 /// Extract text for a node from source
@@ -153,3 +686,70 @@ fn node_text<'a>(node: Node, source: &'a str) -> Option<&'a str> {
         None
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(content: &str) -> CodeChunk {
+        CodeChunk {
+            content: content.to_string(),
+            node_type: "function_item".to_string(),
+            end_byte: content.len(),
+            ..Default::default()
+        }
+    }
+
+    fn split(content: &str, max_size: usize, overlap_percentage: usize) -> Vec<CodeChunk> {
+        split_large_chunk(
+            &chunk(content),
+            None,
+            content,
+            max_size,
+            overlap_percentage,
+            &ChunkSizer::Bytes,
+        )
+    }
+
+    #[test]
+    fn splits_non_ascii_sources_between_characters() {
+        let content = concat!(
+            "// Größe in Bytes — 測定する 🦀\n",
+            "fn größe() -> usize {\n",
+            "    \"日本語のテキスト\".len()\n",
+            "}\n",
+        )
+        .repeat(4);
+
+        for max_size in 5..40 {
+            let parts = split(&content, max_size, 20);
+
+            assert!(parts.iter().all(|part| !part.content.is_empty()));
+            assert!(parts.iter().all(|part| content.contains(&part.content)));
+            assert!(content.starts_with(&parts[0].content));
+            assert!(content.ends_with(&parts.last().unwrap().content));
+        }
+    }
+
+    #[test]
+    fn keeps_characters_wider_than_the_limit_whole() {
+        let parts = split("🦀🦀🦀", 2, 0);
+
+        assert_eq!(
+            parts.iter().map(|part| part.content.as_str()).collect::<Vec<_>>(),
+            ["🦀", "🦀", "🦀"]
+        );
+    }
+
+    #[test]
+    fn split_parts_cover_the_whole_chunk() {
+        let content = "let café = \"naïve\";\n".repeat(10);
+        let parts = split(&content, 32, 0);
+
+        assert!(parts.len() > 1);
+        assert_eq!(
+            parts.iter().map(|part| part.content.as_str()).collect::<String>(),
+            content
+        );
+    }
+}
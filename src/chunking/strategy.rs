@@ -0,0 +1,89 @@
+use std::path::Path;
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use tree_sitter::Tree;
+
+use super::{
+    chunker::{Chunker, ChunkerConfig},
+    types::CodeChunk,
+};
+use crate::utils::parsers::SupportedParsers;
+
+/// How a parsed file is cut into chunks
+pub trait ChunkStrategy: Send + Sync {
+    fn chunk(
+        &self,
+        tree: &Tree,
+        source: &str,
+        path: &Path,
+        language: &SupportedParsers,
+    ) -> Vec<CodeChunk>;
+}
+
+/// Built-in strategies, selectable with `--chunk-strategy`
+#[derive(Debug, Clone, Copy, Default, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ChunkStrategyKind {
+    /// One chunk per function, class or other item matched by the language's query
+    #[default]
+    Ast,
+    /// Fixed-size overlapping windows, for files whose syntax doesn't help
+    SlidingWindow,
+    /// AST chunks with neighbouring siblings merged up to the size limit
+    SemanticMerge,
+}
+
+impl ChunkStrategyKind {
+    pub fn build(self, config: ChunkerConfig) -> Box<dyn ChunkStrategy> {
+        match self {
+            Self::Ast => Box::new(AstStrategy(config)),
+            Self::SlidingWindow => Box::new(SlidingWindowStrategy(config)),
+            Self::SemanticMerge => Box::new(SemanticMergeStrategy(config)),
+        }
+    }
+}
+
+pub struct AstStrategy(pub ChunkerConfig);
+
+impl ChunkStrategy for AstStrategy {
+    fn chunk(
+        &self,
+        tree: &Tree,
+        source: &str,
+        path: &Path,
+        language: &SupportedParsers,
+    ) -> Vec<CodeChunk> {
+        Chunker::new(tree, source, path, language, self.0.clone()).extract_chunks()
+    }
+}
+
+pub struct SlidingWindowStrategy(pub ChunkerConfig);
+
+impl ChunkStrategy for SlidingWindowStrategy {
+    fn chunk(
+        &self,
+        tree: &Tree,
+        source: &str,
+        path: &Path,
+        language: &SupportedParsers,
+    ) -> Vec<CodeChunk> {
+        Chunker::new(tree, source, path, language, self.0.clone()).sliding_windows()
+    }
+}
+
+pub struct SemanticMergeStrategy(pub ChunkerConfig);
+
+impl ChunkStrategy for SemanticMergeStrategy {
+    fn chunk(
+        &self,
+        tree: &Tree,
+        source: &str,
+        path: &Path,
+        language: &SupportedParsers,
+    ) -> Vec<CodeChunk> {
+        Chunker::new(tree, source, path, language, self.0.clone())
+            .merge_all()
+            .extract_chunks()
+    }
+}
@@ -0,0 +1,83 @@
+use std::path::Path;
+
+use tree_sitter::Node;
+
+/// Directories whose files are all tests
+const TEST_DIRECTORIES: &[&str] = &["tests", "test", "__tests__", "spec"];
+
+/// Whether the file at `path` holds tests by the naming conventions of the supported languages
+pub fn is_test_path(path: &Path) -> bool {
+    let in_test_directory = path.parent().is_some_and(|parent| {
+        parent
+            .components()
+            .any(|component| TEST_DIRECTORIES.contains(&&*component.as_os_str().to_string_lossy()))
+    });
+
+    let Some(stem) = path.file_stem().map(|stem| stem.to_string_lossy()) else {
+        return in_test_directory;
+    };
+
+    in_test_directory
+        // test_app.py, app_test.py, app_test.go, app_test.exs, app_spec.rb
+        || stem.starts_with("test_")
+        || stem.ends_with("_test")
+        || stem.ends_with("_spec")
+        // app.test.ts, app.spec.js
+        || stem.ends_with(".test")
+        || stem.ends_with(".spec")
+        // AppTest.php, AppTests.cs
+        || stem.ends_with("Test")
+        || stem.ends_with("Tests")
+}
+
+/// Whether `node` is a test, or sits inside one: a Rust `#[test]` function or `#[cfg(test)]`
+/// module, a Python `test_` function or `Test` class, or a Go `Test`, `Benchmark` or `Fuzz`
+/// function
+pub fn is_test_node(node: Node, source: &str) -> bool {
+    std::iter::successors(Some(node), |node| node.parent()).any(|node| is_test_item(node, source))
+}
+
+fn is_test_item(node: Node, source: &str) -> bool {
+    let name = node
+        .child_by_field_name("name")
+        .and_then(|name| source.get(name.byte_range()))
+        .unwrap_or_default();
+
+    match node.kind() {
+        "function_item" | "mod_item" => has_test_attribute(node, source),
+        "function_definition" => name.starts_with("test_"),
+        "class_definition" => name.starts_with("Test"),
+        "function_declaration" => {
+            ["Test", "Benchmark", "Fuzz"].iter().any(|prefix| name.starts_with(prefix))
+        },
+        _ => false,
+    }
+}
+
+/// Rust attributes sit before the item as siblings
+fn has_test_attribute(node: Node, source: &str) -> bool {
+    let mut current = node;
+
+    while let Some(previous) = current.prev_sibling() {
+        if previous.kind() != "attribute_item" {
+            return false;
+        }
+
+        let attribute: String = source
+            .get(previous.byte_range())
+            .unwrap_or_default()
+            .split_whitespace()
+            .collect();
+        let inner = attribute.trim_start_matches("#[").trim_end_matches(']');
+        let path = inner.split('(').next().unwrap_or_default();
+
+        // #[test], #[tokio::test(flavor = "multi_thread")] and #[cfg(test)]
+        if path == "test" || path.ends_with("::test") || inner == "cfg(test)" {
+            return true;
+        }
+
+        current = previous;
+    }
+
+    false
+}
@@ -0,0 +1,100 @@
+use std::{fs, path::PathBuf};
+
+use clap::Parser;
+
+use super::Command;
+use crate::{
+    chunking::{ChunkStrategyKind, ChunkerConfig, CommentPolicy, ImportContext, Queries},
+    prelude::*,
+    scanner::FileParser,
+    utils::parsers::SupportedParsers,
+};
+
+/// Chunk a single file and print the chunks, without embedding or storing them. For tuning
+/// queries and chunk sizes
+#[derive(Parser, Debug, Clone)]
+pub struct Chunks {
+    /// File to chunk
+    path: PathBuf,
+
+    /// Chunk size limit in bytes (default: 4096)
+    #[arg(short, long)]
+    chunk_size_limit: Option<usize>,
+
+    /// How the file is cut into chunks
+    #[arg(long, value_enum, default_value = "ast")]
+    chunk_strategy: ChunkStrategyKind,
+
+    /// Percentage of overlap between chunks (default: 10%)
+    #[arg(long, default_value = "10")]
+    overlap_percentage: Option<usize>,
+
+    /// Which comments to keep in chunks
+    #[arg(long, value_enum, default_value = "keep-doc-comments")]
+    comments: CommentPolicy,
+
+    /// Imports to put in front of each chunk
+    #[arg(long, value_enum, default_value = "referenced")]
+    imports: ImportContext,
+
+    /// Also chunk control flow statements
+    #[arg(long)]
+    statement_chunks: bool,
+
+    /// Directory of tree-sitter query overrides
+    #[arg(long)]
+    queries_dir: Option<PathBuf>,
+
+    /// Print each chunk's content below its summary
+    #[arg(long)]
+    content: bool,
+
+    /// Print the chunks as JSON lines instead
+    #[arg(long)]
+    json: bool,
+}
+
+impl Command for Chunks {
+    async fn execute(&self) -> Result<()> {
+        let language = SupportedParsers::for_path(&self.path)
+            .ok_or(InvalidArgument(f!("No parser for {}", self.path.display())))?;
+        let content = fs::read_to_string(&self.path)?;
+
+        let mut files = FileParser::new(self.chunk_strategy.build(ChunkerConfig {
+            max_chunk_size: self.chunk_size_limit,
+            overlap_percentage: self.overlap_percentage,
+            comments: self.comments,
+            statement_chunks: self.statement_chunks,
+            queries: Queries::load(self.queries_dir.as_deref())?,
+            imports: self.imports,
+            ..Default::default()
+        }));
+        let chunks = files.parse_file(&self.path, &content, &language)?;
+
+        for chunk in &chunks {
+            if self.json {
+                println!("{}", serde_json::to_string(chunk)?);
+                continue;
+            }
+
+            println!(
+                "{:>5}-{:<5} {:>7} bytes  {}  {}",
+                chunk.start_line + 1,
+                chunk.end_line + 1,
+                chunk.content.len(),
+                chunk.node_type,
+                chunk.symbol.as_deref().unwrap_or("-"),
+            );
+
+            if self.content {
+                println!("{}\n", chunk.content);
+            }
+        }
+
+        if !self.json {
+            println!("{} chunks from {}", chunks.len(), self.path.display());
+        }
+
+        Ok(())
+    }
+}
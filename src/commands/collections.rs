@@ -0,0 +1,128 @@
+use std::io::{self, BufRead, Write};
+
+use clap::{Parser, Subcommand};
+use tracing::info;
+
+use super::Command;
+use crate::{
+    prelude::*,
+    storage::{CollectionInfo, QdrantStorage, Storage},
+};
+
+/// See what's indexed and clean up collections without the Qdrant dashboard
+#[derive(Parser, Debug, Clone)]
+pub struct Collections {
+    /// Qdrant URL
+    #[arg(long, default_value = "http://localhost:6334", global = true)]
+    qdrant_url: String,
+
+    /// Print collections as JSON
+    #[arg(long, global = true)]
+    json: bool,
+
+    #[command(subcommand)]
+    action: CollectionsAction,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+enum CollectionsAction {
+    /// Every collection with its point count, vector size and embedding model
+    List,
+    /// Point count, vector size and embedding model of one collection
+    Info {
+        /// Collection to inspect
+        collection: String,
+    },
+    /// Delete a collection and every point in it
+    Delete {
+        /// Collection to delete
+        collection: String,
+
+        /// Don't ask before deleting
+        #[arg(short, long)]
+        yes: bool,
+    },
+    /// Rename a collection by moving its points into a new one
+    Rename {
+        /// Collection to rename
+        from: String,
+
+        /// New name, which mustn't be taken
+        to: String,
+    },
+}
+
+impl Command for Collections {
+    async fn execute(&self) -> Result<()> {
+        match &self.action {
+            CollectionsAction::List => {
+                let server = QdrantStorage::server(&self.qdrant_url)?;
+                let mut collections = Vec::new();
+
+                for name in server.collections().await? {
+                    let storage = QdrantStorage::open(&self.qdrant_url, &name).await?;
+                    collections.push(storage.collection_info().await?);
+                }
+
+                self.print(&collections)
+            },
+            CollectionsAction::Info { collection } => {
+                let storage = QdrantStorage::open(&self.qdrant_url, collection).await?;
+
+                self.print(&[storage.collection_info().await?])
+            },
+            CollectionsAction::Delete { collection, yes } => {
+                let storage = QdrantStorage::open(&self.qdrant_url, collection).await?;
+                let points = storage.collection_info().await?.points;
+
+                if !yes && !confirm(&f!("Delete {collection} and its {points} points?"))? {
+                    return Ok(());
+                }
+
+                storage.delete_collection().await?;
+                info!("Deleted {collection}");
+                Ok(())
+            },
+            CollectionsAction::Rename { from, to } => {
+                let storage = QdrantStorage::open(&self.qdrant_url, from).await?;
+
+                storage.rename_collection(to).await?;
+                info!("Renamed {from} to {to}");
+                Ok(())
+            },
+        }
+    }
+}
+
+impl Collections {
+    fn print(&self, collections: &[CollectionInfo]) -> Result<()> {
+        if self.json {
+            println!("{}", serde_json::to_string_pretty(collections)?);
+            return Ok(());
+        }
+
+        println!("{:<32} {:>10} {:>8}  model", "collection", "points", "size");
+        for collection in collections {
+            println!(
+                "{:<32} {:>10} {:>8}  {}",
+                collection.name,
+                collection.points,
+                collection.vector_size.map(|size| size.to_string()).unwrap_or_default(),
+                collection.model.as_deref().unwrap_or("unknown")
+            );
+        }
+
+        Ok(())
+    }
+}
+
+/// Ask `question` on stderr, true when it's answered yes
+fn confirm(question: &str) -> Result<bool> {
+    eprint!("{question} [y/N] ");
+    io::stderr().flush()?;
+
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer)?;
+
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+}
@@ -0,0 +1,154 @@
+use std::{
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+use chrono::Local;
+use clap::Parser;
+use cron::Schedule;
+use tokio::time::sleep;
+use tracing::{info, warn};
+
+use super::{Command, scan::Scan};
+use crate::{prelude::*, utils::git::fetch_state};
+
+/// Keep running and rescan the codebase on a schedule, and after a git fetch or pull when asked,
+/// so a shared index stays fresh without an external scheduler
+#[derive(Parser, Debug, Clone)]
+pub struct Daemon {
+    #[command(flatten)]
+    scan: Scan,
+
+    /// Time between rescans, e.g. `30m`, `6h` or `1d`, counted from the end of the last one
+    #[arg(long, default_value = "1h", value_parser = parse_interval)]
+    every: Duration,
+
+    /// Rescan on a cron schedule in local time instead of `--every`, e.g. `0 2 * * *` for 2am
+    /// every night. A leading seconds field is optional
+    #[arg(long, conflicts_with = "every", value_parser = parse_cron)]
+    cron: Option<Schedule>,
+
+    /// Also rescan as soon as a fetch, pull or checkout moves the repository
+    #[arg(long)]
+    on_fetch: bool,
+
+    /// Time between checks for a fetch with `--on-fetch`
+    #[arg(long, default_value = "1m", value_parser = parse_interval)]
+    poll: Duration,
+}
+
+impl Command for Daemon {
+    async fn execute(&self) -> Result<()> {
+        let mut state = self.fetch_state();
+
+        loop {
+            // Scans are incremental, so a rescan only embeds what changed
+            if let Err(e) = self.scan.execute().await {
+                warn!("Scheduled scan failed: {e}");
+            }
+
+            let wait = self.next_scan()?;
+            let next = Instant::now() + wait;
+            info!("Next scan in {}s", wait.as_secs());
+
+            loop {
+                let remaining = next.saturating_duration_since(Instant::now());
+                if remaining.is_zero() {
+                    break;
+                }
+
+                match self.on_fetch {
+                    true => sleep(remaining.min(self.poll)).await,
+                    false => sleep(remaining).await,
+                }
+
+                if self.on_fetch {
+                    let current = self.fetch_state();
+
+                    if current != state {
+                        info!("Repository moved, scanning");
+                        state = current;
+                        break;
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Daemon {
+    /// Time until the next scheduled scan
+    fn next_scan(&self) -> Result<Duration> {
+        let Some(schedule) = &self.cron else {
+            return Ok(self.every);
+        };
+
+        let next = schedule.upcoming(Local).next().ok_or(InvalidArgument(String::from(
+            "The cron schedule has no upcoming times",
+        )))?;
+
+        Ok((next - Local::now()).to_std().unwrap_or_default())
+    }
+
+    /// What the roots' repositories have checked out and last fetched, to notice when it moves
+    fn fetch_state(&self) -> Vec<Option<String>> {
+        self.scan
+            .roots()
+            .map(|roots| roots.iter().map(|root| fetch_state(root)).collect())
+            .unwrap_or_default()
+    }
+}
+
+/// Parse a duration like `90s`, `15m`, `6h` or `1d`, seconds when there's no unit
+fn parse_interval(value: &str) -> std::result::Result<Duration, String> {
+    let value = value.trim();
+    let split = value.find(|c: char| !c.is_ascii_digit()).unwrap_or(value.len());
+    let (amount, unit) = value.split_at(split);
+
+    let amount: u64 = amount.parse().map_err(|_| f!("Invalid interval {value}"))?;
+    let seconds = match unit {
+        "" | "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        _ => return Err(f!("Unknown unit {unit} in {value}, use s, m, h or d")),
+    };
+
+    match amount {
+        0 => Err(String::from("The interval has to be longer than 0")),
+        amount => amount
+            .checked_mul(seconds)
+            .map(Duration::from_secs)
+            .ok_or(f!("Interval {value} is too long")),
+    }
+}
+
+/// Parse a cron expression, with or without the leading seconds field
+fn parse_cron(value: &str) -> std::result::Result<Schedule, String> {
+    let expression = match value.split_whitespace().count() {
+        5 => f!("0 {value}"),
+        _ => value.to_string(),
+    };
+
+    Schedule::from_str(&expression).map_err(|e| f!("Invalid cron schedule {value}: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn intervals_too_long_to_hold_are_rejected() {
+        assert_eq!(parse_interval("6h"), Ok(Duration::from_secs(6 * 60 * 60)));
+        assert!(parse_interval("99999999999999999d").is_err());
+    }
+
+    #[test]
+    fn cron_schedules_can_leave_out_seconds() {
+        let nightly = parse_cron("0 2 * * *").unwrap();
+        let next = nightly.upcoming(Local).next().unwrap();
+
+        assert_eq!(next.format("%H:%M:%S").to_string(), "02:00:00");
+        assert!(parse_cron("every night").is_err());
+    }
+}
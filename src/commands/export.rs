@@ -0,0 +1,62 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+use tracing::info;
+
+use super::Command;
+use crate::{
+    prelude::*,
+    storage::{PointFormat, PointWriter, QdrantStorage, Storage},
+};
+
+/// Dump every chunk and its embedding in a collection to a JSONL or Parquet file
+#[derive(Parser, Debug, Clone)]
+pub struct Export {
+    /// Qdrant URL
+    #[arg(long, default_value = "http://localhost:6334")]
+    qdrant_url: String,
+
+    /// Collection to export
+    #[arg(long)]
+    collection: String,
+
+    /// File to write the export to
+    #[arg(short, long)]
+    output: PathBuf,
+
+    /// Layout of the export, Parquet for `.parquet` files and JSONL otherwise by default
+    #[arg(long)]
+    format: Option<PointFormat>,
+}
+
+impl Command for Export {
+    async fn execute(&self) -> Result<()> {
+        let storage = QdrantStorage::open(&self.qdrant_url, &self.collection).await?;
+        let format = self.format.unwrap_or(PointFormat::of(&self.output));
+        let mut writer = PointWriter::create(&self.output, format)?;
+
+        // Written as it's scrolled, so the collection never has to fit in memory
+        let mut exported = 0;
+        let mut offset = None;
+        loop {
+            let (points, next) = storage.export_page(offset).await?;
+            writer.write(&points)?;
+            exported += points.len();
+
+            match next {
+                Some(next) => offset = Some(next),
+                None => break,
+            }
+        }
+
+        writer.finish()?;
+
+        info!(
+            "Exported {} points from {} to {}",
+            exported,
+            self.collection,
+            self.output.display()
+        );
+        Ok(())
+    }
+}
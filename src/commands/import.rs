@@ -0,0 +1,73 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+use tracing::info;
+
+use super::Command;
+use crate::{
+    prelude::*,
+    storage::{PointFormat, PointReader, QdrantStorage, Storage, StorageConfig},
+};
+
+/// Load a JSONL or Parquet export into a collection, creating it if needed
+#[derive(Parser, Debug, Clone)]
+pub struct Import {
+    /// Qdrant URL
+    #[arg(long, default_value = "http://localhost:6334")]
+    qdrant_url: String,
+
+    /// Collection to import into
+    #[arg(long)]
+    collection: String,
+
+    /// File produced by `export`
+    #[arg(short, long)]
+    input: PathBuf,
+
+    /// Layout of the export, Parquet for `.parquet` files and JSONL otherwise by default
+    #[arg(long)]
+    format: Option<PointFormat>,
+}
+
+impl Command for Import {
+    async fn execute(&self) -> Result<()> {
+        if !self.input.exists() {
+            return Err(NotFound(self.input.clone()));
+        }
+
+        let format = self.format.unwrap_or(PointFormat::of(&self.input));
+        let mut pages = PointReader::open(&self.input, format)?;
+
+        let Some(first) = pages.next().transpose()? else {
+            info!("Nothing to import from {}", self.input.display());
+            return Ok(());
+        };
+
+        // The collection is sized by the first point, and every later page must match it
+        let embedding_size = first[0].embedding.len();
+        let storage = QdrantStorage::new(
+            &self.qdrant_url,
+            &self.collection,
+            embedding_size,
+            StorageConfig::default(),
+        )
+        .await?;
+
+        let mut imported = 0;
+        for points in std::iter::once(Ok(first)).chain(pages) {
+            let points = points?;
+
+            if points.iter().any(|point| point.embedding.len() != embedding_size) {
+                return Err(Payload(
+                    "Embeddings in the export differ in length".to_string(),
+                ));
+            }
+
+            storage.import_points(&points).await?;
+            imported += points.len();
+        }
+
+        info!("Imported {imported} points into {}", self.collection);
+        Ok(())
+    }
+}
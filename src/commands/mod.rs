@@ -1,15 +1,34 @@
+mod chunks;
+mod collections;
+mod daemon;
+mod export;
+mod import;
+mod progress;
 mod query;
 mod scan;
+mod watch;
 
+use chunks::Chunks;
 use clap::{Parser, Subcommand};
+use collections::Collections;
+use daemon::Daemon;
+use export::Export;
+use import::Import;
 use query::Query;
 use scan::Scan;
+use watch::Watch;
 
 #[derive(Subcommand, Debug, Clone)]
 #[allow(clippy::large_enum_variant)]
 pub enum Commands {
     Scan(Scan),
     Query(Query),
+    Export(Export),
+    Import(Import),
+    Chunks(Chunks),
+    Watch(Watch),
+    Daemon(Daemon),
+    Collections(Collections),
 }
 
 #[derive(Parser, Debug)]
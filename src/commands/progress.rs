@@ -0,0 +1,192 @@
+use std::{
+    io::{IsTerminal, stderr},
+    time::{Duration, Instant},
+};
+
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use tokio::{sync::mpsc::UnboundedReceiver, task::JoinHandle};
+use tracing::info;
+
+use crate::{prelude::*, scanner::ProgressEvent};
+
+/// How often progress is logged when stderr isn't a terminal
+const LOG_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Draw scan progress as progress bars, log it now and then when stderr isn't a terminal, or
+/// print every event as NDJSON with `json`
+pub fn render_progress(
+    mut receiver: UnboundedReceiver<ProgressEvent>,
+    json: bool,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut display = match stderr().is_terminal() {
+            true => Display::Bars(ProgressBars::new()),
+            false => Display::Log(ProgressLog::default()),
+        };
+
+        while let Some(event) = receiver.recv().await {
+            if json {
+                if let Ok(line) = serde_json::to_string(&event) {
+                    println!("{line}");
+                }
+
+                continue;
+            }
+
+            match &mut display {
+                Display::Bars(bars) => bars.update(event),
+                Display::Log(log) => log.update(event),
+            }
+        }
+    })
+}
+
+enum Display {
+    Bars(ProgressBars),
+    Log(ProgressLog),
+}
+
+/// A bar per stage. Embedding is the slow one, so its ETA is the scan's once parsing is done
+struct ProgressBars {
+    multi: MultiProgress,
+    parse: ProgressBar,
+    embed: ProgressBar,
+    store: ProgressBar,
+}
+
+impl ProgressBars {
+    fn new() -> Self {
+        let style = |template: &str| {
+            ProgressStyle::with_template(template).unwrap_or_else(|_| ProgressStyle::default_bar())
+        };
+
+        let multi = MultiProgress::new();
+        let parse = multi.add(
+            ProgressBar::new_spinner().with_style(style("parse {spinner} {pos} files, {wide_msg}")),
+        );
+        parse.enable_steady_tick(Duration::from_millis(100));
+
+        let embed = multi.add(ProgressBar::new(0).with_style(style(
+            "embed {bar:40} {pos}/{len} chunks ({per_sec}, ETA {eta}) {wide_msg}",
+        )));
+        let store =
+            multi.add(ProgressBar::new(0).with_style(style("store {bar:40} {pos}/{len} chunks")));
+
+        Self {
+            multi,
+            parse,
+            embed,
+            store,
+        }
+    }
+
+    fn update(&mut self, event: ProgressEvent) {
+        match event {
+            ProgressEvent::Parsed {
+                files,
+                chunks,
+                current_file,
+            } => {
+                self.parse.set_position(files as u64);
+                self.parse.set_message(f!("{chunks} chunks, {}", current_file.display()));
+                self.embed.set_length(chunks as u64);
+                self.store.set_length(chunks as u64);
+            },
+            ProgressEvent::ParseFinished {
+                files,
+                files_unchanged,
+                chunks,
+                ..
+            } => {
+                self.parse.set_position(files as u64);
+                self.parse
+                    .finish_with_message(f!("{chunks} chunks, {files_unchanged} files unchanged"));
+                self.embed.set_length(chunks as u64);
+                self.store.set_length(chunks as u64);
+            },
+            ProgressEvent::EmbedStarted { .. } => self.embed.reset_eta(),
+            ProgressEvent::Embedded {
+                done,
+                failed,
+                current_file,
+                ..
+            } => {
+                self.embed.set_position((done + failed) as u64);
+                self.embed.set_message(current_file.display().to_string());
+            },
+            ProgressEvent::EmbedFinished { .. } => self.embed.finish_with_message(""),
+            ProgressEvent::Stored { stored } => self.store.set_position(stored as u64),
+            ProgressEvent::ScanFinished { .. } => {
+                self.store.finish();
+                let _ = self.multi.clear();
+            },
+        }
+    }
+}
+
+/// Progress as a log line every `LOG_INTERVAL`, for CI logs and redirected output
+#[derive(Default)]
+struct ProgressLog {
+    last_logged: Option<Instant>,
+    parsed_chunks: usize,
+}
+
+impl ProgressLog {
+    /// Whether enough time passed since the last line to log another
+    fn due(&mut self) -> bool {
+        let due = self.last_logged.is_none_or(|last| last.elapsed() >= LOG_INTERVAL);
+
+        if due {
+            self.last_logged = Some(Instant::now());
+        }
+
+        due
+    }
+
+    fn update(&mut self, event: ProgressEvent) {
+        match event {
+            ProgressEvent::Parsed { files, chunks, .. } => {
+                self.parsed_chunks = chunks;
+
+                if self.due() {
+                    info!("Parsed {files} files into {chunks} chunks");
+                }
+            },
+            ProgressEvent::ParseFinished {
+                files,
+                files_unchanged,
+                chunks,
+                elapsed_seconds,
+            } => {
+                self.parsed_chunks = chunks;
+                info!(
+                    "Parsed {files} files into {chunks} chunks in {elapsed_seconds:.1}s, \
+                     {files_unchanged} files unchanged"
+                );
+            },
+            ProgressEvent::Embedded {
+                done,
+                failed,
+                eta_seconds,
+                ..
+            } => {
+                if self.due() {
+                    let eta = eta_seconds.map(|eta| f!(", ETA {eta:.0}s")).unwrap_or_default();
+                    info!(
+                        "Embedded {}/{} chunks{eta}",
+                        done + failed,
+                        self.parsed_chunks
+                    );
+                }
+            },
+            ProgressEvent::EmbedFinished {
+                done,
+                failed,
+                elapsed_seconds,
+            } => info!("Embedded {done} chunks in {elapsed_seconds:.1}s, {failed} failed"),
+            ProgressEvent::EmbedStarted { .. }
+            | ProgressEvent::Stored { .. }
+            | ProgressEvent::ScanFinished { .. } => {},
+        }
+    }
+}
@@ -1,16 +1,122 @@
+use std::collections::{HashMap, HashSet};
+
 use clap::Parser;
 
-use super::Command;
-use crate::prelude::*;
+use super::{Command, scan::Scan};
+use crate::{
+    chunking::CodeChunk,
+    prelude::*,
+    storage::{SearchHit, Storage, all_of, package_filter, test_filter},
+};
 
+/// Search a scanned codebase, embedding the query with the provider and options it was scanned
+/// with
 #[derive(Parser, Debug, Clone)]
 pub struct Query {
     #[arg(short, long)]
     query: String,
+
+    #[command(flatten)]
+    scan: Scan,
+
+    /// Number of chunks to return
+    #[arg(long, default_value = "10")]
+    limit: u64,
+
+    /// Keep test functions and test files in the results (the default)
+    #[arg(long, overrides_with = "exclude_tests")]
+    include_tests: bool,
+
+    /// Leave test functions and test files out of the results
+    #[arg(long, overrides_with = "include_tests")]
+    exclude_tests: bool,
+
+    /// Only search chunks of this crate, npm package or Go module
+    #[arg(long)]
+    package: Option<String>,
+
+    /// Show the class, impl, module or file each result belongs to under it
+    #[arg(long)]
+    expand_parents: bool,
 }
 
 impl Command for Query {
     async fn execute(&self) -> Result<()> {
+        let embedding = self.scan.embed_query(&self.query).await?;
+
+        let storage = self.scan.open_storage().await?;
+        let filter =
+            all_of([test_filter(!self.exclude_tests), package_filter(self.package.as_deref())]);
+        let hits = storage.search(embedding, self.limit, filter).await?;
+
+        let mut parents: HashMap<u64, CodeChunk> = HashMap::new();
+        if self.expand_parents {
+            // Parents that are hits themselves don't need fetching
+            parents.extend(hits.iter().map(|hit| (hit.chunk.id(), hit.chunk.clone())));
+            let fetched = storage.get_points(&missing_parents(&hits)).await?;
+            parents.extend(fetched.into_iter().map(|point| (point.id, point.chunk)));
+        }
+
+        for hit in &hits {
+            println!("{:.3}  {}", hit.score, describe(&hit.chunk));
+
+            if let Some(parent) = hit.chunk.parent_id.and_then(|id| parents.get(&id)) {
+                println!("       in {}", describe(parent));
+            }
+        }
+
         Ok(())
     }
 }
+
+/// Where a chunk is and what it holds, on one line
+fn describe(chunk: &CodeChunk) -> String {
+    f!(
+        "{}:{}-{}  {}",
+        chunk.path.display(),
+        chunk.start_line + 1,
+        chunk.end_line + 1,
+        chunk.symbol.as_deref().unwrap_or(&chunk.node_type),
+    )
+}
+
+/// Parents of the hits that aren't among the hits, each once and in the order they're first seen
+fn missing_parents(hits: &[SearchHit]) -> Vec<u64> {
+    let mut seen: HashSet<u64> = hits.iter().map(|hit| hit.chunk.id()).collect();
+
+    hits.iter()
+        .filter_map(|hit| hit.chunk.parent_id)
+        .filter(|id| seen.insert(*id))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hit(node_type: &str, start_byte: usize, parent_id: Option<u64>) -> SearchHit {
+        SearchHit {
+            score: 1.0,
+            chunk: CodeChunk {
+                node_type: node_type.to_string(),
+                start_byte,
+                parent_id,
+                ..Default::default()
+            },
+        }
+    }
+
+    #[test]
+    fn parents_are_fetched_once_unless_they_were_hits() {
+        let class = hit("class_definition", 0, None);
+        let hits = [
+            hit("function_definition", 10, Some(7)),
+            hit("function_definition", 20, Some(7)),
+            hit("function_definition", 30, Some(class.chunk.id())),
+            class,
+            hit("function_definition", 40, None),
+        ];
+
+        assert_eq!(missing_parents(&hits), vec![7]);
+    }
+}
@@ -1,22 +1,72 @@
-use std::{env, path::PathBuf, str::FromStr};
+use std::{
+    collections::{BTreeMap, HashSet},
+    env,
+    ffi::OsString,
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    iter,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::Arc,
+    time::Instant,
+};
 
 use clap::{Parser, ValueEnum};
 use serde::{Deserialize, Serialize};
-use tracing::{error, info};
+use tokio::sync::mpsc;
+use tracing::{error, info, warn};
 use url::Url;
 
-use super::Command;
+use super::{Command, progress::render_progress};
+#[cfg(feature = "local")]
+use crate::embedding::LocalEmbeddingClient;
+#[cfg(feature = "candle")]
+use crate::embedding::{CandleDevice, CandleEmbeddingClient};
 use crate::{
+    chunking::{
+        ChunkSizer, ChunkStrategyKind, ChunkerConfig, CodeChunk, CommentPolicy, ImportContext,
+        LicenseHeaders, Queries, sha256_hex,
+    },
     embedding::{
-        EmbeddingClient, EmbeddingClientImpl, HuggingFaceEmbeddingClient, OllamaEmbeddingClient,
-        OpenAIEmbeddingClient,
+        AzureOpenAIEmbeddingClient, CachedEmbeddingClient, ConfiguredRateLimits, CostEstimator,
+        DEFAULT_AZURE_API_VERSION, Embedding, EmbeddingCache, EmbeddingClient, EmbeddingConfig,
+        FallbackEmbeddingClient, HuggingFaceEmbeddingClient, JinaEmbeddingClient, JinaTask,
+        OllamaEmbeddingClient, OllamaOptions, OpenAIEmbeddingClient, PooledEmbeddingClient,
+        PrefixedEmbeddingClient, Prefixes, ProviderLimits, RateLimits, TeiEmbeddingClient,
+        Tokenizer, TruncatedEmbeddingClient, Usage, VoyageEmbeddingClient, cost_of, health_check,
     },
     prelude::*,
-    scanner::{CodebaseScanner, ScannerConfig},
-    storage::QdrantStorage,
-    utils::path_to_collection_name,
+    scanner::{
+        Checkpoint, ChunkFilters, CodebaseScanner, DEFAULT_MAX_FILE_SIZE, DEFAULT_SKIP_DIRS,
+        FileError, ScannerConfig, SkippedFile, chunk_codebase, dry_run,
+    },
+    storage::{QdrantStorage, StorageConfig},
+    utils::{
+        archive::{archive_name, extract},
+        common_ancestor,
+        credentials::Credentials,
+        git::{
+            changed_since, head_commit, last_indexed_commit, record_indexed_commit,
+            repository_name, shallow_clone, submodules, tracked_files,
+        },
+        lock::ScanLock,
+        path_to_collection_name,
+        retry::RetryPolicy,
+    },
 };
 
+/// `--changed-since` value standing for the commit the last scan indexed
+const LAST_INDEXED: &str = "last";
+
+/// Upper bounds of the file size ranges a dry run counts files in
+const SIZE_BUCKETS: &[(usize, &str)] = &[
+    (1024, "under 1 KiB"),
+    (10 * 1024, "1-10 KiB"),
+    (100 * 1024, "10-100 KiB"),
+    (1024 * 1024, "100 KiB-1 MiB"),
+    (usize::MAX, "1 MiB and over"),
+];
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 struct Address {
     pub url: Url,
@@ -40,6 +90,89 @@ enum ClientType {
     Ollama,
     OpenAI,
     HuggingFace,
+    AzureOpenAI,
+    Voyage,
+    Jina,
+    Local,
+    Candle,
+    Tei,
+}
+
+/// What a scan does with the git submodules under its roots
+#[derive(Debug, Clone, Copy, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum Submodules {
+    /// Scan them along with the rest, into the same collection
+    Include,
+    /// Leave them out
+    Skip,
+    /// Scan each into a collection of its own, named after the parent's and the submodule's
+    SeparateCollections,
+}
+
+/// Unit chunk sizes are measured in
+#[derive(Debug, Clone, Copy, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum ChunkUnit {
+    Bytes,
+    /// The embedding model's tokens, so chunks always fit its context window
+    Tokens,
+}
+
+impl ClientType {
+    fn name(&self) -> String {
+        f!("{self:?}").to_lowercase()
+    }
+
+    fn default_model(&self) -> &'static str {
+        match self {
+            Self::Ollama => "nomic-embed-text",
+            Self::OpenAI => "gpt-4o",
+            Self::HuggingFace => "Snowflake/snowflake-arctic-embed-l-v2.0",
+            Self::AzureOpenAI => "text-embedding-3-small",
+            Self::Voyage => "voyage-code-3",
+            Self::Jina => "jina-embeddings-v2-base-code",
+            Self::Local => "BAAI/bge-small-en-v1.5",
+            Self::Candle => "BAAI/bge-base-en-v1.5",
+            // Replaced by the model the server reports
+            Self::Tei => "",
+        }
+    }
+
+    /// Name the provider's key is stored under and the env var that overrides it
+    fn credential(&self) -> Option<(&'static str, &'static str)> {
+        match self {
+            Self::OpenAI => Some(("openai", "OPENAI_API_KEY")),
+            Self::HuggingFace => Some(("huggingface", "HUGGINGFACE_API_KEY")),
+            Self::AzureOpenAI => Some(("azure-openai", "AZURE_OPENAI_API_KEY")),
+            Self::Voyage => Some(("voyage", "VOYAGE_API_KEY")),
+            Self::Jina => Some(("jina", "JINA_API_KEY")),
+            Self::Ollama | Self::Local | Self::Candle | Self::Tei => None,
+        }
+    }
+}
+
+/// A fallback provider, given as `client` or `client:model`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct Provider {
+    client: ClientType,
+    model: Option<String>,
+}
+
+impl FromStr for Provider {
+    type Err = Error;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let (client, model) = match s.split_once(':') {
+            Some((client, model)) => (client, Some(model.to_string())),
+            None => (s, None),
+        };
+
+        let client = ClientType::from_str(client, true)
+            .map_err(|e| InvalidArgument(f!("Unknown provider {client}: {e}")))?;
+
+        Ok(Self { client, model })
+    }
 }
 
 #[derive(Debug, Parser, Serialize, Deserialize, Clone)]
@@ -47,13 +180,83 @@ pub struct Scan {
     #[arg(long, value_enum)]
     client: ClientType,
 
-    // Ollama-specific args
+    // Ollama and TEI server address
     #[arg(long, required_if_eq("client", "Ollama"))]
     address: Option<Address>,
 
     #[arg(long, short)]
     model: Option<String>,
 
+    /// Provider to fall back to when the ones before it fail, as `client` or `client:model`.
+    /// Repeat to build a chain, e.g. `--fallback openai:text-embedding-3-small`
+    #[arg(long)]
+    fallback: Vec<Provider>,
+
+    /// How long Ollama keeps the model loaded between requests (-1, 0, or e.g. 10m)
+    #[arg(long, default_value = "10m")]
+    keep_alive: Option<String>,
+
+    /// Context window Ollama loads the model with
+    #[arg(long)]
+    num_ctx: Option<u32>,
+
+    /// Base URL of an OpenAI-compatible server (vLLM, LM Studio, llama.cpp, LiteLLM)
+    #[arg(long)]
+    base_url: Option<Url>,
+
+    /// Azure OpenAI resource endpoint, e.g. https://my-resource.openai.azure.com/
+    #[arg(long, required_if_eq("client", "azure-open-ai"))]
+    azure_endpoint: Option<Url>,
+
+    /// Azure OpenAI deployment name
+    #[arg(long, required_if_eq("client", "azure-open-ai"))]
+    azure_deployment: Option<String>,
+
+    /// Azure OpenAI REST API version
+    #[arg(long, default_value = DEFAULT_AZURE_API_VERSION)]
+    azure_api_version: String,
+
+    /// Task jina-embeddings-v3 optimizes embeddings for
+    #[arg(long, value_enum, default_value = "retrieval.passage")]
+    jina_task: JinaTask,
+
+    /// Embed each Jina batch as one document so chunks keep their surrounding context
+    #[arg(long)]
+    late_chunking: bool,
+
+    /// Device the candle backend runs on (cuda and metal need the matching cargo feature)
+    #[cfg(feature = "candle")]
+    #[arg(long, value_enum, default_value = "cpu")]
+    device: CandleDevice,
+
+    /// Text prepended to every chunk, overriding the model's known document prefix
+    #[arg(long)]
+    document_prefix: Option<String>,
+
+    /// Text prepended to queries, overriding the model's known query prefix
+    #[arg(long)]
+    query_prefix: Option<String>,
+
+    /// Request shortened embeddings (OpenAI text-embedding-3 models only)
+    #[arg(long)]
+    dimensions: Option<usize>,
+
+    /// Keep only the first N dimensions of each embedding (Matryoshka trained models)
+    #[arg(long)]
+    truncate_dimensions: Option<usize>,
+
+    /// Abort before embedding if the estimated cost in USD is higher than this
+    #[arg(long)]
+    max_cost: Option<f64>,
+
+    /// Proxy for embedding provider requests, e.g. http://proxy.corp:3128
+    #[arg(long)]
+    proxy: Option<Url>,
+
+    /// PEM file of extra root certificates to trust for embedding provider requests
+    #[arg(long)]
+    ca_bundle: Option<PathBuf>,
+
     /// Qdrant URL
     #[arg(long, default_value = "http://localhost:6334")]
     qdrant_url: String,
@@ -66,17 +269,258 @@ pub struct Scan {
     #[arg(short, long)]
     extensions: Option<String>,
 
-    /// Chunk size limit (in bytes)
+    /// Chunk size limit, in --chunk-unit (defaults to 4096 bytes, or the model's context length
+    /// in tokens)
     #[arg(short, long)]
     chunk_size_limit: Option<usize>,
 
+    /// How files are cut into chunks: per syntax item, fixed-size windows, or syntax items merged
+    /// with their neighbours up to the size limit
+    #[arg(long, value_enum, default_value = "ast")]
+    chunk_strategy: ChunkStrategyKind,
+
+    /// Measure chunk sizes and overlap in bytes or in the embedding model's tokens
+    #[arg(long, value_enum, default_value = "bytes")]
+    chunk_unit: ChunkUnit,
+
     /// Percentage of overlap between chunks (default: 10%)
     #[arg(long, default_value = "10")]
     overlap_percentage: Option<usize>,
 
-    /// Path to the codebase root
-    #[arg(short, long)]
-    path: PathBuf,
+    /// Which comments to keep in chunks: doc comments and docstrings, all of them, or none
+    #[arg(long, value_enum, default_value = "keep-doc-comments")]
+    comments: CommentPolicy,
+
+    /// License headers at the top of files: strip them, strip them but keep the text in
+    /// metadata, or keep them in chunks
+    #[arg(long, value_enum, default_value = "strip")]
+    license_headers: LicenseHeaders,
+
+    /// Imports to put in front of each chunk: none, the ones it references, or all of them
+    #[arg(long, value_enum, default_value = "referenced")]
+    imports: ImportContext,
+
+    /// Also chunk control flow statements such as Python's `if`, `for` and `while` blocks
+    #[arg(long)]
+    statement_chunks: bool,
+
+    /// Also scan generated code, minified bundles and lockfiles
+    #[arg(long)]
+    include_generated: bool,
+
+    /// Only scan files git tracks, leaving out untracked build output, environments and editor
+    /// files
+    #[arg(long)]
+    tracked_only: bool,
+
+    /// Descend at most this many directories below each root, 1 scans only the files directly in
+    /// it
+    #[arg(long)]
+    max_depth: Option<usize>,
+
+    /// Also scan git repositories nested inside a root that aren't its submodules, e.g. clones
+    /// in a workspace directory, which are skipped by default
+    #[arg(long)]
+    nested_repos: bool,
+
+    /// Scan git submodules into the same collection, skip them, or scan each into its own
+    #[arg(long, value_enum, default_value = "skip")]
+    submodules: Submodules,
+
+    /// Collection a submodule is scanned into with `--submodules separate-collections`
+    #[arg(skip)]
+    collection_override: Option<String>,
+
+    /// Paths `--files-from` listed, already read for a submodule's scan
+    #[arg(skip)]
+    listed_paths: Option<Vec<PathBuf>>,
+
+    /// Run git blame on every file and store the author of most of each chunk's lines and when
+    /// they last changed, so searches can favor recent code
+    #[arg(long)]
+    with_blame: bool,
+
+    /// Also scan hidden files and directories, like ripgrep's --hidden
+    #[arg(long)]
+    hidden: bool,
+
+    /// Directories never scanned, hidden or not (comma-separated). Replaces the default list of
+    /// version control, build output and dependency directories
+    #[arg(
+        long,
+        value_delimiter = ',',
+        default_values = DEFAULT_SKIP_DIRS.iter().copied()
+    )]
+    skip_dirs: Vec<OsString>,
+
+    /// Follow symlinked directories, e.g. vendored trees, instead of skipping them. Links back up
+    /// the tree are detected and skipped
+    #[arg(long)]
+    follow_symlinks: bool,
+
+    /// Most MiB of chunks and their embeddings held between the parse, embed and store stages.
+    /// Parsing waits for stored chunks to free room, so huge codebases scan on small machines
+    #[arg(long)]
+    max_memory: Option<u64>,
+
+    /// Skip files larger than this many bytes without reading them
+    #[arg(long, default_value_t = DEFAULT_MAX_FILE_SIZE)]
+    max_file_size: u64,
+
+    /// Only scan files matching this glob, e.g. `src/**/*.rs`. Repeat for more patterns
+    #[arg(long)]
+    include: Vec<String>,
+
+    /// Leave out files and directories matching this glob, e.g. `**/fixtures/**`, on top of
+    /// `.gitignore` and `.sherpaignore` rules. Repeat for more patterns
+    #[arg(long)]
+    exclude: Vec<String>,
+
+    /// Reparse and re-embed every file, even the ones unchanged since the last scan
+    #[arg(long)]
+    full: bool,
+
+    /// Pick an interrupted scan up where it stopped, skipping the files it already stored
+    #[arg(long)]
+    resume: bool,
+
+    /// Only scan files changed since this git commit or ref, removing deleted ones. Without a
+    /// value, since the commit the last scan indexed
+    #[arg(long, num_args = 0..=1, default_missing_value = LAST_INDEXED)]
+    changed_since: Option<String>,
+
+    /// Only scan the files listed in this file, one path per line, or in stdin with `-`. Files
+    /// that aren't listed keep their chunks, listed ones that are gone have theirs removed
+    #[arg(long, conflicts_with = "changed_since")]
+    files_from: Option<PathBuf>,
+
+    /// Exit with an error when any file fails to be read, parsed or embedded
+    #[arg(long)]
+    fail_on_error: bool,
+
+    /// Exit with an error when more files than this fail to be read, parsed or embedded
+    #[arg(long, conflicts_with = "fail_on_error")]
+    max_errors: Option<usize>,
+
+    /// Write the chunks of the codebase to this file as JSON lines, or to stdout with `-`, and
+    /// stop there without calling the embedding provider or touching storage
+    #[arg(long, conflicts_with = "dry_run")]
+    emit_chunks: Option<PathBuf>,
+
+    /// Scan even when another scan of the collection holds its lock, e.g. one that crashed and
+    /// left the lock behind
+    #[arg(long)]
+    force: bool,
+
+    /// Walk and chunk the codebase, then list the files a scan would index with chunk counts per
+    /// language and file sizes, without calling the embedding provider or touching storage
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Directory of tree-sitter query overrides, e.g. `rust.scm` or `python-statements.scm`
+    /// (defaults to code-sherpa/queries in the user config directory)
+    #[arg(long)]
+    queries_dir: Option<PathBuf>,
+
+    /// Number of embedding requests sent concurrently
+    #[arg(long, default_value = "4")]
+    embed_concurrency: Option<usize>,
+
+    /// Times a rate limited or failed embedding request is retried
+    #[arg(long, default_value = "5")]
+    embed_retries: usize,
+
+    /// Requests per minute allowed by the primary provider's account, over the config file.
+    /// Fallbacks are only limited by the config file
+    #[arg(long)]
+    requests_per_minute: Option<u32>,
+
+    /// Tokens per minute allowed by the primary provider's account, over the config file.
+    /// Fallbacks are only limited by the config file
+    #[arg(long)]
+    tokens_per_minute: Option<u32>,
+
+    /// Number of upsert batches sent to Qdrant concurrently
+    #[arg(long, default_value = "4")]
+    upsert_parallelism: Option<usize>,
+
+    /// Don't wait for Qdrant to apply upserts before moving on
+    #[arg(long)]
+    no_wait: bool,
+
+    /// Directory for the embedding cache and local models (defaults to the user cache directory)
+    #[arg(long)]
+    cache_dir: Option<PathBuf>,
+
+    /// Always request fresh embeddings instead of reusing cached ones
+    #[arg(long)]
+    no_cache: bool,
+
+    /// Config file to read API keys and chunk filters from (defaults to code-sherpa/config.toml
+    /// in the user config directory). Keys go in an `[api_keys]` table, e.g. `openai = "sk-..."`.
+    /// Each `[[filters]]` entry has a regex `pattern`, an `action` of `drop`, `replace` (with a
+    /// `replacement`) or `tag` (with a `tag`), and optionally the `languages` it applies to.
+    /// Account limits go in `[rate_limits.<provider>]` or `[rate_limits."<provider>:<model>"]`
+    /// tables with `requests_per_minute` and `tokens_per_minute`
+    #[arg(long)]
+    config: Option<PathBuf>,
+
+    /// Print embedding progress as NDJSON events on stdout instead of a progress bar, and a dry
+    /// run's findings as JSON
+    #[arg(long)]
+    json: bool,
+
+    /// Write a JSON usage report (tokens, requests, stage timings, cost) to this file
+    #[arg(long)]
+    report: Option<PathBuf>,
+
+    /// Path to a codebase root, repeat it to scan several into one collection. Files are stored
+    /// under the path they're walked by, so `backend/src/lib.rs` and `frontend/src/lib.rs` stay
+    /// apart
+    #[arg(short, long = "path", required_unless_present_any = ["repo", "archive"])]
+    paths: Vec<PathBuf>,
+
+    /// Git remote to shallow-clone into the cache directory and scan instead of a local path.
+    /// The collection is named after the repository
+    #[arg(long, conflicts_with = "paths")]
+    repo: Option<Url>,
+
+    /// `.tar`, `.tar.gz`, `.tgz` or `.zip` archive to unpack into the cache directory and scan
+    /// instead of a local path. The collection is named after the archive
+    #[arg(long, conflicts_with_all = ["paths", "repo"])]
+    archive: Option<PathBuf>,
+}
+
+/// Clients, storage and scanner a scan sets up, kept to rescan with while watching
+pub struct ScanSession {
+    scanner: CodebaseScanner<
+        TruncatedEmbeddingClient<FallbackEmbeddingClient<Box<dyn EmbeddingClient>>>,
+        QdrantStorage,
+    >,
+    /// The primary model, used for the cost estimate and the report
+    model: String,
+    usage: Arc<Usage>,
+}
+
+/// What a scan sent to the embedding provider and where its time went
+#[derive(Debug, Serialize)]
+struct UsageReport {
+    chunks_processed: usize,
+    embeddings_generated: usize,
+    failed_chunks: usize,
+    tokens: usize,
+    requests: usize,
+    parse_seconds: f64,
+    embed_seconds: f64,
+    store_seconds: f64,
+    total_seconds: f64,
+    estimated_cost: Option<f64>,
+    /// Chunks embedded by each provider, more than one entry means a fallback was used
+    providers: BTreeMap<String, usize>,
+    /// Files left out of the index, e.g. for being too large
+    skipped_files: Vec<SkippedFile>,
+    /// Files that failed to be walked, read, parsed or embedded
+    errors: Vec<FileError>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -87,31 +531,684 @@ enum ClientConfig {
     HuggingFace { api_key: String, model: String },
 }
 
+impl Scan {
+    fn cache_dir(&self) -> Result<PathBuf> {
+        self.cache_dir
+            .clone()
+            .or_else(|| dirs::cache_dir().map(|dir| dir.join("code-sherpa")))
+            .ok_or(Missing(String::from("cache directory, set --cache-dir")))
+    }
+
+    /// Build the client for one provider, returning it with the model it embeds with
+    async fn build_client(
+        &self,
+        client_type: &ClientType,
+        model: Option<String>,
+        credentials: &Credentials,
+        embedding_config: EmbeddingConfig,
+    ) -> Result<(Box<dyn EmbeddingClient>, String)> {
+        let mut model = model.unwrap_or(client_type.default_model().to_string());
+
+        // OpenAI-compatible servers don't always need a key, so each client checks its own
+        let api_key = client_type
+            .credential()
+            .and_then(|(provider, env_var)| credentials.api_key(provider, env_var));
+
+        let client: Box<dyn EmbeddingClient> = match client_type {
+            ClientType::Ollama => {
+                let address = self.address.clone().unwrap_or_else(|| {
+                    Address::from_str("http://localhost:11434")
+                        .expect("Default address should be valid")
+                });
+                Box::new(OllamaEmbeddingClient::new(
+                    address.url,
+                    address.port.unwrap_or(11434),
+                    &model,
+                    self.chunk_size_limit,
+                    OllamaOptions {
+                        keep_alive: self.keep_alive.clone(),
+                        num_ctx: self.num_ctx,
+                    },
+                    embedding_config,
+                )?)
+            },
+            ClientType::OpenAI => Box::new(OpenAIEmbeddingClient::new(
+                api_key.as_deref(),
+                &model,
+                self.base_url.clone(),
+                self.dimensions,
+                embedding_config,
+            )?),
+            ClientType::HuggingFace => {
+                let api_key = api_key.ok_or(Missing(String::from(
+                    "HUGGINGFACE_API_KEY environment variable",
+                )))?;
+
+                Box::new(HuggingFaceEmbeddingClient::new(
+                    &api_key,
+                    &model,
+                    embedding_config,
+                )?)
+            },
+            ClientType::AzureOpenAI => {
+                let api_key = api_key.ok_or(Missing(String::from(
+                    "AZURE_OPENAI_API_KEY environment variable",
+                )))?;
+                let endpoint = self
+                    .azure_endpoint
+                    .as_ref()
+                    .ok_or(Missing(String::from("--azure-endpoint")))?;
+                let deployment = self
+                    .azure_deployment
+                    .as_deref()
+                    .ok_or(Missing(String::from("--azure-deployment")))?;
+
+                Box::new(AzureOpenAIEmbeddingClient::new(
+                    endpoint,
+                    deployment,
+                    &self.azure_api_version,
+                    &api_key,
+                    &model,
+                    self.dimensions,
+                    embedding_config,
+                )?)
+            },
+            ClientType::Voyage => {
+                let api_key =
+                    api_key.ok_or(Missing(String::from("VOYAGE_API_KEY environment variable")))?;
+
+                Box::new(VoyageEmbeddingClient::new(
+                    &api_key,
+                    &model,
+                    embedding_config,
+                )?)
+            },
+            ClientType::Jina => {
+                let api_key =
+                    api_key.ok_or(Missing(String::from("JINA_API_KEY environment variable")))?;
+
+                Box::new(JinaEmbeddingClient::new(
+                    &api_key,
+                    &model,
+                    self.jina_task,
+                    self.late_chunking,
+                    embedding_config,
+                )?)
+            },
+            #[cfg(feature = "local")]
+            ClientType::Local => Box::new(LocalEmbeddingClient::new(
+                &model,
+                self.cache_dir()?.join("models"),
+            )?),
+            #[cfg(not(feature = "local"))]
+            ClientType::Local => {
+                return Err(InvalidArgument(String::from(
+                    "code-sherpa was built without the `local` feature",
+                )));
+            },
+            #[cfg(feature = "candle")]
+            ClientType::Candle => Box::new(CandleEmbeddingClient::new(
+                &model,
+                self.device,
+                self.cache_dir()?.join("models"),
+            )?),
+            #[cfg(not(feature = "candle"))]
+            ClientType::Candle => {
+                return Err(InvalidArgument(String::from(
+                    "code-sherpa was built without the `candle` feature",
+                )));
+            },
+            ClientType::Tei => {
+                let address = self.address.clone().unwrap_or_else(|| {
+                    Address::from_str("http://localhost:8080")
+                        .expect("Default address should be valid")
+                });
+                let client = TeiEmbeddingClient::new(address.url, embedding_config)?;
+
+                // TEI serves a single model chosen when the server starts
+                model = client.model_id().await?;
+                info!("TEI server is serving {model}");
+
+                Box::new(client)
+            },
+        };
+
+        Ok((client, model))
+    }
+
+    /// Where the commit of the last scan of this collection is recorded
+    fn commit_record(&self) -> Result<PathBuf> {
+        Ok(self.cache_dir()?.join("indexed-commits").join(self.collection_name()))
+    }
+
+    /// A scan of each submodule under the roots into a collection of its own, with the same
+    /// settings otherwise
+    fn submodule_scans(&self, listed: Option<&[PathBuf]>) -> Result<Vec<Scan>> {
+        let mut scans = Vec::new();
+
+        for root in self.roots()? {
+            for (name, path) in submodules(&root)? {
+                scans.push(Scan {
+                    paths: vec![path],
+                    repo: None,
+                    archive: None,
+                    collection_override: Some(f!(
+                        "{}-{}",
+                        self.collection_name(),
+                        name.replace('/', "-")
+                    )),
+                    listed_paths: listed.map(<[PathBuf]>::to_vec),
+                    ..self.clone()
+                });
+            }
+        }
+
+        Ok(scans)
+    }
+
+    /// Lockfile held while this collection is scanned
+    fn lock_path(&self) -> Result<PathBuf> {
+        Ok(self.cache_dir()?.join("locks").join(f!("{}.lock", self.collection_name())))
+    }
+
+    /// Where an interrupted scan of this collection left off
+    fn checkpoint_path(&self) -> Result<PathBuf> {
+        Ok(self
+            .cache_dir()?
+            .join("checkpoints")
+            .join(f!("{}.jsonl", self.collection_name())))
+    }
+
+    /// Where `--repo` is cloned, one directory per remote so rescans reuse the clone
+    fn clone_dir(&self, url: &Url) -> Result<PathBuf> {
+        Ok(self
+            .cache_dir()?
+            .join("repos")
+            .join(url.host_str().unwrap_or("local"))
+            .join(url.path().trim_matches('/').trim_end_matches(".git")))
+    }
+
+    /// Where `--archive` is unpacked, one directory per archive path so rescans of a newer
+    /// release land in the same place
+    fn archive_dir(&self, archive: &Path) -> Result<PathBuf> {
+        let id = sha256_hex(&archive.canonicalize()?.to_string_lossy());
+
+        Ok(self
+            .cache_dir()?
+            .join("archives")
+            .join(f!("{}-{}", archive_name(archive), &id[..12])))
+    }
+
+    /// Collection named after the remote repository, the archive, the codebase root, or the
+    /// directory holding all of the roots
+    fn collection_name(&self) -> String {
+        if let Some(collection) = &self.collection_override {
+            return collection.clone();
+        }
+        if let Some(url) = &self.repo {
+            return repository_name(url);
+        }
+        if let Some(archive) = &self.archive {
+            return archive_name(archive);
+        }
+
+        match self.paths.as_slice() {
+            [path] => path_to_collection_name(path),
+            paths => {
+                let canonical: Vec<_> = paths
+                    .iter()
+                    .map(|path| path.canonicalize().unwrap_or_else(|_| path.clone()))
+                    .collect();
+
+                path_to_collection_name(&common_ancestor(&canonical))
+            },
+        }
+    }
+
+    /// Files `listed` by `--files-from`, or changed since `--changed-since`, or `None` to scan
+    /// everything
+    fn changed_files(&self, listed: Option<&[PathBuf]>) -> Result<Option<HashSet<PathBuf>>> {
+        if let Some(listed) = listed {
+            let listed = self.listed_files(listed)?;
+            info!("{} listed files to scan", listed.len());
+
+            return Ok(Some(listed));
+        }
+
+        let Some(reference) = &self.changed_since else {
+            return Ok(None);
+        };
+
+        let reference = match reference.as_str() {
+            LAST_INDEXED => last_indexed_commit(&self.commit_record()?).ok_or(Missing(
+                String::from("indexed commit, run a scan without --changed-since first"),
+            ))?,
+            reference => reference.to_string(),
+        };
+
+        let mut changed = HashSet::new();
+        for root in self.roots()? {
+            changed.extend(changed_since(&root, &reference)?);
+        }
+        info!("{} files changed since {reference}", changed.len());
+
+        Ok(Some(changed))
+    }
+
+    /// Paths in the `--files-from` list, one per line. Lines can be relative to the working
+    /// directory, as `git diff --name-only` prints them from the repository root. The list is
+    /// only read once, so submodule scans don't wait on stdin again
+    fn listed_paths(&self) -> Result<Option<Vec<PathBuf>>> {
+        if self.listed_paths.is_some() {
+            return Ok(self.listed_paths.clone());
+        }
+        let Some(list) = &self.files_from else {
+            return Ok(None);
+        };
+
+        let text = match list.to_str() {
+            Some("-") => io::read_to_string(io::stdin())?,
+            _ => fs::read_to_string(list)?,
+        };
+
+        Ok(Some(
+            text.lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                // Deleted files can't be resolved, and aren't walked either
+                .filter_map(|line| Path::new(line).canonicalize().ok())
+                .collect(),
+        ))
+    }
+
+    /// The `listed` paths under this scan's roots, joined onto the root holding them the way the
+    /// scanner walks them
+    fn listed_files(&self, listed: &[PathBuf]) -> Result<HashSet<PathBuf>> {
+        let roots = self
+            .roots()?
+            .into_iter()
+            .map(|root| Ok((root.canonicalize()?, root)))
+            .collect::<Result<Vec<_>>>()?;
+
+        let listed = listed
+            .iter()
+            .filter_map(|file| {
+                roots.iter().find_map(|(dir, root)| {
+                    file.strip_prefix(dir).ok().map(|relative| root.join(relative))
+                })
+            })
+            .collect();
+
+        Ok(listed)
+    }
+
+    /// What to scan and how to chunk it, leaving out the cost estimate and checkpoint only a
+    /// real scan uses
+    fn scanner_config(
+        &self,
+        roots: &[PathBuf],
+        changed: Option<HashSet<PathBuf>>,
+        sizer: ChunkSizer,
+        chunk_size_limit: Option<usize>,
+    ) -> Result<ScannerConfig> {
+        Ok(ScannerConfig {
+            chunker: self.chunk_strategy.build(ChunkerConfig {
+                max_chunk_size: chunk_size_limit,
+                overlap_percentage: self.overlap_percentage,
+                comments: self.comments,
+                sizer,
+                statement_chunks: self.statement_chunks,
+                queries: Queries::load(self.queries_dir.as_deref())?,
+                imports: self.imports,
+                license_headers: self.license_headers,
+            }),
+            cost_estimator: None,
+            include_generated: self.include_generated,
+            include: self.include.clone(),
+            exclude: self.exclude.clone(),
+            incremental: !self.full,
+            changed,
+            checkpoint: None,
+            max_file_size: self.max_file_size,
+            follow_symlinks: self.follow_symlinks,
+            hidden: self.hidden,
+            skip_dirs: self.skip_dirs.clone(),
+            max_depth: self.max_depth,
+            nested_repos: self.nested_repos,
+            submodules: matches!(self.submodules, Submodules::Include),
+            blame: self.with_blame,
+            max_memory: self.max_memory.map(|mib| mib * 1024 * 1024),
+            filters: ChunkFilters::load(self.config.as_deref())?,
+            tracked: match self.tracked_only {
+                true => Some(
+                    roots
+                        .iter()
+                        .map(|root| tracked_files(root))
+                        .collect::<Result<Vec<_>>>()?
+                        .into_iter()
+                        .flatten()
+                        .collect(),
+                ),
+                false => None,
+            },
+        })
+    }
+
+    /// Tokenizer chunks are measured with for `model`
+    fn tokenizer(&self, model: &str) -> Tokenizer {
+        match self.client {
+            ClientType::OpenAI | ClientType::AzureOpenAI => Tokenizer::for_openai(model),
+            ClientType::Voyage => Tokenizer::for_huggingface(&f!("voyageai/{model}")),
+            ClientType::Jina => Tokenizer::for_huggingface(&f!("jinaai/{model}")),
+            // Hub repo ids, or Ollama names mapped to them
+            _ => Tokenizer::for_huggingface(model),
+        }
+    }
+
+    /// How chunks are measured when there's no provider to ask for its context window
+    fn offline_sizer(&self) -> ChunkSizer {
+        match self.chunk_unit {
+            ChunkUnit::Bytes => ChunkSizer::Bytes,
+            ChunkUnit::Tokens => {
+                if self.chunk_size_limit.is_none() {
+                    warn!(
+                        "Without --chunk-size-limit the model isn't asked for its context \
+                         window, chunks aren't split to fit it"
+                    );
+                }
+
+                let model = self.model.as_deref().unwrap_or(self.client.default_model());
+                ChunkSizer::Tokens(self.tokenizer(model))
+            },
+        }
+    }
+
+    /// Walk and chunk the codebase without a provider or storage, then print what a scan would
+    /// index
+    fn dry_run(&self, roots: &[PathBuf], changed: Option<HashSet<PathBuf>>) -> Result<()> {
+        let config =
+            self.scanner_config(roots, changed, self.offline_sizer(), self.chunk_size_limit)?;
+        let results = dry_run(config, roots)?;
+
+        if self.json {
+            println!("{}", serde_json::to_string_pretty(&results)?);
+            return Ok(());
+        }
+
+        for file in &results.files {
+            println!(
+                "{}  {} bytes, {} chunks ({})",
+                file.path.display(),
+                file.bytes,
+                file.chunks,
+                file.language
+            );
+        }
+
+        // Files, chunks and bytes per language
+        let mut languages: BTreeMap<&str, (usize, usize, usize)> = BTreeMap::new();
+        for file in &results.files {
+            let language = languages.entry(&file.language).or_default();
+            language.0 += 1;
+            language.1 += file.chunks;
+            language.2 += file.bytes;
+        }
+
+        println!(
+            "\n{:<12} {:>8} {:>8} {:>12}",
+            "language", "files", "chunks", "bytes"
+        );
+        for (language, (files, chunks, bytes)) in &languages {
+            println!("{language:<12} {files:>8} {chunks:>8} {bytes:>12}");
+        }
+
+        println!("\n{:<16} {:>8}", "file size", "files");
+        let mut smallest = 0;
+        for (largest, label) in SIZE_BUCKETS {
+            let files = results
+                .files
+                .iter()
+                .filter(|file| (smallest..*largest).contains(&file.bytes))
+                .count();
+            println!("{label:<16} {files:>8}");
+            smallest = *largest;
+        }
+
+        println!(
+            "\n{} files, {} chunks would be indexed. {} files skipped, {} failed",
+            results.files.len(),
+            results.files.iter().map(|file| file.chunks).sum::<usize>(),
+            results.skipped_files.len(),
+            results.file_errors.len()
+        );
+
+        for error in &results.file_errors {
+            warn!(
+                "  {} ({}): {}",
+                error.path.display(),
+                error.stage,
+                error.reason
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Walk and chunk the codebase without a provider or storage, writing every chunk to `out` as
+    /// a JSON line, or to stdout with `-`
+    fn emit_chunks(
+        &self,
+        roots: &[PathBuf],
+        changed: Option<HashSet<PathBuf>>,
+        out: &Path,
+    ) -> Result<()> {
+        let mut writer: BufWriter<Box<dyn Write>> = BufWriter::new(match out.to_str() {
+            Some("-") => Box::new(io::stdout()),
+            _ => Box::new(File::create(out)?),
+        });
+
+        let config =
+            self.scanner_config(roots, changed, self.offline_sizer(), self.chunk_size_limit)?;
+        let (mut files, mut chunks) = (0, 0);
+
+        let (skipped, errors) = chunk_codebase(config, roots, |file| {
+            for chunk in &file.chunks {
+                serde_json::to_writer(&mut writer, chunk)?;
+                writeln!(writer)?;
+            }
+
+            files += 1;
+            chunks += file.chunks.len();
+            Ok(())
+        })?;
+        writer.flush()?;
+
+        info!(
+            "Wrote {chunks} chunks from {files} files to {}, {} files skipped",
+            out.display(),
+            skipped.len()
+        );
+
+        for error in &errors {
+            warn!(
+                "  {} ({}): {}",
+                error.path.display(),
+                error.stage,
+                error.reason
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Options besides the model that change the embeddings `client_type` returns, so cached ones
+    /// are only reused for the same options
+    fn cache_settings(&self, client_type: &ClientType) -> String {
+        match client_type {
+            ClientType::OpenAI | ClientType::AzureOpenAI => self
+                .dimensions
+                .map(|dimensions| f!("dimensions={dimensions}"))
+                .unwrap_or_default(),
+            ClientType::Jina => f!(
+                "task={:?},late_chunking={}",
+                self.jina_task,
+                self.late_chunking
+            ),
+            _ => String::new(),
+        }
+    }
+
+    /// Pooling inside the cache stores one embedding per chunk, however many parts it took.
+    /// Prefixing before the cache keeps cached embeddings tied to the prefix they were made with
+    async fn layer_client(
+        &self,
+        client: Box<dyn EmbeddingClient>,
+        client_type: &ClientType,
+        model: &str,
+        cache: Option<EmbeddingCache>,
+    ) -> Result<Box<dyn EmbeddingClient>> {
+        let prefixes = Prefixes::resolve(
+            model,
+            self.document_prefix.clone(),
+            self.query_prefix.clone(),
+        );
+
+        if !prefixes.is_empty() {
+            info!("Using document prefix {:?} for {model}", prefixes.document);
+        }
+
+        let client = PooledEmbeddingClient::new(client).await?;
+        let client =
+            CachedEmbeddingClient::new(client, model, self.cache_settings(client_type), cache);
+
+        Ok(Box::new(PrefixedEmbeddingClient::new(client, prefixes)))
+    }
+}
+
 impl Command for Scan {
     async fn execute(&self) -> Result<()> {
-        if !self.path.exists() {
-            error!("Path does not exist: {}", self.path.display());
-            return Err(NotFound(self.path.clone()));
+        self.execute_with(&mut None).await
+    }
+}
+
+impl Scan {
+    /// Fetch the codebase and scan it with its submodules, setting up `session` for scans that
+    /// follow
+    pub async fn execute_with(&self, session: &mut Option<ScanSession>) -> Result<()> {
+        if let Some(url) = &self.repo {
+            shallow_clone(url, &self.clone_dir(url)?)?;
+        }
+        if let Some(archive) = &self.archive {
+            extract(archive, &self.archive_dir(archive)?)?;
         }
 
-        let model = self.model.clone().unwrap_or(
-            match self.client {
-                ClientType::Ollama => "nomic-embed-text",
-                ClientType::OpenAI => "gpt-4o",
-                ClientType::HuggingFace => "snowflake-arctic-embed-l-v2.0",
+        let listed = self.listed_paths()?;
+        self.run(self.changed_files(listed.as_deref())?, session).await?;
+
+        if let Submodules::SeparateCollections = self.submodules {
+            for scan in self.submodule_scans(listed.as_deref())? {
+                info!("Scanning submodule {}", scan.collection_name());
+                Box::pin(scan.execute()).await?;
             }
-            .to_string(),
+        }
+
+        Ok(())
+    }
+
+    /// Embed `query` with the primary provider, set up the way the scan embeds chunks, behind the
+    /// model's query prefix
+    pub async fn embed_query(&self, query: &str) -> Result<Embedding> {
+        let credentials = Credentials::load(self.config.as_deref())?;
+        let embedding_config = EmbeddingConfig {
+            retry: RetryPolicy {
+                max_retries: self.embed_retries,
+                ..Default::default()
+            },
+            proxy: self.proxy.clone(),
+            ca_bundle: self.ca_bundle.clone(),
+            ..Default::default()
+        };
+
+        let (client, model) = self
+            .build_client(
+                &self.client,
+                self.model.clone(),
+                &credentials,
+                embedding_config,
+            )
+            .await?;
+
+        let client = TruncatedEmbeddingClient::new(client, &model, self.truncate_dimensions)?;
+        let prefixes = Prefixes::resolve(
+            &model,
+            self.document_prefix.clone(),
+            self.query_prefix.clone(),
         );
+        let query = CodeChunk {
+            content: prefixes.query(query),
+            ..Default::default()
+        };
 
-        let api_key = match self.client {
-            ClientType::Ollama => Ok(String::from("")),
-            ClientType::OpenAI => env::var("OPENAI_API_KEY"),
-            ClientType::HuggingFace => env::var("HUGGINGFACE_API_KEY"),
+        client.embed(&[query]).await?.pop().ok_or(Error::Embedding(String::from(
+            "No embedding returned for the query",
+        )))
+    }
+
+    /// The collection the scan stores into, which must already exist
+    pub async fn open_storage(&self) -> Result<QdrantStorage> {
+        QdrantStorage::open(&self.qdrant_url, &self.collection_name()).await
+    }
+
+    /// Codebase roots being scanned. A root inside another one is dropped, so no file is walked,
+    /// and stored, under two paths
+    pub fn roots(&self) -> Result<Vec<PathBuf>> {
+        if let Some(url) = &self.repo {
+            return Ok(vec![self.clone_dir(url)?]);
+        }
+        if let Some(archive) = &self.archive {
+            return Ok(vec![self.archive_dir(archive)?]);
         }
-        .map_err(|_| Missing(String::from("API key environment variable not set")))?;
 
-        info!("Scanning codebase at {}", self.path.display());
-        info!("Using embedding model: {}", model);
+        let canonical = self
+            .paths
+            .iter()
+            .map(|path| {
+                path.canonicalize().map_err(|_| {
+                    error!("Path does not exist: {}", path.display());
+                    NotFound(path.clone())
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let roots =
+            self.paths
+                .iter()
+                .zip(&canonical)
+                .enumerate()
+                .filter(|&(i, (path, dir))| {
+                    let nested = canonical.iter().enumerate().any(|(j, other)| {
+                        j != i && dir.starts_with(other) && (dir != other || j < i)
+                    });
+
+                    if nested {
+                        warn!("{} is already scanned under another path", path.display());
+                    }
+
+                    !nested
+                })
+                .map(|(_, (path, _))| path.clone())
+                .collect();
+
+        Ok(roots)
+    }
+
+    /// Build the embedding clients, checking each provider is up, the storage and the scanner
+    async fn start_session(&self, roots: &[PathBuf]) -> Result<ScanSession> {
+        let credentials = Credentials::load(self.config.as_deref())?;
+
+        for root in roots {
+            info!("Scanning codebase at {}", root.display());
+        }
 
         // Parse extensions filter if provided
         let extensions = self
@@ -123,58 +1220,325 @@ impl Command for Scan {
             info!("Filtering by extensions: {}", exts.join(", "));
         }
 
-        if let Some(chunk_size) = self.chunk_size_limit {
-            info!("Using chunk size limit: {} bytes", chunk_size);
-        }
-
         info!(
             "Using chunk overlap: {}%",
             self.overlap_percentage.unwrap_or(10)
         );
 
-        let mut embedding_client = match self.client {
-            ClientType::Ollama => {
-                let address = self.address.clone().unwrap_or_else(|| {
-                    Address::from_str("http://localhost:11434")
-                        .expect("Default address should be valid")
-                });
-                EmbeddingClientImpl::Ollama(OllamaEmbeddingClient::new(
-                    address.url,
-                    address.port.unwrap_or(11434),
-                    &model,
-                    self.chunk_size_limit,
-                ))
-            },
-            ClientType::OpenAI => {
-                EmbeddingClientImpl::OpenAI(OpenAIEmbeddingClient::new(&api_key, &model))
-            },
-            ClientType::HuggingFace => {
-                EmbeddingClientImpl::HuggingFace(HuggingFaceEmbeddingClient::new(&api_key, &model))
+        let embedding_config = EmbeddingConfig {
+            concurrency: self.embed_concurrency,
+            retry: RetryPolicy {
+                max_retries: self.embed_retries,
+                ..Default::default()
             },
+            proxy: self.proxy.clone(),
+            ca_bundle: self.ca_bundle.clone(),
+            ..Default::default()
         };
+        let usage = embedding_config.usage.clone();
+
+        let cache = if self.no_cache {
+            None
+        } else {
+            let cache_dir = self.cache_dir()?;
+
+            info!("Using embedding cache at {}", cache_dir.display());
+            Some(EmbeddingCache::open(&cache_dir.join("embeddings"))?)
+        };
+
+        // The primary provider first, then each fallback in the order given
+        let providers = iter::once(Provider {
+            client: self.client.clone(),
+            model: self.model.clone(),
+        })
+        .chain(self.fallback.iter().cloned());
+
+        let mut clients = Vec::new();
+        let mut primary_model = None;
+        let configured_limits = ConfiguredRateLimits::load(self.config.as_deref())?;
+
+        for (i, provider) in providers.enumerate() {
+            let requested_model =
+                provider.model.as_deref().unwrap_or(provider.client.default_model());
+            let configured = configured_limits.get(&provider.client.name(), requested_model);
+            // The flags only apply to the primary provider, the one they're given next to
+            let limits = match i {
+                0 => ProviderLimits {
+                    requests_per_minute: self
+                        .requests_per_minute
+                        .or(configured.requests_per_minute),
+                    tokens_per_minute: self.tokens_per_minute.or(configured.tokens_per_minute),
+                },
+                _ => configured,
+            };
+
+            let (client, model) = self
+                .build_client(
+                    &provider.client,
+                    provider.model,
+                    &credentials,
+                    EmbeddingConfig {
+                        rate_limits: RateLimits::new(
+                            limits.requests_per_minute,
+                            limits.tokens_per_minute,
+                        )
+                        .map(Arc::new),
+                        ..embedding_config.clone()
+                    },
+                )
+                .await?;
+
+            let name = f!("{}:{model}", provider.client.name());
+
+            // Check the raw client so the cache can't hide a broken provider
+            if let Err(e) = health_check(client.as_ref()).await {
+                let error = HealthCheck {
+                    provider: name,
+                    source: Box::new(e),
+                };
+
+                // A broken fallback only loses its place in the chain
+                if primary_model.is_some() {
+                    warn!("Skipping fallback, {error}");
+                    continue;
+                }
+
+                return Err(error);
+            }
+
+            info!("Using embedding model: {name}");
+            primary_model.get_or_insert_with(|| model.clone());
+
+            clients.push((
+                name,
+                self.layer_client(client, &provider.client, &model, cache.clone()).await?,
+            ));
+        }
+
+        // The primary model, used for the cost estimate and the report
+        let model = primary_model.expect("The primary provider is always built");
+
+        let embedding_client = FallbackEmbeddingClient::new(clients, usage.clone())?;
+        let embedding_client =
+            TruncatedEmbeddingClient::new(embedding_client, &model, self.truncate_dimensions)?;
 
         let storage = QdrantStorage::new(
             &self.qdrant_url,
-            &path_to_collection_name(&self.path),
+            &self.collection_name(),
             embedding_client.embed_length().await?,
+            StorageConfig {
+                upsert_parallelism: self.upsert_parallelism,
+                wait: !self.no_wait,
+                model: Some(f!("{}:{model}", self.client.name())),
+                ..Default::default()
+            },
         )
         .await?;
 
-        info!("Starting codebase scan");
+        // Per-request input and token limits, to estimate how many requests each paid provider gets
+        let paid_batch_limits = match self.client {
+            ClientType::OpenAI if self.base_url.is_none() => Some((2048, 300_000)),
+            ClientType::AzureOpenAI => Some((2048, 300_000)),
+            ClientType::Voyage => Some((128, 120_000)),
+            ClientType::Jina => Some((128, usize::MAX)),
+            _ => None,
+        };
+
+        let cost_estimator = match paid_batch_limits {
+            Some((inputs, tokens)) => {
+                Some(CostEstimator::new(&model, inputs, tokens, self.max_cost))
+            },
+            None if self.max_cost.is_some() => {
+                warn!("--max-cost is ignored, {model} doesn't run on a paid provider");
+                None
+            },
+            None => None,
+        };
+
+        let (sizer, chunk_size_limit) = match self.chunk_unit {
+            ChunkUnit::Bytes => (ChunkSizer::Bytes, self.chunk_size_limit),
+            ChunkUnit::Tokens => {
+                let tokenizer = self.tokenizer(&model);
+                let limit = match self.chunk_size_limit {
+                    Some(limit) => limit,
+                    None => embedding_client.context_length().await?,
+                };
+
+                (ChunkSizer::Tokens(tokenizer), Some(limit))
+            },
+        };
+
+        if let Some(chunk_size) = chunk_size_limit {
+            info!("Using chunk size limit: {chunk_size} {:?}", self.chunk_unit);
+        }
+
         let scanner_config = ScannerConfig {
-            chunk_size_limit: self.chunk_size_limit,
-            overlap_percentage: self.overlap_percentage,
+            cost_estimator,
+            checkpoint: Some(Checkpoint::open(&self.checkpoint_path()?, self.resume)?),
+            ..self.scanner_config(roots, None, sizer, chunk_size_limit)?
+        };
+
+        Ok(ScanSession {
+            scanner: CodebaseScanner::new(embedding_client, storage, scanner_config),
+            model,
+            usage,
+        })
+    }
+
+    /// Scan the codebase, only looking at `changed` files when given. The clients, storage and
+    /// scanner are set up in `session` by the first scan and reused by the ones after it
+    pub async fn run(
+        &self,
+        changed: Option<HashSet<PathBuf>>,
+        session: &mut Option<ScanSession>,
+    ) -> Result<()> {
+        let roots = self.roots()?;
+
+        if self.dry_run {
+            return self.dry_run(&roots, changed);
+        }
+        if let Some(out) = &self.emit_chunks {
+            return self.emit_chunks(&roots, changed, out);
+        }
+
+        // Held until the scan is done, dry runs and chunk output don't touch storage
+        let _lock = ScanLock::acquire(&self.lock_path()?, &self.collection_name(), self.force)?;
+
+        let started = Instant::now();
+
+        let session = match session.take() {
+            Some(reused) => session.insert(reused),
+            None => session.insert(self.start_session(&roots).await?),
         };
+        let ScanSession {
+            scanner,
+            model,
+            usage,
+        } = session;
+
+        // Totals are reported for each scan on its own
+        usage.reset();
+        scanner.set_changed(changed);
 
-        let mut scanner = CodebaseScanner::new(embedding_client, storage, scanner_config);
+        info!("Starting codebase scan");
+        let (progress, receiver) = mpsc::unbounded_channel();
+        let renderer = render_progress(receiver, self.json);
 
-        match scanner.scan_codebase(&self.path).await {
+        scanner.set_progress(Some(progress));
+        let results = scanner.scan_codebase(&roots).await;
+
+        // Dropping the sender closes the channel so the renderer can finish
+        scanner.set_progress(None);
+        let _ = renderer.await;
+
+        match results {
             Ok(results) => {
                 info!("Scan completed successfully");
+
+                // Outside a git repository there's no commit to pick up from next time. Roots
+                // from several repositories pick up from the first one's. After failures the
+                // last clean commit is kept, so the next `--changed-since` retries the files
+                let clean = results.failed_chunks.is_empty() && results.file_errors.is_empty();
+                match head_commit(&roots[0]) {
+                    Ok(commit) if clean => record_indexed_commit(&self.commit_record()?, &commit)?,
+                    Ok(_) => warn!("Not recording the indexed commit, files failed to scan"),
+                    Err(_) => {},
+                }
                 info!("Processed {} code chunks", results.chunks_processed);
+                info!("Skipped {} unchanged files", results.files_unchanged);
+                info!(
+                    "Moved {} files without embedding them again",
+                    results.files_moved
+                );
                 info!("Generated {} embeddings", results.embeddings_generated);
                 info!("Stored in collection: {}", self.collection);
-                Ok(())
+
+                let errors = results.errors();
+                let report = UsageReport {
+                    chunks_processed: results.chunks_processed,
+                    embeddings_generated: results.embeddings_generated,
+                    failed_chunks: results.failed_chunks.len(),
+                    tokens: usage.tokens(),
+                    requests: usage.requests(),
+                    parse_seconds: results.timings.parse.as_secs_f64(),
+                    embed_seconds: results.timings.embed.as_secs_f64(),
+                    store_seconds: results.timings.store.as_secs_f64(),
+                    total_seconds: started.elapsed().as_secs_f64(),
+                    estimated_cost: cost_of(model, usage.tokens()),
+                    providers: usage.providers(),
+                    skipped_files: results.skipped_files.clone(),
+                    errors: errors.clone(),
+                };
+
+                let cost = report.estimated_cost.map(|cost| f!(", costing about ${cost:.4}"));
+
+                info!(
+                    "Sent {} tokens in {} requests{}",
+                    report.tokens,
+                    report.requests,
+                    cost.unwrap_or_default()
+                );
+                info!(
+                    "Took {:.1}s: parse {:.1}s, embed {:.1}s, store {:.1}s",
+                    report.total_seconds,
+                    report.parse_seconds,
+                    report.embed_seconds,
+                    report.store_seconds
+                );
+
+                if report.providers.len() > 1 {
+                    for (provider, chunks) in &report.providers {
+                        info!("{provider} embedded {chunks} chunks");
+                    }
+                }
+
+                if let Some(path) = &self.report {
+                    fs::write(path, serde_json::to_string_pretty(&report)?)?;
+                    info!("Wrote usage report to {}", path.display());
+                }
+
+                if !results.skipped_files.is_empty() {
+                    info!("Skipped {} files:", results.skipped_files.len());
+
+                    for skipped in &results.skipped_files {
+                        info!("  {}: {}", skipped.path.display(), skipped.reason);
+                    }
+                }
+
+                if !errors.is_empty() {
+                    warn!("{} files failed and were left out:", errors.len());
+
+                    for error in &errors {
+                        warn!(
+                            "  {} ({}): {}",
+                            error.path.display(),
+                            error.stage,
+                            error.reason
+                        );
+                    }
+                }
+
+                if !results.failed_chunks.is_empty() {
+                    // Cached embeddings mean a rerun only sends the chunks that failed
+                    let command: Vec<String> = env::args().collect();
+                    warn!(
+                        "Retry the chunks that failed to embed with: {}",
+                        command.join(" ")
+                    );
+                }
+
+                let limit = match self.fail_on_error {
+                    true => Some(0),
+                    false => self.max_errors,
+                };
+
+                match limit {
+                    Some(limit) if errors.len() > limit => Err(TooManyErrors {
+                        errors: errors.len(),
+                        limit,
+                    }),
+                    _ => Ok(()),
+                }
             },
             Err(e) => {
                 error!("Scan failed: {}", e);
@@ -0,0 +1,108 @@
+use std::{
+    collections::HashSet,
+    path::{Component, PathBuf},
+    time::Duration,
+};
+
+use clap::Parser;
+use notify::{Event, RecursiveMode, Watcher};
+use tokio::{sync::mpsc, time::timeout};
+use tracing::{info, warn};
+
+use super::{Command, scan::Scan};
+use crate::prelude::*;
+
+/// Scan the codebase, then keep the index in step with the working tree, rescanning the files
+/// that change
+#[derive(Parser, Debug, Clone)]
+pub struct Watch {
+    #[command(flatten)]
+    scan: Scan,
+
+    /// Milliseconds without changes before a burst of them is indexed
+    #[arg(long, default_value = "500")]
+    debounce_ms: u64,
+}
+
+impl Command for Watch {
+    async fn execute(&self) -> Result<()> {
+        // Clients, storage and scanner are set up once, each change is only scanned
+        let mut session = None;
+        self.scan.execute_with(&mut session).await?;
+
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        let mut watcher = notify::recommended_watcher(move |event: notify::Result<Event>| {
+            let _ = sender.send(event);
+        })?;
+
+        // Events carry paths under the watched one, so watch the absolute path to strip it again
+        let mut roots = Vec::new();
+        for root in self.scan.roots()? {
+            let watched = root.canonicalize()?;
+            watcher.watch(&watched, RecursiveMode::Recursive)?;
+            info!("Watching {} for changes", root.display());
+            roots.push((root, watched));
+        }
+        let debounce = Duration::from_millis(self.debounce_ms);
+
+        while let Some(event) = receiver.recv().await {
+            let mut changed = HashSet::new();
+            self.collect(event, &roots, &mut changed);
+
+            // Wait for the burst to settle, an editor save or checkout touches many files
+            while let Ok(Some(event)) = timeout(debounce, receiver.recv()).await {
+                self.collect(event, &roots, &mut changed);
+            }
+
+            if changed.is_empty() {
+                continue;
+            }
+
+            info!("Indexing {} changed files", changed.len());
+
+            if let Err(e) = self.scan.run(Some(changed), &mut session).await {
+                warn!("Failed to index changes: {e}");
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Watch {
+    /// Add the paths of `event` to `changed`, joined onto the scan root holding them the way the
+    /// scanner walks them. `roots` pairs each root with the absolute path being watched. Changes
+    /// inside `.git` are left out
+    fn collect(
+        &self,
+        event: notify::Result<Event>,
+        roots: &[(PathBuf, PathBuf)],
+        changed: &mut HashSet<PathBuf>,
+    ) {
+        let event = match event {
+            Ok(event) if !event.kind.is_access() => event,
+            Ok(_) => return,
+            Err(e) => {
+                warn!("Watch error: {e}");
+                return;
+            },
+        };
+
+        for path in event.paths {
+            let Some((root, relative)) = roots.iter().find_map(|(root, watched)| {
+                path.strip_prefix(watched).ok().map(|relative| (root, relative))
+            }) else {
+                continue;
+            };
+
+            if relative
+                .components()
+                .any(|component| component == Component::Normal(".git".as_ref()))
+            {
+                continue;
+            }
+
+            changed.insert(root.join(relative));
+        }
+    }
+}
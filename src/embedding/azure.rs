@@ -0,0 +1,148 @@
+use async_trait::async_trait;
+use futures::{StreamExt, TryStreamExt, stream};
+use reqwest::Client as ReqwestClient;
+use url::Url;
+
+use super::{
+    Embedding,
+    client::{EmbeddingClient, EmbeddingConfig},
+    openai::{
+        MAX_BATCH_INPUTS, MAX_BATCH_TOKENS, OpenAIEmbeddingRequest, OpenAIEmbeddingResponse,
+        known_context_length, known_embed_length,
+    },
+    retry::{api_error, classify_error},
+    tokenizer::Tokenizer,
+};
+use crate::{chunking::CodeChunk, prelude::*};
+
+pub const DEFAULT_AZURE_API_VERSION: &str = "2024-02-01";
+
+/// Embeddings from an Azure OpenAI deployment
+#[derive(Debug, Clone)]
+pub struct AzureOpenAIEmbeddingClient {
+    client: ReqwestClient,
+    api_url: Url,
+    api_key: String,
+    // The model behind the deployment, used to look up its limits
+    model: String,
+    dimensions: Option<usize>,
+    tokenizer: Tokenizer,
+    config: EmbeddingConfig,
+}
+
+impl AzureOpenAIEmbeddingClient {
+    pub fn new(
+        endpoint: &Url,
+        deployment: &str,
+        api_version: &str,
+        api_key: &str,
+        model: &str,
+        dimensions: Option<usize>,
+        config: EmbeddingConfig,
+    ) -> Result<Self> {
+        let mut api_url = endpoint.join(&f!("openai/deployments/{deployment}/embeddings"))?;
+        api_url.query_pairs_mut().append_pair("api-version", api_version);
+
+        let client = config.http_client()?;
+
+        Ok(Self {
+            client,
+            api_url,
+            api_key: api_key.to_string(),
+            model: model.to_string(),
+            dimensions,
+            tokenizer: Tokenizer::for_openai(model),
+            config,
+        })
+    }
+
+    async fn embed_batch(&self, batch: Vec<String>) -> Result<Vec<Embedding>> {
+        // The deployment already determines the model
+        let request = OpenAIEmbeddingRequest {
+            model: None,
+            input: batch,
+            dimensions: self.dimensions,
+        };
+
+        self.config
+            .retry
+            .run(
+                "Azure OpenAI embedding request",
+                || self.send(&request),
+                classify_error,
+            )
+            .await
+            .map_err(|(e, _)| e)
+    }
+
+    async fn send(&self, request: &OpenAIEmbeddingRequest) -> Result<Vec<Embedding>> {
+        self.config.throttle(&self.tokenizer, &request.input).await;
+
+        let response = self
+            .client
+            .post(self.api_url.clone())
+            .header("api-key", &self.api_key)
+            .json(request)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(api_error(response).await);
+        }
+
+        let embedding_response: OpenAIEmbeddingResponse = response.json().await?;
+
+        embedding_response.into_embeddings("Azure OpenAI", request.input.len())
+    }
+}
+
+#[async_trait]
+impl EmbeddingClient for AzureOpenAIEmbeddingClient {
+    async fn embed(&self, chunks: &[CodeChunk]) -> Result<Vec<Embedding>> {
+        let max_tokens = known_context_length(&self.model);
+        let texts: Vec<String> =
+            chunks.iter().map(|chunk| self.tokenizer.fit(chunk, max_tokens)).collect();
+
+        // Fill each request up to the endpoint's input and token limits
+        let batches =
+            self.tokenizer
+                .batches(&texts, String::as_str, MAX_BATCH_INPUTS, MAX_BATCH_TOKENS);
+
+        // `buffered` yields results in submission order, keeping embeddings aligned with chunks
+        let batches: Vec<Vec<Embedding>> =
+            stream::iter(batches.into_iter().map(<[String]>::to_vec))
+                .map(|batch| self.embed_batch(batch))
+                .buffered(self.config.concurrency())
+                .try_collect()
+                .await?;
+
+        Ok(batches.into_iter().flatten().collect())
+    }
+
+    async fn context_length(&self) -> Result<usize> {
+        Ok(known_context_length(&self.model))
+    }
+
+    async fn embed_length(&self) -> Result<usize> {
+        if let Some(length) = self.dimensions.or(known_embed_length(&self.model)) {
+            return Ok(length);
+        }
+
+        let embeddings = self
+            .send(&OpenAIEmbeddingRequest {
+                model: None,
+                input: vec!["test".to_string()],
+                dimensions: None,
+            })
+            .await?;
+
+        embeddings
+            .first()
+            .map(|embedding| embedding.len())
+            .ok_or(Error::Embedding("Empty embedding response".to_string()))
+    }
+
+    fn tokenizer(&self) -> Tokenizer {
+        self.tokenizer.clone()
+    }
+}
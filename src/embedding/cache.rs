@@ -0,0 +1,147 @@
+use std::path::Path;
+
+use async_trait::async_trait;
+use sha2::{Digest, Sha256};
+use tracing::debug;
+
+use super::{Embedding, client::EmbeddingClient, tokenizer::Tokenizer};
+use crate::{chunking::CodeChunk, prelude::*};
+
+/// On-disk map of (model, settings, chunk content) to embedding
+#[derive(Clone)]
+pub struct EmbeddingCache {
+    db: sled::Db,
+}
+
+impl EmbeddingCache {
+    pub fn open(path: &Path) -> Result<Self> {
+        Ok(Self {
+            db: sled::open(path)?,
+        })
+    }
+
+    /// `settings` are the request options besides the model that change the embedding, left out
+    /// of the key when empty so entries cached before they were recorded stay valid
+    fn key(model: &str, settings: &str, content: &str) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.update(model.as_bytes());
+        hasher.update([0]);
+        if !settings.is_empty() {
+            hasher.update(settings.as_bytes());
+            hasher.update([0]);
+        }
+        hasher.update(content.as_bytes());
+        hasher.finalize().into()
+    }
+
+    pub fn get(&self, model: &str, settings: &str, content: &str) -> Result<Option<Embedding>> {
+        Ok(
+            self.db.get(Self::key(model, settings, content))?.map(|bytes| {
+                bytes
+                    .chunks_exact(4)
+                    .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+                    .collect()
+            }),
+        )
+    }
+
+    pub fn insert(
+        &self,
+        model: &str,
+        settings: &str,
+        content: &str,
+        embedding: &Embedding,
+    ) -> Result<()> {
+        let bytes: Vec<u8> = embedding.iter().flat_map(|v| v.to_le_bytes()).collect();
+        self.db.insert(Self::key(model, settings, content), bytes)?;
+        Ok(())
+    }
+
+    pub async fn flush(&self) -> Result<()> {
+        self.db.flush_async().await?;
+        Ok(())
+    }
+}
+
+/// Wraps a client so only chunks missing from the cache reach the provider
+pub struct CachedEmbeddingClient<E: EmbeddingClient> {
+    inner: E,
+    model: String,
+    settings: String,
+    cache: Option<EmbeddingCache>,
+}
+
+impl<E: EmbeddingClient> CachedEmbeddingClient<E> {
+    /// `settings` describe the request options besides `model` that change the embeddings, e.g.
+    /// `dimensions=256`, so embeddings made with other options aren't reused
+    pub fn new(inner: E, model: &str, settings: String, cache: Option<EmbeddingCache>) -> Self {
+        Self {
+            inner,
+            model: model.to_string(),
+            settings,
+            cache,
+        }
+    }
+}
+
+#[async_trait]
+impl<E: EmbeddingClient> EmbeddingClient for CachedEmbeddingClient<E> {
+    async fn embed(&self, chunks: &[CodeChunk]) -> Result<Vec<Embedding>> {
+        let Some(cache) = &self.cache else {
+            return self.inner.embed(chunks).await;
+        };
+
+        let mut embeddings = Vec::with_capacity(chunks.len());
+        let mut misses = Vec::new();
+
+        for (i, chunk) in chunks.iter().enumerate() {
+            let cached = cache.get(&self.model, &self.settings, &chunk.content)?;
+
+            if cached.is_none() {
+                misses.push(i);
+            }
+
+            embeddings.push(cached);
+        }
+
+        debug!(
+            "Embedding cache hit for {} of {} chunks",
+            chunks.len() - misses.len(),
+            chunks.len()
+        );
+
+        if !misses.is_empty() {
+            let to_embed: Vec<CodeChunk> = misses.iter().map(|&i| chunks[i].clone()).collect();
+            let fresh = self.inner.embed(&to_embed).await?;
+
+            if fresh.len() != to_embed.len() {
+                return Err(Error::Embedding(f!(
+                    "Expected {} embeddings, got {}",
+                    to_embed.len(),
+                    fresh.len()
+                )));
+            }
+
+            for (i, embedding) in misses.into_iter().zip(fresh) {
+                cache.insert(&self.model, &self.settings, &chunks[i].content, &embedding)?;
+                embeddings[i] = Some(embedding);
+            }
+
+            cache.flush().await?;
+        }
+
+        Ok(embeddings.into_iter().flatten().collect())
+    }
+
+    async fn context_length(&self) -> Result<usize> {
+        self.inner.context_length().await
+    }
+
+    async fn embed_length(&self) -> Result<usize> {
+        self.inner.embed_length().await
+    }
+
+    fn tokenizer(&self) -> Tokenizer {
+        self.inner.tokenizer()
+    }
+}
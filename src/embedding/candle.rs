@@ -0,0 +1,184 @@
+use std::{fmt, fs, path::PathBuf, sync::Arc};
+
+use async_trait::async_trait;
+use candle_core::{Device, Tensor};
+use candle_nn::VarBuilder;
+use candle_transformers::models::bert::{BertModel, Config, DTYPE};
+use clap::ValueEnum;
+use hf_hub::{Repo, RepoType, api::sync::ApiBuilder};
+use serde::{Deserialize, Serialize};
+use tokenizers::{PaddingParams, Tokenizer, TruncationParams};
+use tracing::info;
+
+use super::{Embedding, client::EmbeddingClient, tokenizer::Tokenizer as TokenCounter};
+use crate::{chunking::CodeChunk, prelude::*};
+
+const BATCH_SIZE: usize = 32;
+
+#[derive(Debug, Clone, Copy, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CandleDevice {
+    Cpu,
+    Cuda,
+    Metal,
+}
+
+impl CandleDevice {
+    fn device(&self) -> Result<Device> {
+        match self {
+            Self::Cpu => Ok(Device::Cpu),
+            Self::Cuda => Device::new_cuda(0).map_err(candle_error),
+            Self::Metal => Device::new_metal(0).map_err(candle_error),
+        }
+    }
+}
+
+struct CandleModel {
+    model: BertModel,
+    tokenizer: Tokenizer,
+    device: Device,
+}
+
+/// Runs a BERT-style safetensors model from the HuggingFace hub with candle
+#[derive(Clone)]
+pub struct CandleEmbeddingClient {
+    inner: Arc<CandleModel>,
+    model_name: String,
+    embed_length: usize,
+    max_length: usize,
+    /// The model's tokenizer without the truncation and padding inference uses
+    counter: TokenCounter,
+}
+
+impl fmt::Debug for CandleEmbeddingClient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CandleEmbeddingClient")
+            .field("model_name", &self.model_name)
+            .field("embed_length", &self.embed_length)
+            .field("device", &self.inner.device)
+            .finish()
+    }
+}
+
+fn candle_error(e: impl fmt::Display) -> Error {
+    Error::Embedding(e.to_string())
+}
+
+impl CandleEmbeddingClient {
+    /// Load `model` (a hub repo id, e.g. `BAAI/bge-base-en-v1.5`), downloading it into
+    /// `cache_dir` the first time it's used
+    pub fn new(model: &str, device: CandleDevice, cache_dir: PathBuf) -> Result<Self> {
+        let device = device.device()?;
+        info!("Loading {model} with candle on {device:?}");
+
+        let api = ApiBuilder::new().with_cache_dir(cache_dir).build().map_err(candle_error)?;
+        let repo = api.repo(Repo::new(model.to_string(), RepoType::Model));
+
+        let config_path = repo.get("config.json").map_err(candle_error)?;
+        let tokenizer_path = repo.get("tokenizer.json").map_err(candle_error)?;
+        let weights_path = repo.get("model.safetensors").map_err(candle_error)?;
+
+        let config: Config = serde_json::from_str(&fs::read_to_string(config_path)?)?;
+        let max_length = config.max_position_embeddings;
+        let embed_length = config.hidden_size;
+
+        let mut tokenizer = Tokenizer::from_file(tokenizer_path).map_err(candle_error)?;
+        let mut counter = tokenizer.clone();
+        counter.with_truncation(None).map_err(candle_error)?;
+        counter.with_padding(None);
+
+        tokenizer.with_padding(Some(PaddingParams::default()));
+        tokenizer
+            .with_truncation(Some(TruncationParams {
+                max_length,
+                ..Default::default()
+            }))
+            .map_err(candle_error)?;
+
+        // SAFETY: the weights file is owned by the hub cache and not modified while mapped
+        let vb = unsafe { VarBuilder::from_mmaped_safetensors(&[weights_path], DTYPE, &device) }
+            .map_err(candle_error)?;
+        let bert = BertModel::load(vb, &config).map_err(candle_error)?;
+
+        Ok(Self {
+            inner: Arc::new(CandleModel {
+                model: bert,
+                tokenizer,
+                device,
+            }),
+            model_name: model.to_string(),
+            embed_length,
+            max_length,
+            counter: TokenCounter::HuggingFace(Arc::new(counter)),
+        })
+    }
+}
+
+impl CandleModel {
+    /// Mean-pooled, L2-normalized embeddings for a batch of texts
+    fn embed_batch(&self, texts: Vec<String>) -> Result<Vec<Embedding>> {
+        let encodings = self.tokenizer.encode_batch(texts, true).map_err(candle_error)?;
+
+        let to_tensor = |rows: Vec<&[u32]>| -> Result<Tensor> {
+            let rows = rows
+                .into_iter()
+                .map(|row| Tensor::new(row, &self.device))
+                .collect::<std::result::Result<Vec<_>, _>>()
+                .map_err(candle_error)?;
+            Tensor::stack(&rows, 0).map_err(candle_error)
+        };
+
+        let token_ids = to_tensor(encodings.iter().map(|e| e.get_ids()).collect())?;
+        let attention_mask = to_tensor(encodings.iter().map(|e| e.get_attention_mask()).collect())?;
+
+        let pooled = (|| {
+            let token_type_ids = token_ids.zeros_like()?;
+            let hidden = self.model.forward(&token_ids, &token_type_ids, Some(&attention_mask))?;
+
+            // Average over real tokens only, ignoring padding
+            let mask = attention_mask.to_dtype(DTYPE)?.unsqueeze(2)?;
+            let summed = hidden.broadcast_mul(&mask)?.sum(1)?;
+            let pooled = summed.broadcast_div(&mask.sum(1)?)?;
+            let norm = pooled.sqr()?.sum_keepdim(1)?.sqrt()?;
+
+            pooled.broadcast_div(&norm)?.to_vec2::<f32>()
+        })()
+        .map_err(candle_error)?;
+
+        Ok(pooled)
+    }
+}
+
+#[async_trait]
+impl EmbeddingClient for CandleEmbeddingClient {
+    async fn embed(&self, chunks: &[CodeChunk]) -> Result<Vec<Embedding>> {
+        // The tokenizer truncates inputs to the model's max length
+        let texts: Vec<String> = chunks.iter().map(|chunk| chunk.content.clone()).collect();
+        let inner = self.inner.clone();
+
+        // Inference is compute bound, keep it off the async workers
+        tokio::task::spawn_blocking(move || {
+            let mut embeddings = Vec::with_capacity(texts.len());
+
+            for batch in texts.chunks(BATCH_SIZE) {
+                embeddings.extend(inner.embed_batch(batch.to_vec())?);
+            }
+
+            Ok(embeddings)
+        })
+        .await
+        .map_err(candle_error)?
+    }
+
+    async fn context_length(&self) -> Result<usize> {
+        Ok(self.max_length)
+    }
+
+    async fn embed_length(&self) -> Result<usize> {
+        Ok(self.embed_length)
+    }
+
+    fn tokenizer(&self) -> TokenCounter {
+        self.counter.clone()
+    }
+}
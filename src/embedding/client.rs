@@ -1,9 +1,141 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::PathBuf,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use reqwest::{Certificate, Client as ReqwestClient, Proxy};
+use url::Url;
+
 use crate::{chunking::CodeChunk, embedding::Embedding};
 
-use crate::prelude::*;
+use super::{rate_limit::RateLimits, tokenizer::Tokenizer};
+use crate::{prelude::*, utils::retry::RetryPolicy};
+
+const DEFAULT_CONCURRENCY: usize = 4;
+
+/// Settings shared by every embedding provider
+#[derive(Debug, Clone, Default)]
+pub struct EmbeddingConfig {
+    /// Number of embedding requests in flight at once
+    pub concurrency: Option<usize>,
+    /// Backoff for rate limited or failed requests
+    pub retry: RetryPolicy,
+    /// Totals of what was sent, shared by every clone of the config
+    pub usage: Arc<Usage>,
+    /// Proxy every provider request goes through
+    pub proxy: Option<Url>,
+    /// PEM bundle of extra root certificates to trust
+    pub ca_bundle: Option<PathBuf>,
+    /// Account limits requests are held back to, shared by every clone of the config
+    pub rate_limits: Option<Arc<RateLimits>>,
+}
+
+/// Requests made and tokens sent to an embedding provider
+#[derive(Debug, Default)]
+pub struct Usage {
+    requests: AtomicUsize,
+    tokens: AtomicUsize,
+    /// Chunks embedded by each provider of a fallback chain
+    providers: Mutex<BTreeMap<String, usize>>,
+}
+
+impl Usage {
+    pub fn requests(&self) -> usize {
+        self.requests.load(Ordering::Relaxed)
+    }
+
+    pub fn tokens(&self) -> usize {
+        self.tokens.load(Ordering::Relaxed)
+    }
+
+    /// Start counting again from nothing
+    pub fn reset(&self) {
+        self.requests.store(0, Ordering::Relaxed);
+        self.tokens.store(0, Ordering::Relaxed);
+        self.providers.lock().expect("Usage lock poisoned").clear();
+    }
+
+    pub fn providers(&self) -> BTreeMap<String, usize> {
+        self.providers.lock().expect("Usage lock poisoned").clone()
+    }
+
+    pub(super) fn record_provider(&self, provider: &str, chunks: usize) {
+        *self
+            .providers
+            .lock()
+            .expect("Usage lock poisoned")
+            .entry(provider.to_string())
+            .or_default() += chunks;
+    }
+}
+
+impl EmbeddingConfig {
+    pub fn concurrency(&self) -> usize {
+        self.concurrency.unwrap_or(DEFAULT_CONCURRENCY).max(1)
+    }
 
+    /// HTTP client using the configured proxy and root certificates
+    pub(super) fn http_client(&self) -> Result<ReqwestClient> {
+        let mut builder = ReqwestClient::builder().timeout(Duration::from_secs(120));
+
+        if let Some(proxy) = &self.proxy {
+            builder = builder.proxy(Proxy::all(proxy.as_str())?);
+        }
+
+        if let Some(path) = &self.ca_bundle {
+            for certificate in Certificate::from_pem_bundle(&fs::read(path)?)? {
+                builder = builder.add_root_certificate(certificate);
+            }
+        }
+
+        Ok(builder.build()?)
+    }
+
+    /// Wait until the rate limits allow a request carrying `inputs`, then count it
+    pub(super) async fn throttle(&self, tokenizer: &Tokenizer, inputs: &[String]) {
+        let tokens: usize = inputs.iter().map(|input| tokenizer.count(input)).sum();
+
+        if let Some(rate_limits) = &self.rate_limits {
+            rate_limits.wait(tokens).await;
+        }
+
+        self.usage.requests.fetch_add(1, Ordering::Relaxed);
+        self.usage.tokens.fetch_add(tokens, Ordering::Relaxed);
+    }
+}
+
+/// Lengths are looked up lazily, so implementations cache them behind interior mutability
+#[async_trait]
 pub trait EmbeddingClient: Send + Sync {
     async fn embed(&self, chunks: &[CodeChunk]) -> Result<Vec<Embedding>>;
-    async fn context_length(&mut self) -> Result<usize>;
-    async fn embed_length(&mut self) -> Result<usize>;
+    async fn context_length(&self) -> Result<usize>;
+    async fn embed_length(&self) -> Result<usize>;
+    /// Tokenizer of the model, for counting and splitting text the way the provider will
+    fn tokenizer(&self) -> Tokenizer;
+}
+
+#[async_trait]
+impl<E: EmbeddingClient + ?Sized> EmbeddingClient for Box<E> {
+    async fn embed(&self, chunks: &[CodeChunk]) -> Result<Vec<Embedding>> {
+        (**self).embed(chunks).await
+    }
+
+    async fn context_length(&self) -> Result<usize> {
+        (**self).context_length().await
+    }
+
+    async fn embed_length(&self) -> Result<usize> {
+        (**self).embed_length().await
+    }
+
+    fn tokenizer(&self) -> Tokenizer {
+        (**self).tokenizer()
+    }
 }
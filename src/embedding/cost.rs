@@ -0,0 +1,116 @@
+use tracing::info;
+
+use super::tokenizer::Tokenizer;
+use crate::{chunking::CodeChunk, prelude::*};
+
+/// Published list prices in USD per million input tokens
+fn price_per_million_tokens(model: &str) -> Option<f64> {
+    match model {
+        "text-embedding-3-small" => Some(0.02),
+        "text-embedding-3-large" => Some(0.13),
+        "text-embedding-ada-002" => Some(0.10),
+        "voyage-code-3" | "voyage-3" | "voyage-3.5" => Some(0.06),
+        "voyage-code-2" | "voyage-3-large" => Some(0.18),
+        "voyage-3-lite" | "voyage-3.5-lite" => Some(0.02),
+        model if model.starts_with("jina-embeddings") => Some(0.02),
+        _ => None,
+    }
+}
+
+/// Price in USD of sending `tokens` tokens to `model`, if its price is known
+pub fn cost_of(model: &str, tokens: usize) -> Option<f64> {
+    price_per_million_tokens(model).map(|price| tokens as f64 / 1_000_000.0 * price)
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CostEstimate {
+    pub tokens: usize,
+    pub requests: usize,
+    /// `None` when the model's price isn't known
+    pub cost: Option<f64>,
+}
+
+impl CostEstimate {
+    /// Add the estimate of another group of chunks
+    pub fn add(&mut self, other: CostEstimate) {
+        self.tokens += other.tokens;
+        self.requests += other.requests;
+        self.cost = match (self.cost, other.cost) {
+            (Some(cost), Some(other)) => Some(cost + other),
+            (cost, other) => cost.or(other),
+        };
+    }
+}
+
+/// Counts the tokens a scan will send to a paid provider before any request is made
+#[derive(Debug, Clone)]
+pub struct CostEstimator {
+    tokenizer: Tokenizer,
+    model: String,
+    batch_inputs: usize,
+    batch_tokens: usize,
+    max_cost: Option<f64>,
+}
+
+impl CostEstimator {
+    /// `batch_inputs` and `batch_tokens` are the provider's per-request limits
+    pub fn new(
+        model: &str,
+        batch_inputs: usize,
+        batch_tokens: usize,
+        max_cost: Option<f64>,
+    ) -> Self {
+        Self {
+            tokenizer: Tokenizer::for_openai(model),
+            model: model.to_string(),
+            batch_inputs,
+            batch_tokens,
+            max_cost,
+        }
+    }
+
+    pub fn estimate(&self, chunks: &[CodeChunk]) -> CostEstimate {
+        let tokens = chunks.iter().map(|chunk| self.tokenizer.count(&chunk.content)).sum();
+
+        CostEstimate {
+            tokens,
+            requests: self
+                .tokenizer
+                .batches(
+                    chunks,
+                    |chunk| &chunk.content,
+                    self.batch_inputs,
+                    self.batch_tokens,
+                )
+                .len(),
+            cost: cost_of(&self.model, tokens),
+        }
+    }
+
+    /// Log the estimate, summed over every group of chunks, and refuse to go on if it's over
+    /// `max_cost`
+    pub fn check(&self, estimate: CostEstimate) -> Result<CostEstimate> {
+        // Cached chunks aren't sent, so this is an upper bound
+        match estimate.cost {
+            Some(cost) => info!(
+                "Estimated {} tokens in {} requests, costing up to ${cost:.4}",
+                estimate.tokens, estimate.requests
+            ),
+            None => info!(
+                "Estimated {} tokens in {} requests, price unknown for this model",
+                estimate.tokens, estimate.requests
+            ),
+        }
+
+        match (estimate.cost, self.max_cost) {
+            (Some(cost), Some(limit)) if cost > limit => Err(CostLimitExceeded {
+                estimated: cost,
+                limit,
+            }),
+            (None, Some(limit)) => Err(InvalidArgument(f!(
+                "--max-cost {limit} was set but the model's price is unknown"
+            ))),
+            _ => Ok(estimate),
+        }
+    }
+}
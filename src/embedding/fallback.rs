@@ -0,0 +1,91 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use tracing::warn;
+
+use super::{
+    Embedding,
+    client::{EmbeddingClient, Usage},
+    tokenizer::Tokenizer,
+};
+use crate::{chunking::CodeChunk, prelude::*};
+
+/// Tries each provider in order, moving on to the next when one fails
+pub struct FallbackEmbeddingClient<E: EmbeddingClient> {
+    /// Providers with the name they're reported under, primary first
+    clients: Vec<(String, E)>,
+    usage: Arc<Usage>,
+}
+
+impl<E: EmbeddingClient> FallbackEmbeddingClient<E> {
+    pub fn new(clients: Vec<(String, E)>, usage: Arc<Usage>) -> Result<Self> {
+        if clients.is_empty() {
+            return Err(Missing(String::from("embedding provider")));
+        }
+
+        Ok(Self { clients, usage })
+    }
+}
+
+#[async_trait]
+impl<E: EmbeddingClient> EmbeddingClient for FallbackEmbeddingClient<E> {
+    async fn embed(&self, chunks: &[CodeChunk]) -> Result<Vec<Embedding>> {
+        let mut last_error = None;
+
+        for (i, (name, client)) in self.clients.iter().enumerate() {
+            match client.embed(chunks).await {
+                Ok(embeddings) => {
+                    if i > 0 {
+                        warn!("Embedded {} chunks with fallback {name}", chunks.len());
+                    }
+
+                    self.usage.record_provider(name, chunks.len());
+                    return Ok(embeddings);
+                },
+                Err(e) => {
+                    if i + 1 < self.clients.len() {
+                        warn!("{name} failed, falling back to the next provider: {e}");
+                    }
+
+                    last_error = Some(e);
+                },
+            }
+        }
+
+        Err(last_error.expect("There is always at least one provider"))
+    }
+
+    async fn context_length(&self) -> Result<usize> {
+        let mut context_length = usize::MAX;
+
+        for (_, client) in &self.clients {
+            context_length = context_length.min(client.context_length().await?);
+        }
+
+        Ok(context_length)
+    }
+
+    /// Every provider has to produce embeddings the collection can hold
+    async fn embed_length(&self) -> Result<usize> {
+        let mut lengths = Vec::with_capacity(self.clients.len());
+
+        for (name, client) in &self.clients {
+            lengths.push((name.clone(), client.embed_length().await?));
+        }
+
+        let (primary, length) = lengths[0].clone();
+
+        match lengths.iter().find(|(_, other)| *other != length) {
+            Some((name, other)) => Err(InvalidArgument(f!(
+                "Fallback {name} produces {other} dimension embeddings but {primary} produces \
+                 {length}"
+            ))),
+            None => Ok(length),
+        }
+    }
+
+    /// Chunks are sized for the primary provider
+    fn tokenizer(&self) -> Tokenizer {
+        self.clients[0].1.tokenizer()
+    }
+}
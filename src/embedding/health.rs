@@ -0,0 +1,35 @@
+use tracing::debug;
+
+use super::client::EmbeddingClient;
+use crate::{chunking::CodeChunk, prelude::*};
+
+/// Embed a probe chunk and compare it to the dimension the client reports, so an unreachable
+/// server, missing model or bad key fails before any files are parsed
+pub async fn health_check(client: &dyn EmbeddingClient) -> Result<usize> {
+    let expected = client.embed_length().await?;
+
+    let probe = CodeChunk {
+        content: String::from("fn main() {}"),
+        node_type: String::from("probe"),
+        start_line: 0,
+        end_line: 0,
+        path: "probe.rs".into(),
+        language: String::from("rust"),
+        ..Default::default()
+    };
+
+    let embeddings = client.embed(&[probe]).await?;
+
+    match embeddings.first().map(Vec::len) {
+        Some(length) if length == expected => {
+            debug!("Health check embedded a probe with {length} dimensions");
+            Ok(length)
+        },
+        Some(length) => Err(Error::Embedding(f!(
+            "Probe embedding has {length} dimensions but the model reports {expected}"
+        ))),
+        None => Err(Error::Embedding(String::from(
+            "Probe returned no embedding",
+        ))),
+    }
+}
@@ -1,48 +1,131 @@
-use std::time::Duration;
-
+use async_trait::async_trait;
+use futures::{StreamExt, TryStreamExt, stream};
 use reqwest::Client;
 use serde::Serialize;
 
-use super::{Embedding, client::EmbeddingClient};
+use super::{
+    Embedding,
+    client::{EmbeddingClient, EmbeddingConfig},
+    retry::{api_error, classify_error},
+    tokenizer::Tokenizer,
+};
 use crate::{chunking::CodeChunk, prelude::*};
 
+const HF_INFERENCE_URL: &str = "https://router.huggingface.co/hf-inference/models";
+// The serverless API serves most embedding models with a 512 token input limit
+const MAX_INPUT_TOKENS: usize = 512;
+const MAX_BATCH_INPUTS: usize = 32;
+
+/// Client for the HuggingFace serverless inference API, `model` being a hub repo id
 #[derive(Debug, Clone)]
 pub struct HuggingFaceEmbeddingClient {
     client: Client,
     api_key: String,
     model: String,
+    config: EmbeddingConfig,
+    tokenizer: Tokenizer,
 }
 
 #[derive(Serialize)]
-struct HuggingFaceRequest {
-    inputs: Vec<String>,
+struct HuggingFaceRequest<'a> {
+    inputs: &'a [String],
+    // Let the server cut inputs down to the model's max length
+    truncate: bool,
 }
 
 impl HuggingFaceEmbeddingClient {
-    pub fn new(api_key: &str, model: &str) -> Self {
-        let client = Client::builder()
-            .timeout(Duration::from_secs(120))
-            .build()
-            .expect("Failed to build HTTP client");
+    pub fn new(api_key: &str, model: &str, config: EmbeddingConfig) -> Result<Self> {
+        let client = config.http_client()?;
 
-        Self {
+        Ok(Self {
             client,
             api_key: api_key.to_string(),
             model: model.to_string(),
+            config,
+            tokenizer: Tokenizer::for_huggingface(model),
+        })
+    }
+
+    async fn embed_batch(&self, batch: Vec<String>) -> Result<Vec<Embedding>> {
+        self.config
+            .retry
+            .run(
+                "HuggingFace embedding request",
+                || self.send(&batch),
+                classify_error,
+            )
+            .await
+            .map_err(|(e, _)| e)
+    }
+
+    async fn send(&self, batch: &[String]) -> Result<Vec<Embedding>> {
+        self.config.throttle(&self.tokenizer, batch).await;
+
+        let response = self
+            .client
+            .post(f!(
+                "{HF_INFERENCE_URL}/{}/pipeline/feature-extraction",
+                self.model
+            ))
+            .bearer_auth(&self.api_key)
+            .json(&HuggingFaceRequest {
+                inputs: batch,
+                truncate: true,
+            })
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(api_error(response).await);
+        }
+
+        let embeddings: Vec<Embedding> = response.json().await?;
+
+        if embeddings.len() != batch.len() {
+            return Err(Error::Embedding(f!(
+                "Expected {} embeddings, got {}",
+                batch.len(),
+                embeddings.len()
+            )));
         }
+
+        Ok(embeddings)
     }
 }
 
+#[async_trait]
 impl EmbeddingClient for HuggingFaceEmbeddingClient {
-    async fn embed(&self, _chunks: &[CodeChunk]) -> Result<Vec<Embedding>> {
-        // Implementation for HuggingFace API
-        // Similar to OpenAI but with different endpoint and request format
-        todo!("Implement HuggingFace embedding client")
+    async fn embed(&self, chunks: &[CodeChunk]) -> Result<Vec<Embedding>> {
+        let texts: Vec<String> =
+            chunks.iter().map(|chunk| self.tokenizer.fit(chunk, MAX_INPUT_TOKENS)).collect();
+
+        let batches = self.tokenizer.batches(&texts, String::as_str, MAX_BATCH_INPUTS, usize::MAX);
+
+        // `buffered` yields results in submission order, keeping embeddings aligned with chunks
+        let batches: Vec<Vec<Embedding>> =
+            stream::iter(batches.into_iter().map(<[String]>::to_vec))
+                .map(|batch| self.embed_batch(batch))
+                .buffered(self.config.concurrency())
+                .try_collect()
+                .await?;
+
+        Ok(batches.into_iter().flatten().collect())
     }
-    async fn context_length(&mut self) -> Result<usize> {
-        todo!("Implement HuggingFace context length")
+
+    async fn context_length(&self) -> Result<usize> {
+        Ok(MAX_INPUT_TOKENS)
     }
-    async fn embed_length(&mut self) -> Result<usize> {
-        todo!("Implement HuggingFace embed length")
+
+    async fn embed_length(&self) -> Result<usize> {
+        let embeddings = self.embed_batch(vec!["test".to_string()]).await?;
+
+        embeddings
+            .first()
+            .map(|embedding| embedding.len())
+            .ok_or(Error::Embedding("Empty embedding response".to_string()))
+    }
+
+    fn tokenizer(&self) -> Tokenizer {
+        self.tokenizer.clone()
     }
 }
@@ -0,0 +1,185 @@
+use async_trait::async_trait;
+use clap::ValueEnum;
+use futures::{StreamExt, TryStreamExt, stream};
+use reqwest::Client as ReqwestClient;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    Embedding,
+    client::{EmbeddingClient, EmbeddingConfig},
+    retry::{api_error, classify_error},
+    tokenizer::Tokenizer,
+};
+use crate::{chunking::CodeChunk, prelude::*};
+
+const JINA_API_URL: &str = "https://api.jina.ai/v1/embeddings";
+const MAX_BATCH_INPUTS: usize = 128;
+const CONTEXT_LENGTH: usize = 8192;
+
+/// Downstream task jina-embeddings-v3 tunes its embeddings for
+#[derive(Debug, Clone, Copy, ValueEnum, Serialize, Deserialize)]
+pub enum JinaTask {
+    #[serde(rename = "retrieval.passage")]
+    #[value(name = "retrieval.passage")]
+    RetrievalPassage,
+
+    #[serde(rename = "retrieval.query")]
+    #[value(name = "retrieval.query")]
+    RetrievalQuery,
+
+    #[serde(rename = "text-matching")]
+    #[value(name = "text-matching")]
+    TextMatching,
+
+    #[serde(rename = "separation")]
+    #[value(name = "separation")]
+    Separation,
+
+    #[serde(rename = "classification")]
+    #[value(name = "classification")]
+    Classification,
+}
+
+#[derive(Debug, Clone)]
+pub struct JinaEmbeddingClient {
+    client: ReqwestClient,
+    api_key: String,
+    model: String,
+    task: JinaTask,
+    late_chunking: bool,
+    config: EmbeddingConfig,
+    tokenizer: Tokenizer,
+}
+
+#[derive(Serialize)]
+struct JinaEmbeddingRequest<'a> {
+    model: &'a str,
+    input: &'a [String],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    task: Option<JinaTask>,
+    late_chunking: bool,
+}
+
+#[derive(Deserialize)]
+struct JinaEmbeddingResponse {
+    data: Vec<JinaEmbeddingData>,
+}
+
+#[derive(Deserialize)]
+struct JinaEmbeddingData {
+    embedding: Vec<f32>,
+    index: usize,
+}
+
+impl JinaEmbeddingClient {
+    /// With `late_chunking`, each request is embedded as one document so chunks keep the
+    /// context of their neighbours
+    pub fn new(
+        api_key: &str,
+        model: &str,
+        task: JinaTask,
+        late_chunking: bool,
+        config: EmbeddingConfig,
+    ) -> Result<Self> {
+        let client = config.http_client()?;
+
+        Ok(Self {
+            client,
+            api_key: api_key.to_string(),
+            model: model.to_string(),
+            task,
+            late_chunking,
+            config,
+            tokenizer: Tokenizer::for_huggingface(&f!("jinaai/{model}")),
+        })
+    }
+
+    async fn embed_batch(&self, batch: Vec<String>) -> Result<Vec<Embedding>> {
+        self.config
+            .retry
+            .run(
+                "Jina embedding request",
+                || self.send(&batch),
+                classify_error,
+            )
+            .await
+            .map_err(|(e, _)| e)
+    }
+
+    async fn send(&self, batch: &[String]) -> Result<Vec<Embedding>> {
+        self.config.throttle(&self.tokenizer, batch).await;
+
+        let response = self
+            .client
+            .post(JINA_API_URL)
+            .bearer_auth(&self.api_key)
+            .json(&JinaEmbeddingRequest {
+                model: &self.model,
+                input: batch,
+                // Only v3 accepts a task
+                task: self.model.starts_with("jina-embeddings-v3").then_some(self.task),
+                late_chunking: self.late_chunking,
+            })
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(api_error(response).await);
+        }
+
+        let mut embedding_response: JinaEmbeddingResponse = response.json().await?;
+        embedding_response.data.sort_by_key(|data| data.index);
+
+        Ok(embedding_response.data.into_iter().map(|data| data.embedding).collect())
+    }
+}
+
+#[async_trait]
+impl EmbeddingClient for JinaEmbeddingClient {
+    async fn embed(&self, chunks: &[CodeChunk]) -> Result<Vec<Embedding>> {
+        let texts: Vec<String> =
+            chunks.iter().map(|chunk| self.tokenizer.fit(chunk, CONTEXT_LENGTH)).collect();
+
+        // Late chunking concatenates the whole batch, so it has to fit the context window
+        let batch_tokens = match self.late_chunking {
+            true => CONTEXT_LENGTH,
+            false => usize::MAX,
+        };
+
+        let batches =
+            self.tokenizer.batches(&texts, String::as_str, MAX_BATCH_INPUTS, batch_tokens);
+
+        // `buffered` yields results in submission order, keeping embeddings aligned with chunks
+        let batches: Vec<Vec<Embedding>> =
+            stream::iter(batches.into_iter().map(<[String]>::to_vec))
+                .map(|batch| self.embed_batch(batch))
+                .buffered(self.config.concurrency())
+                .try_collect()
+                .await?;
+
+        Ok(batches.into_iter().flatten().collect())
+    }
+
+    async fn context_length(&self) -> Result<usize> {
+        Ok(CONTEXT_LENGTH)
+    }
+
+    async fn embed_length(&self) -> Result<usize> {
+        match self.model.as_str() {
+            "jina-embeddings-v3" => Ok(1024),
+            "jina-embeddings-v2-base-code" => Ok(768),
+            _ => {
+                let embeddings = self.send(&["test".to_string()]).await?;
+
+                embeddings
+                    .first()
+                    .map(|embedding| embedding.len())
+                    .ok_or(Error::Embedding("Empty embedding response".to_string()))
+            },
+        }
+    }
+
+    fn tokenizer(&self) -> Tokenizer {
+        self.tokenizer.clone()
+    }
+}
@@ -0,0 +1,91 @@
+use std::{fmt, path::PathBuf, str::FromStr, sync::Arc};
+
+use async_trait::async_trait;
+use fastembed::{EmbeddingModel, InitOptions, TextEmbedding};
+use tracing::info;
+
+use super::{Embedding, client::EmbeddingClient, tokenizer::Tokenizer};
+use crate::{chunking::CodeChunk, prelude::*};
+
+const MAX_LENGTH: usize = 512;
+const BATCH_SIZE: usize = 64;
+
+/// Runs an ONNX embedding model in-process with fastembed
+#[derive(Clone)]
+pub struct LocalEmbeddingClient {
+    model: Arc<TextEmbedding>,
+    model_name: String,
+    embed_length: usize,
+    tokenizer: Tokenizer,
+}
+
+impl fmt::Debug for LocalEmbeddingClient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LocalEmbeddingClient")
+            .field("model_name", &self.model_name)
+            .field("embed_length", &self.embed_length)
+            .finish()
+    }
+}
+
+impl LocalEmbeddingClient {
+    /// Load `model` (e.g. `BAAI/bge-small-en-v1.5`), downloading it into `cache_dir` the first
+    /// time it's used
+    pub fn new(model: &str, cache_dir: PathBuf) -> Result<Self> {
+        let embedding_model = EmbeddingModel::from_str(model).map_err(|e| {
+            let supported = TextEmbedding::list_supported_models()
+                .into_iter()
+                .map(|info| info.model_code)
+                .collect::<Vec<_>>()
+                .join(", ");
+            InvalidArgument(f!("{e}. Supported models: {supported}"))
+        })?;
+
+        let embed_length = TextEmbedding::get_model_info(&embedding_model)
+            .map_err(|e| Error::Embedding(e.to_string()))?
+            .dim;
+
+        info!("Loading local embedding model {model}");
+        let text_embedding = TextEmbedding::try_new(
+            InitOptions::new(embedding_model)
+                .with_max_length(MAX_LENGTH)
+                .with_cache_dir(cache_dir)
+                .with_show_download_progress(true),
+        )
+        .map_err(|e| Error::Embedding(e.to_string()))?;
+
+        Ok(Self {
+            model: Arc::new(text_embedding),
+            model_name: model.to_string(),
+            embed_length,
+            tokenizer: Tokenizer::for_huggingface(model),
+        })
+    }
+}
+
+#[async_trait]
+impl EmbeddingClient for LocalEmbeddingClient {
+    async fn embed(&self, chunks: &[CodeChunk]) -> Result<Vec<Embedding>> {
+        // fastembed truncates inputs to the model's max length itself
+        let texts: Vec<String> = chunks.iter().map(|chunk| chunk.content.clone()).collect();
+        let model = self.model.clone();
+
+        // Inference is CPU bound, keep it off the async workers
+        tokio::task::spawn_blocking(move || model.embed(texts, Some(BATCH_SIZE)))
+            .await
+            .map_err(|e| Error::Embedding(e.to_string()))?
+            .map_err(|e| Error::Embedding(e.to_string()))
+    }
+
+    async fn context_length(&self) -> Result<usize> {
+        Ok(MAX_LENGTH)
+    }
+
+    async fn embed_length(&self) -> Result<usize> {
+        Ok(self.embed_length)
+    }
+
+    fn tokenizer(&self) -> Tokenizer {
+        self.tokenizer.clone()
+    }
+}
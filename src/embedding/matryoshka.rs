@@ -0,0 +1,85 @@
+use async_trait::async_trait;
+use tracing::warn;
+
+use super::{Embedding, client::EmbeddingClient, tokenizer::Tokenizer};
+use crate::{chunking::CodeChunk, prelude::*};
+
+/// Models trained with Matryoshka representation learning, whose leading dimensions stand alone
+fn supports_truncation(model: &str) -> bool {
+    let model = model.to_lowercase();
+
+    ["nomic-embed-text", "text-embedding-3", "mxbai-embed", "jina-embeddings-v3"]
+        .iter()
+        .any(|family| model.contains(family))
+        || (model.contains("snowflake-arctic-embed") && model.contains("v2"))
+}
+
+/// Wraps a client so embeddings are cut to their first `dimensions` values and re-normalized
+pub struct TruncatedEmbeddingClient<E: EmbeddingClient> {
+    inner: E,
+    dimensions: Option<usize>,
+}
+
+impl<E: EmbeddingClient> TruncatedEmbeddingClient<E> {
+    pub fn new(inner: E, model: &str, dimensions: Option<usize>) -> Result<Self> {
+        if dimensions == Some(0) {
+            return Err(InvalidArgument(String::from(
+                "Truncated dimension must be above 0",
+            )));
+        }
+
+        if dimensions.is_some() && !supports_truncation(model) {
+            warn!("{model} isn't known to be Matryoshka trained, truncation may hurt quality");
+        }
+
+        Ok(Self { inner, dimensions })
+    }
+}
+
+fn truncate(mut embedding: Embedding, dimensions: usize) -> Embedding {
+    embedding.truncate(dimensions);
+
+    let norm = embedding.iter().map(|v| v * v).sum::<f32>().sqrt();
+
+    if norm > 0.0 {
+        embedding.iter_mut().for_each(|v| *v /= norm);
+    }
+
+    embedding
+}
+
+#[async_trait]
+impl<E: EmbeddingClient> EmbeddingClient for TruncatedEmbeddingClient<E> {
+    async fn embed(&self, chunks: &[CodeChunk]) -> Result<Vec<Embedding>> {
+        let embeddings = self.inner.embed(chunks).await?;
+
+        let Some(dimensions) = self.dimensions else {
+            return Ok(embeddings);
+        };
+
+        Ok(embeddings
+            .into_iter()
+            .map(|embedding| truncate(embedding, dimensions))
+            .collect())
+    }
+
+    async fn context_length(&self) -> Result<usize> {
+        self.inner.context_length().await
+    }
+
+    async fn embed_length(&self) -> Result<usize> {
+        let length = self.inner.embed_length().await?;
+
+        match self.dimensions {
+            Some(dimensions) if dimensions > length => Err(InvalidArgument(f!(
+                "Can't truncate {length} dimension embeddings to {dimensions}"
+            ))),
+            Some(dimensions) => Ok(dimensions),
+            None => Ok(length),
+        }
+    }
+
+    fn tokenizer(&self) -> Tokenizer {
+        self.inner.tokenizer()
+    }
+}
@@ -1,50 +1,63 @@
+mod azure;
+mod cache;
+#[cfg(feature = "candle")]
+mod candle;
 mod client;
+mod cost;
+mod fallback;
+mod health;
 mod huggingface;
+mod jina;
+#[cfg(feature = "local")]
+mod local;
+mod matryoshka;
 mod ollama;
 mod openai;
+mod pooled;
+mod prefix;
+mod rate_limit;
+mod retry;
+mod tei;
+mod tokenizer;
+mod voyage;
 
-pub use client::EmbeddingClient;
+#[allow(unused_imports)]
+pub use azure::{AzureOpenAIEmbeddingClient, DEFAULT_AZURE_API_VERSION};
+pub use cache::{CachedEmbeddingClient, EmbeddingCache};
+#[cfg(feature = "candle")]
+#[allow(unused_imports)]
+pub use candle::{CandleDevice, CandleEmbeddingClient};
+#[allow(unused_imports)]
+pub use client::{EmbeddingClient, EmbeddingConfig, Usage};
+#[allow(unused_imports)]
+pub use cost::{CostEstimate, CostEstimator, cost_of};
+#[allow(unused_imports)]
+pub use fallback::FallbackEmbeddingClient;
+pub use health::health_check;
 #[allow(unused_imports)]
 pub use huggingface::HuggingFaceEmbeddingClient;
 #[allow(unused_imports)]
-pub use ollama::OllamaEmbeddingClient;
+pub use jina::{JinaEmbeddingClient, JinaTask};
+#[cfg(feature = "local")]
+#[allow(unused_imports)]
+pub use local::LocalEmbeddingClient;
+#[allow(unused_imports)]
+pub use matryoshka::TruncatedEmbeddingClient;
+#[allow(unused_imports)]
+pub use ollama::{OllamaEmbeddingClient, OllamaOptions};
 #[allow(unused_imports)]
 pub use openai::OpenAIEmbeddingClient;
-
-use crate::chunking::CodeChunk;
-use crate::prelude::*;
+#[allow(unused_imports)]
+pub use pooled::PooledEmbeddingClient;
+#[allow(unused_imports)]
+pub use prefix::{PrefixedEmbeddingClient, Prefixes};
+#[allow(unused_imports)]
+pub use rate_limit::{ConfiguredRateLimits, ProviderLimits, RateLimits};
+pub use retry::is_input_error;
+#[allow(unused_imports)]
+pub use tei::TeiEmbeddingClient;
+pub use tokenizer::Tokenizer;
+#[allow(unused_imports)]
+pub use voyage::VoyageEmbeddingClient;
 
 pub type Embedding = Vec<f32>;
-
-#[derive(Debug, Clone)]
-pub enum EmbeddingClientImpl {
-    Ollama(ollama::OllamaEmbeddingClient),
-    OpenAI(openai::OpenAIEmbeddingClient),
-    HuggingFace(huggingface::HuggingFaceEmbeddingClient),
-}
-
-impl EmbeddingClient for EmbeddingClientImpl {
-    async fn embed(&self, chunks: &[CodeChunk]) -> Result<Vec<Embedding>> {
-        match self {
-            Self::Ollama(client) => client.embed(chunks).await,
-            Self::OpenAI(client) => client.embed(chunks).await,
-            Self::HuggingFace(client) => client.embed(chunks).await,
-        }
-    }
-
-    async fn context_length(&mut self) -> Result<usize> {
-        match self {
-            Self::Ollama(client) => client.context_length().await,
-            Self::OpenAI(client) => client.context_length().await,
-            Self::HuggingFace(client) => client.context_length().await,
-        }
-    }
-
-    async fn embed_length(&mut self) -> Result<usize> {
-        match self {
-            Self::Ollama(client) => client.embed_length().await,
-            Self::OpenAI(client) => client.embed_length().await,
-            Self::HuggingFace(client) => client.embed_length().await,
-        }
-    }
-}
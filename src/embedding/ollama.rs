@@ -1,48 +1,102 @@
 use std::collections::HashMap;
 
+use async_trait::async_trait;
+use futures::{StreamExt, TryStreamExt, stream};
 use ollama_rs::{
     Ollama,
-    generation::embeddings::request::{EmbeddingsInput, GenerateEmbeddingsRequest},
+    generation::{
+        embeddings::request::{EmbeddingsInput, GenerateEmbeddingsRequest},
+        options::GenerationOptions,
+        parameters::{KeepAlive, TimeUnit},
+    },
 };
+use reqwest::Client as ReqwestClient;
 use serde::Deserialize;
 use serde_json::json;
+use tokio::sync::OnceCell;
 use tracing::debug;
 use url::Url;
 
-use super::{Embedding, client::EmbeddingClient};
+use super::{
+    Embedding,
+    client::{EmbeddingClient, EmbeddingConfig},
+    tokenizer::Tokenizer,
+};
 use crate::{chunking::CodeChunk, prelude::*};
 
+const DEFAULT_CONTEXT_LENGTH: usize = 2048;
+
+/// Request options passed through to Ollama
+#[derive(Debug, Clone, Default)]
+pub struct OllamaOptions {
+    /// How long the model stays loaded after a request: `-1` (forever), `0`, or e.g. `10m`
+    pub keep_alive: Option<String>,
+    /// Context window to load the model with
+    pub num_ctx: Option<u32>,
+}
+
 #[derive(Debug, Clone)]
 pub struct OllamaEmbeddingClient {
     client: Ollama,
+    http: ReqwestClient,
     api_url: Url,
     model: String,
     batch_size: usize,
+    options: OllamaOptions,
+    config: EmbeddingConfig,
+    tokenizer: Tokenizer,
+    model_info: OnceCell<ModelInfo>,
+}
+
+/// Lengths Ollama reports for a model, looked up on first use
+#[derive(Debug, Clone)]
+struct ModelInfo {
     embed_length: Option<usize>,
     context_length: Option<usize>,
 }
 
 impl OllamaEmbeddingClient {
-    pub fn new(api_url: Url, port: u16, model: &str, batch_size: Option<usize>) -> Self {
-        let client = Ollama::new(api_url.to_owned(), port);
+    pub fn new(
+        api_url: Url,
+        port: u16,
+        model: &str,
+        batch_size: Option<usize>,
+        options: OllamaOptions,
+        config: EmbeddingConfig,
+    ) -> Result<Self> {
+        // Fail on a bad value up front rather than on the first request
+        if let Some(keep_alive) = &options.keep_alive {
+            parse_keep_alive(keep_alive)?;
+        }
+
+        let http = config.http_client()?;
+        let client = Ollama::new_with_client(api_url.to_owned(), port, http.clone());
 
-        Self {
+        Ok(Self {
             client,
+            http,
             api_url,
             model: model.to_string(),
             batch_size: batch_size.unwrap_or(512),
-            embed_length: None,
-            context_length: None,
-        }
+            options,
+            config,
+            tokenizer: Tokenizer::for_huggingface(model),
+            model_info: OnceCell::new(),
+        })
     }
 
-    async fn get_model_url(&mut self) -> Result<()> {
+    async fn model_info(&self) -> Result<&ModelInfo> {
+        self.model_info.get_or_try_init(|| self.fetch_model_info()).await
+    }
+
+    async fn fetch_model_info(&self) -> Result<ModelInfo> {
         #[derive(Deserialize)]
         struct ModelResponse {
             model_info: HashMap<String, serde_json::Value>,
         }
 
-        let response = reqwest::Client::new()
+        let response = self
+            .http
             .post(self.api_url.join("api/show")?)
             .json(&json!({"name": self.model}))
             .send()
@@ -56,59 +110,147 @@ impl OllamaEmbeddingClient {
             .map_err(|e| Payload(f!("Failed to get model info. {e}")))?
             .model_info;
 
-        self.embed_length = model_response
-            .iter()
-            .find(|(key, _)| key.ends_with(".embedding_length"))
-            .and_then(|(_, value)| value.as_u64().map(|v| v as usize));
+        let length = |suffix: &str| {
+            model_response
+                .iter()
+                .find(|(key, _)| key.ends_with(suffix))
+                .and_then(|(_, value)| value.as_u64().map(|v| v as usize))
+        };
 
-        self.context_length = model_response
-            .iter()
-            .find(|(key, _)| key.ends_with(".context_length"))
-            .and_then(|(_, value)| value.as_u64().map(|v| v as usize));
+        Ok(ModelInfo {
+            embed_length: length(".embedding_length"),
+            context_length: length(".context_length"),
+        })
+    }
 
-        Ok(())
+    /// Group chunks into requests bounded by `batch_size` and the model's context length
+    fn batches<'a>(&self, chunks: &'a [CodeChunk]) -> Vec<&'a [CodeChunk]> {
+        self.tokenizer.batches(
+            chunks,
+            |chunk| &chunk.content,
+            self.batch_size,
+            self.max_tokens(),
+        )
     }
-}
 
-impl EmbeddingClient for OllamaEmbeddingClient {
-    async fn embed(&self, chunks: &[CodeChunk]) -> Result<Vec<Embedding>> {
-        let mut all_embeddings = Vec::with_capacity(chunks.len());
+    fn max_tokens(&self) -> usize {
+        let context_length = self
+            .model_info
+            .get()
+            .and_then(|info| info.context_length)
+            .unwrap_or(DEFAULT_CONTEXT_LENGTH);
 
-        for chunk_batch in chunks.chunks(self.batch_size) {
-            let mut batch_embeddings = Vec::with_capacity(chunk_batch.len());
+        match self.options.num_ctx {
+            Some(num_ctx) => context_length.min(num_ctx as usize),
+            None => context_length,
+        }
+    }
 
-            for chunk in chunk_batch {
-                debug!("Generating embedding for chunk from {:?}", chunk.path);
+    async fn embed_batch(&self, inputs: Vec<String>) -> Result<Vec<Embedding>> {
+        debug!(
+            "Generating embeddings for a batch of {} chunks",
+            inputs.len()
+        );
 
-                let request = GenerateEmbeddingsRequest::new(
-                    self.model.to_string(),
-                    EmbeddingsInput::Single(chunk.content.to_string()),
-                );
-                let response = self.client.generate_embeddings(request).await?;
+        let input_count = inputs.len();
+        self.config.throttle(&self.tokenizer, &inputs).await;
 
-                batch_embeddings.extend(response.embeddings);
-            }
+        let mut request = GenerateEmbeddingsRequest::new(
+            self.model.to_string(),
+            EmbeddingsInput::Multiple(inputs),
+        );
 
-            all_embeddings.extend(batch_embeddings);
+        if let Some(num_ctx) = self.options.num_ctx {
+            request = request.options(GenerationOptions::default().num_ctx(num_ctx.into()));
         }
 
+        if let Some(keep_alive) = &self.options.keep_alive {
+            request = request.keep_alive(parse_keep_alive(keep_alive)?);
+        }
+
+        let response = self.client.generate_embeddings(request).await?;
+
+        if response.embeddings.len() != input_count {
+            return Err(Error::Embedding(f!(
+                "Ollama returned {} embeddings for {} chunks",
+                response.embeddings.len(),
+                input_count
+            )));
+        }
+
+        Ok(response.embeddings)
+    }
+}
+
+/// Parse Ollama's `keep_alive` syntax: `-1`, `0`, or a number of seconds, minutes or hours
+fn parse_keep_alive(value: &str) -> Result<KeepAlive> {
+    let invalid = || {
+        InvalidArgument(f!(
+            "Invalid keep_alive {value:?}, expected -1, 0 or e.g. 10m"
+        ))
+    };
+
+    match value.trim() {
+        "-1" => Ok(KeepAlive::Indefinitely),
+        "0" => Ok(KeepAlive::UnloadOnCompletion),
+        value => {
+            let (time, unit) = match value.char_indices().last() {
+                Some((i, 's')) => (&value[..i], TimeUnit::Seconds),
+                Some((i, 'm')) => (&value[..i], TimeUnit::Minutes),
+                Some((i, 'h')) => (&value[..i], TimeUnit::Hours),
+                _ => (value, TimeUnit::Seconds),
+            };
+
+            Ok(KeepAlive::Until {
+                time: time.parse().map_err(|_| invalid())?,
+                unit,
+            })
+        },
+    }
+}
+
+#[async_trait]
+impl EmbeddingClient for OllamaEmbeddingClient {
+    async fn embed(&self, chunks: &[CodeChunk]) -> Result<Vec<Embedding>> {
+        let inputs: Vec<Vec<String>> = self
+            .batches(chunks)
+            .into_iter()
+            .map(|chunk_batch| {
+                chunk_batch
+                    .iter()
+                    .map(|chunk| self.tokenizer.fit(chunk, self.max_tokens()))
+                    .collect()
+            })
+            .collect();
+
+        // `buffered` yields results in submission order, keeping embeddings aligned with chunks
+        let batches: Vec<Vec<Embedding>> = stream::iter(inputs)
+            .map(|inputs| self.embed_batch(inputs))
+            .buffered(self.config.concurrency())
+            .try_collect()
+            .await?;
+
+        let all_embeddings: Vec<Embedding> = batches.into_iter().flatten().collect();
+
         debug!("Generated {} embeddings with Ollama", all_embeddings.len());
         Ok(all_embeddings)
     }
 
-    async fn context_length(&mut self) -> Result<usize> {
-        if self.context_length.is_none() {
-            self.get_model_url().await?;
-        }
-
-        self.context_length.ok_or(Missing(String::from("Context length not found")))
+    async fn context_length(&self) -> Result<usize> {
+        self.model_info()
+            .await?
+            .context_length
+            .ok_or(Missing(String::from("Context length not found")))
     }
 
-    async fn embed_length(&mut self) -> Result<usize> {
-        if self.context_length.is_none() {
-            self.get_model_url().await?;
-        }
+    async fn embed_length(&self) -> Result<usize> {
+        self.model_info()
+            .await?
+            .embed_length
+            .ok_or(Missing(String::from("Embedding length not found")))
+    }
 
-        self.embed_length.ok_or(Missing(String::from("Embedding length not found")))
+    fn tokenizer(&self) -> Tokenizer {
+        self.tokenizer.clone()
     }
 }
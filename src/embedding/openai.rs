@@ -1,127 +1,233 @@
-use std::time::Duration;
-
-use reqwest::Client as ReqwestClient;
+use async_trait::async_trait;
+use futures::{StreamExt, TryStreamExt, stream};
+use reqwest::{Client as ReqwestClient, RequestBuilder};
 use serde::{Deserialize, Serialize};
-
-use super::{Embedding, client::EmbeddingClient};
+use url::Url;
+
+use super::{
+    Embedding,
+    client::{EmbeddingClient, EmbeddingConfig},
+    retry::{api_error, classify_error},
+    tokenizer::Tokenizer,
+};
 use crate::{chunking::CodeChunk, error::Error, prelude::*};
 
 #[derive(Debug, Clone)]
 pub struct OpenAIEmbeddingClient {
     client: ReqwestClient,
-    api_key: String,
+    api_url: Url,
+    api_key: Option<String>,
     model: String,
+    dimensions: Option<usize>,
+    tokenizer: Tokenizer,
+    config: EmbeddingConfig,
 }
 
 #[derive(Serialize)]
-struct OpenAIEmbeddingRequest {
-    model: String,
-    input: Vec<String>,
+pub(super) struct OpenAIEmbeddingRequest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    pub input: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dimensions: Option<usize>,
 }
 
 #[derive(Deserialize)]
-struct OpenAIEmbeddingResponse {
-    data: Vec<OpenAIEmbeddingData>,
+pub(super) struct OpenAIEmbeddingResponse {
+    pub data: Vec<OpenAIEmbeddingData>,
 }
 
 #[derive(Deserialize)]
-struct OpenAIEmbeddingData {
-    embedding: Vec<f32>,
+pub(super) struct OpenAIEmbeddingData {
+    pub embedding: Vec<f32>,
+    pub index: usize,
+}
+
+impl OpenAIEmbeddingResponse {
+    /// Embeddings in input order, checked against the `inputs` they were requested for
+    pub fn into_embeddings(mut self, provider: &str, inputs: usize) -> Result<Vec<Embedding>> {
+        if self.data.len() != inputs {
+            return Err(Error::Embedding(f!(
+                "{provider} returned {} embeddings for {inputs} chunks",
+                self.data.len()
+            )));
+        }
+
+        self.data.sort_by_key(|data| data.index);
+
+        Ok(self.data.into_iter().map(|data| data.embedding).collect())
+    }
 }
 
-const OPENAI_API_URL: &str = "https://api.openai.com/v1/embeddings";
+// FIXME: This is AI generated, I don't have an API key so need to find out if this works
+// at some point
+pub(super) fn known_context_length(model: &str) -> usize {
+    match model {
+        "text-embedding-ada-002" => 8191,
+        "text-embedding-3-small" => 8191,
+        "text-embedding-3-large" => 8191,
+        _ => 2048,
+    }
+}
+
+pub(super) fn known_embed_length(model: &str) -> Option<usize> {
+    match model {
+        "text-embedding-ada-002" => Some(1536),
+        "text-embedding-3-small" => Some(1536),
+        "text-embedding-3-large" => Some(3072),
+        _ => None,
+    }
+}
+
+const OPENAI_BASE_URL: &str = "https://api.openai.com/v1/";
+
+/// Per-request limits of the embeddings endpoint
+pub(super) const MAX_BATCH_INPUTS: usize = 2048;
+pub(super) const MAX_BATCH_TOKENS: usize = 300_000;
 
 impl OpenAIEmbeddingClient {
-    pub fn new(api_key: &str, model: &str) -> Self {
-        let client = ReqwestClient::builder()
-            .timeout(Duration::from_secs(120))
-            .build()
-            .expect("Failed to build HTTP client");
+    /// `base_url` points the client at any OpenAI-compatible server, in which case `api_key` may
+    /// be omitted. `dimensions` asks text-embedding-3 models for shortened embeddings
+    pub fn new(
+        api_key: Option<&str>,
+        model: &str,
+        base_url: Option<Url>,
+        dimensions: Option<usize>,
+        config: EmbeddingConfig,
+    ) -> Result<Self> {
+        if base_url.is_none() && api_key.is_none() {
+            return Err(Missing(String::from("OPENAI_API_KEY environment variable")));
+        }
+
+        // Compatible servers name their models freely, so only check against OpenAI's own
+        if base_url.is_none() && dimensions.is_some() && !model.starts_with("text-embedding-3") {
+            return Err(InvalidArgument(f!(
+                "{model} doesn't support reduced dimensions, only text-embedding-3 models do"
+            )));
+        }
+
+        let client = config.http_client()?;
+
+        let mut base_url = base_url.unwrap_or(Url::parse(OPENAI_BASE_URL)?);
 
-        Self {
+        // Without a trailing slash `join` would replace the last path segment
+        if !base_url.path().ends_with('/') {
+            base_url.set_path(&f!("{}/", base_url.path()));
+        }
+
+        Ok(Self {
             client,
-            api_key: api_key.to_string(),
+            api_url: base_url.join("embeddings")?,
+            api_key: api_key.map(str::to_string),
             model: model.to_string(),
-        }
+            dimensions,
+            tokenizer: Tokenizer::for_openai(model),
+            config,
+        })
     }
-}
 
-impl EmbeddingClient for OpenAIEmbeddingClient {
-    async fn embed(&self, chunks: &[CodeChunk]) -> Result<Vec<Embedding>> {
-        // FIXME: This is AI generated, I don't have an API key so need to find out if this works
-        // at some point
-
-        let texts: Vec<String> = chunks.iter().map(|chunk| chunk.content.clone()).collect();
+    fn max_tokens(&self) -> usize {
+        known_context_length(&self.model)
+    }
 
-        let mut all_embeddings = Vec::new();
+    async fn embed_batch(&self, batch: Vec<String>) -> Result<Vec<Embedding>> {
+        let request = OpenAIEmbeddingRequest {
+            model: Some(self.model.clone()),
+            input: batch,
+            dimensions: self.dimensions,
+        };
+
+        self.config
+            .retry
+            .run(
+                "OpenAI embedding request",
+                || self.send(&request),
+                classify_error,
+            )
+            .await
+            .map_err(|(e, _)| e)
+    }
 
-        for batch in texts.chunks(20) {
-            let request = OpenAIEmbeddingRequest {
-                model: self.model.clone(),
-                input: batch.to_vec(),
-            };
+    fn post(&self) -> RequestBuilder {
+        let request = self.client.post(self.api_url.clone());
 
-            let response = self
-                .client
-                .post(OPENAI_API_URL)
-                .header("Authorization", f!("Bearer {}", self.api_key))
-                .json(&request)
-                .send()
-                .await?;
+        match &self.api_key {
+            Some(api_key) => request.bearer_auth(api_key),
+            None => request,
+        }
+    }
 
-            if !response.status().is_success() {
-                let error_text = response.text().await?;
-                return Err(Error::Embedding(error_text));
-            }
+    async fn send(&self, request: &OpenAIEmbeddingRequest) -> Result<Vec<Embedding>> {
+        self.config.throttle(&self.tokenizer, &request.input).await;
 
-            let embedding_response: OpenAIEmbeddingResponse = response.json().await?;
+        let response = self.post().json(request).send().await?;
 
-            all_embeddings.extend(embedding_response.data.into_iter().map(|data| data.embedding));
+        if !response.status().is_success() {
+            return Err(api_error(response).await);
         }
 
-        Ok(all_embeddings)
+        let embedding_response: OpenAIEmbeddingResponse = response.json().await?;
+
+        embedding_response.into_embeddings("OpenAI", request.input.len())
     }
+}
 
-    async fn context_length(&mut self) -> Result<usize> {
+#[async_trait]
+impl EmbeddingClient for OpenAIEmbeddingClient {
+    async fn embed(&self, chunks: &[CodeChunk]) -> Result<Vec<Embedding>> {
         // FIXME: This is AI generated, I don't have an API key so need to find out if this works
         // at some point
 
-        Ok(match self.model.as_str() {
-            "text-embedding-ada-002" => 8191,
-            "text-embedding-3-small" => 8191,
-            "text-embedding-3-large" => 8191,
-            _ => 2048,
-        })
+        let texts: Vec<String> = chunks
+            .iter()
+            .map(|chunk| self.tokenizer.fit(chunk, self.max_tokens()))
+            .collect();
+
+        // Fill each request up to the endpoint's input and token limits
+        let batches =
+            self.tokenizer
+                .batches(&texts, String::as_str, MAX_BATCH_INPUTS, MAX_BATCH_TOKENS);
+
+        // `buffered` yields results in submission order, keeping embeddings aligned with chunks
+        let batches: Vec<Vec<Embedding>> =
+            stream::iter(batches.into_iter().map(<[String]>::to_vec))
+                .map(|batch| self.embed_batch(batch))
+                .buffered(self.config.concurrency())
+                .try_collect()
+                .await?;
+
+        Ok(batches.into_iter().flatten().collect())
     }
 
-    async fn embed_length(&mut self) -> Result<usize> {
-        // FIXME: This is AI generated, I don't have an API key so need to find out if this works
-        // at some point
+    async fn context_length(&self) -> Result<usize> {
+        Ok(self.max_tokens())
+    }
 
-        Ok(match self.model.as_str() {
-            "text-embedding-ada-002" => 1536,
-            "text-embedding-3-small" => 1536,
-            "text-embedding-3-large" => 3072,
-            _ => {
-                // For unknown models, make a small test request
-                let test_response = self
-                    .client
-                    .post(OPENAI_API_URL)
-                    .header("Authorization", format!("Bearer {}", self.api_key))
-                    .json(&OpenAIEmbeddingRequest {
-                        model: self.model.clone(),
-                        input: vec!["test".to_string()],
-                    })
-                    .send()
-                    .await?;
-
-                let embedding_response: OpenAIEmbeddingResponse = test_response.json().await?;
-                if embedding_response.data.is_empty() {
-                    return Err(Error::Embedding("Empty embedding response".to_string()));
-                }
-
-                embedding_response.data[0].embedding.len()
-            },
-        })
+    async fn embed_length(&self) -> Result<usize> {
+        if let Some(dimensions) = self.dimensions {
+            return Ok(dimensions);
+        }
+
+        if let Some(length) = known_embed_length(&self.model) {
+            return Ok(length);
+        }
+
+        // For unknown models, make a small test request
+        let embeddings = self
+            .send(&OpenAIEmbeddingRequest {
+                model: Some(self.model.clone()),
+                input: vec!["test".to_string()],
+                dimensions: None,
+            })
+            .await?;
+
+        embeddings
+            .first()
+            .map(|embedding| embedding.len())
+            .ok_or(Error::Embedding("Empty embedding response".to_string()))
+    }
+
+    fn tokenizer(&self) -> Tokenizer {
+        self.tokenizer.clone()
     }
 }
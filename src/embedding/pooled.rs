@@ -0,0 +1,118 @@
+use async_trait::async_trait;
+use tracing::debug;
+
+use super::{Embedding, client::EmbeddingClient, tokenizer::Tokenizer};
+use crate::{chunking::CodeChunk, prelude::*};
+
+/// Wraps a client so chunks longer than the context window are embedded in parts and
+/// mean-pooled back into one embedding instead of being truncated
+pub struct PooledEmbeddingClient<E: EmbeddingClient> {
+    inner: E,
+    tokenizer: Tokenizer,
+    max_tokens: usize,
+}
+
+impl<E: EmbeddingClient> PooledEmbeddingClient<E> {
+    pub async fn new(inner: E) -> Result<Self> {
+        // Looked up first, some clients only learn their tokenizer along with it
+        let max_tokens = inner.context_length().await?;
+
+        Ok(Self {
+            tokenizer: inner.tokenizer(),
+            inner,
+            max_tokens,
+        })
+    }
+}
+
+/// Average `embeddings` and scale the result back to unit length
+fn mean_pool(embeddings: &[Embedding]) -> Embedding {
+    let mut pooled = vec![0.0; embeddings.first().map_or(0, Vec::len)];
+
+    for embedding in embeddings {
+        for (sum, value) in pooled.iter_mut().zip(embedding) {
+            *sum += value;
+        }
+    }
+
+    let norm = pooled.iter().map(|v| v * v).sum::<f32>().sqrt();
+
+    if norm > 0.0 {
+        pooled.iter_mut().for_each(|v| *v /= norm);
+    }
+
+    pooled
+}
+
+#[async_trait]
+impl<E: EmbeddingClient> EmbeddingClient for PooledEmbeddingClient<E> {
+    async fn embed(&self, chunks: &[CodeChunk]) -> Result<Vec<Embedding>> {
+        // Number of parts each chunk was embedded as, to regroup the flat results
+        let mut part_counts = Vec::with_capacity(chunks.len());
+        let mut parts = Vec::with_capacity(chunks.len());
+
+        for chunk in chunks {
+            if self.tokenizer.count(&chunk.content) <= self.max_tokens {
+                part_counts.push(1);
+                parts.push(chunk.clone());
+                continue;
+            }
+
+            let pieces = self.tokenizer.split(&chunk.content, self.max_tokens);
+
+            debug!(
+                "Embedding {}:{} in {} parts",
+                chunk.path.display(),
+                chunk.start_line,
+                pieces.len()
+            );
+
+            part_counts.push(pieces.len());
+            parts.extend(pieces.into_iter().map(|content| CodeChunk {
+                content,
+                ..chunk.clone()
+            }));
+        }
+
+        if parts.len() == chunks.len() {
+            return self.inner.embed(chunks).await;
+        }
+
+        let embeddings = self.inner.embed(&parts).await?;
+
+        if embeddings.len() != parts.len() {
+            return Err(Error::Embedding(f!(
+                "Expected {} embeddings, got {}",
+                parts.len(),
+                embeddings.len()
+            )));
+        }
+
+        let mut embeddings = embeddings.as_slice();
+
+        Ok(part_counts
+            .into_iter()
+            .map(|count| {
+                let (chunk_embeddings, rest) = embeddings.split_at(count);
+                embeddings = rest;
+
+                match chunk_embeddings {
+                    [embedding] => embedding.clone(),
+                    _ => mean_pool(chunk_embeddings),
+                }
+            })
+            .collect())
+    }
+
+    async fn context_length(&self) -> Result<usize> {
+        Ok(self.max_tokens)
+    }
+
+    async fn embed_length(&self) -> Result<usize> {
+        self.inner.embed_length().await
+    }
+
+    fn tokenizer(&self) -> Tokenizer {
+        self.tokenizer.clone()
+    }
+}
@@ -0,0 +1,111 @@
+use async_trait::async_trait;
+
+use super::{Embedding, client::EmbeddingClient, tokenizer::Tokenizer};
+use crate::{chunking::CodeChunk, prelude::*};
+
+/// Instructions a model expects in front of the text it embeds
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Prefixes {
+    pub document: String,
+    pub query: String,
+}
+
+impl Prefixes {
+    fn new(document: &str, query: &str) -> Self {
+        Self {
+            document: document.to_string(),
+            query: query.to_string(),
+        }
+    }
+
+    /// Prefixes published with known model families, matched on the model name
+    pub fn for_model(model: &str) -> Option<Self> {
+        // Strip any organisation (`BAAI/`) or tag (`:latest`) around the name
+        let name = model.rsplit('/').next().unwrap_or(model);
+        let name = name.split(':').next().unwrap_or(name).to_lowercase();
+
+        let bge_query = "Represent this sentence for searching relevant passages: ";
+
+        match name.as_str() {
+            name if name.starts_with("nomic-embed") => {
+                Some(Self::new("search_document: ", "search_query: "))
+            },
+            name if name.contains("e5-") => Some(Self::new("passage: ", "query: ")),
+            name if name.starts_with("bge-") && name.contains("-en") => {
+                Some(Self::new("", bge_query))
+            },
+            name if name.starts_with("gte-qwen") => Some(Self::new(
+                "",
+                "Instruct: Given a web search query, retrieve relevant passages that answer the \
+                 query\nQuery: ",
+            )),
+            name if name.starts_with("snowflake-arctic-embed") && name.contains("v2") => {
+                Some(Self::new("", "query: "))
+            },
+            name if name.starts_with("snowflake-arctic-embed") => Some(Self::new("", bge_query)),
+            _ => None,
+        }
+    }
+
+    /// Known prefixes for `model`, with either side replaced by an explicit override
+    pub fn resolve(model: &str, document: Option<String>, query: Option<String>) -> Self {
+        let known = Self::for_model(model).unwrap_or_default();
+
+        Self {
+            document: document.unwrap_or(known.document),
+            query: query.unwrap_or(known.query),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.document.is_empty() && self.query.is_empty()
+    }
+
+    /// `text` behind the query prefix
+    pub fn query(&self, text: &str) -> String {
+        f!("{}{text}", self.query)
+    }
+}
+
+/// Wraps a client so every chunk is sent with the model's document prefix
+pub struct PrefixedEmbeddingClient<E: EmbeddingClient> {
+    inner: E,
+    prefixes: Prefixes,
+}
+
+impl<E: EmbeddingClient> PrefixedEmbeddingClient<E> {
+    pub fn new(inner: E, prefixes: Prefixes) -> Self {
+        Self { inner, prefixes }
+    }
+}
+
+#[async_trait]
+impl<E: EmbeddingClient> EmbeddingClient for PrefixedEmbeddingClient<E> {
+    async fn embed(&self, chunks: &[CodeChunk]) -> Result<Vec<Embedding>> {
+        if self.prefixes.document.is_empty() {
+            return self.inner.embed(chunks).await;
+        }
+
+        let prefixed: Vec<CodeChunk> = chunks
+            .iter()
+            .map(|chunk| CodeChunk {
+                content: f!("{}{}", self.prefixes.document, chunk.content),
+                ..chunk.clone()
+            })
+            .collect();
+
+        self.inner.embed(&prefixed).await
+    }
+
+    async fn context_length(&self) -> Result<usize> {
+        self.inner.context_length().await
+    }
+
+    async fn embed_length(&self) -> Result<usize> {
+        self.inner.embed_length().await
+    }
+
+    fn tokenizer(&self) -> Tokenizer {
+        self.inner.tokenizer()
+    }
+}
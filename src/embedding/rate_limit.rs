@@ -0,0 +1,147 @@
+use std::{collections::HashMap, fmt, fs, num::NonZeroU32, path::Path};
+
+use governor::{DefaultDirectRateLimiter, Quota, RateLimiter};
+use serde::Deserialize;
+use tracing::debug;
+
+use crate::{prelude::*, utils::credentials::config_file};
+
+/// The parts of the config file rate limits are read from
+#[derive(Debug, Default, Deserialize)]
+struct ConfigFile {
+    #[serde(default)]
+    rate_limits: HashMap<String, ProviderLimits>,
+}
+
+/// A `[rate_limits.<provider>]` table of the config file
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+pub struct ProviderLimits {
+    pub requests_per_minute: Option<u32>,
+    pub tokens_per_minute: Option<u32>,
+}
+
+/// Limits of each provider account set in the config file, under `client` or `client:model`
+#[derive(Debug, Default)]
+pub struct ConfiguredRateLimits {
+    limits: HashMap<String, ProviderLimits>,
+}
+
+impl ConfiguredRateLimits {
+    /// Load the `[rate_limits]` tables of `path`, or of the user config file when `path` is
+    /// `None`. A missing default config file is not an error
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        let Some(path) = config_file(path) else {
+            return Ok(Self::default());
+        };
+
+        let content = fs::read_to_string(&path)?;
+        let config: ConfigFile = toml::from_str(&content)
+            .map_err(|e| InvalidArgument(f!("Invalid config file {}: {e}", path.display())))?;
+
+        debug!(
+            "Loaded rate limits of {} providers from {}",
+            config.rate_limits.len(),
+            path.display()
+        );
+
+        Ok(Self {
+            limits: config.rate_limits,
+        })
+    }
+
+    /// Limits of `model` on `client`, a `client:model` table taking precedence over a `client`
+    /// one. Unset when neither is there
+    pub fn get(&self, client: &str, model: &str) -> ProviderLimits {
+        self.limits
+            .get(&f!("{client}:{model}"))
+            .or(self.limits.get(client))
+            .copied()
+            .unwrap_or_default()
+    }
+}
+
+/// Requests-per-minute and tokens-per-minute budgets for one provider account
+pub struct RateLimits {
+    requests: Option<DefaultDirectRateLimiter>,
+    tokens: Option<(DefaultDirectRateLimiter, NonZeroU32)>,
+}
+
+impl fmt::Debug for RateLimits {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RateLimits")
+            .field("requests", &self.requests.is_some())
+            .field(
+                "tokens",
+                &self.tokens.as_ref().map(|(_, per_minute)| per_minute),
+            )
+            .finish()
+    }
+}
+
+impl RateLimits {
+    /// `None` when neither limit is set, a zero limit counts as unset
+    pub fn new(requests_per_minute: Option<u32>, tokens_per_minute: Option<u32>) -> Option<Self> {
+        let requests_per_minute = requests_per_minute.and_then(NonZeroU32::new);
+        let tokens_per_minute = tokens_per_minute.and_then(NonZeroU32::new);
+
+        if requests_per_minute.is_none() && tokens_per_minute.is_none() {
+            return None;
+        }
+
+        Some(Self {
+            requests: requests_per_minute.map(|rpm| RateLimiter::direct(Quota::per_minute(rpm))),
+            tokens: tokens_per_minute.map(|tpm| (RateLimiter::direct(Quota::per_minute(tpm)), tpm)),
+        })
+    }
+
+    /// Wait until a request carrying `tokens` tokens fits in both budgets
+    pub async fn wait(&self, tokens: usize) {
+        if let Some(requests) = &self.requests {
+            requests.until_ready().await;
+        }
+
+        if let Some((limiter, per_minute)) = &self.tokens {
+            // A request over a whole minute's budget could never go out, so it waits for all of it
+            let tokens = u32::try_from(tokens).unwrap_or(u32::MAX).min(per_minute.get());
+
+            if let Some(tokens) = NonZeroU32::new(tokens) {
+                // Can't fail, `tokens` is capped at the burst size
+                let _ = limiter.until_n_ready(tokens).await;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn model_limits_take_precedence_over_the_provider_ones() {
+        let config: ConfigFile = toml::from_str(
+            r#"
+            [rate_limits.openai]
+            requests_per_minute = 500
+
+            [rate_limits."openai:text-embedding-3-large"]
+            tokens_per_minute = 1000000
+            "#,
+        )
+        .unwrap();
+        let limits = ConfiguredRateLimits {
+            limits: config.rate_limits,
+        };
+
+        let small = limits.get("openai", "text-embedding-3-small");
+        assert_eq!(small.requests_per_minute, Some(500));
+        assert_eq!(small.tokens_per_minute, None);
+
+        let large = limits.get("openai", "text-embedding-3-large");
+        assert_eq!(large.requests_per_minute, None);
+        assert_eq!(large.tokens_per_minute, Some(1_000_000));
+
+        // Providers without limits aren't held back at all
+        let voyage = limits.get("voyage", "voyage-code-3");
+        assert!(RateLimits::new(voyage.requests_per_minute, voyage.tokens_per_minute).is_none());
+    }
+}
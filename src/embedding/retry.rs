@@ -0,0 +1,54 @@
+use std::time::Duration;
+
+use ollama_rs::error::OllamaError;
+use reqwest::{Response, StatusCode, header::RETRY_AFTER};
+
+use crate::{prelude::*, utils::retry::Backoff};
+
+/// Turn an unsuccessful provider response into an error, keeping what's needed to retry it
+pub async fn api_error(response: Response) -> Error {
+    let status = response.status();
+    let retry_after = response
+        .headers()
+        .get(RETRY_AFTER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse::<u64>().ok())
+        .map(Duration::from_secs);
+    let message = response.text().await.unwrap_or_default();
+
+    EmbeddingApi {
+        status: status.as_u16(),
+        retry_after,
+        message,
+    }
+}
+
+/// Whether `error` is about the chunks that were sent rather than the provider: input it
+/// rejected, or a response that doesn't match the request. Sending the chunks one at a time can
+/// then find the ones at fault, where a provider error would fail every one of them
+pub fn is_input_error(error: &Error) -> bool {
+    match error {
+        EmbeddingApi { status, .. } => matches!(status, 400 | 413 | 422),
+        Embedding(_) => true,
+        Ollama(OllamaError::InternalError(_) | OllamaError::Other(_)) => true,
+        _ => false,
+    }
+}
+
+/// Rate limits, server errors and dropped connections are retried, anything else is not
+pub fn classify_error(error: &Error) -> Backoff {
+    match error {
+        EmbeddingApi {
+            status,
+            retry_after,
+            ..
+        } if *status == StatusCode::TOO_MANY_REQUESTS.as_u16() || *status >= 500 => {
+            match retry_after {
+                Some(delay) => Backoff::RetryAfter(*delay),
+                None => Backoff::Retry,
+            }
+        },
+        Request(e) if e.is_timeout() || e.is_connect() => Backoff::Retry,
+        _ => Backoff::Stop,
+    }
+}
@@ -0,0 +1,166 @@
+use async_trait::async_trait;
+use futures::{StreamExt, TryStreamExt, stream};
+use reqwest::Client as ReqwestClient;
+use serde::{Deserialize, Serialize};
+use tokio::sync::OnceCell;
+use url::Url;
+
+use super::{
+    Embedding,
+    client::{EmbeddingClient, EmbeddingConfig},
+    retry::{api_error, classify_error},
+    tokenizer::Tokenizer,
+};
+use crate::{chunking::CodeChunk, prelude::*};
+
+const DEFAULT_MAX_INPUT_LENGTH: usize = 512;
+const DEFAULT_BATCH_SIZE: usize = 32;
+
+/// Client for a self-hosted text-embeddings-inference server
+#[derive(Debug, Clone)]
+pub struct TeiEmbeddingClient {
+    client: ReqwestClient,
+    api_url: Url,
+    config: EmbeddingConfig,
+    info: OnceCell<TeiInfo>,
+}
+
+#[derive(Serialize)]
+struct TeiEmbedRequest<'a> {
+    inputs: &'a [String],
+    // Let the server cut inputs down to its max input length
+    truncate: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct TeiInfo {
+    model_id: String,
+    max_input_length: Option<usize>,
+    max_client_batch_size: Option<usize>,
+    max_batch_tokens: Option<usize>,
+    /// Not part of `/info`, found by embedding a probe
+    #[serde(skip)]
+    embed_length: Option<usize>,
+    /// The served model's tokenizer, fetched from the HuggingFace hub
+    #[serde(skip)]
+    tokenizer: Option<Tokenizer>,
+}
+
+impl TeiEmbeddingClient {
+    pub fn new(api_url: Url, config: EmbeddingConfig) -> Result<Self> {
+        let client = config.http_client()?;
+
+        Ok(Self {
+            client,
+            api_url,
+            config,
+            info: OnceCell::new(),
+        })
+    }
+
+    async fn info(&self) -> Result<&TeiInfo> {
+        self.info.get_or_try_init(|| self.fetch_info()).await
+    }
+
+    async fn fetch_info(&self) -> Result<TeiInfo> {
+        let response = self.client.get(self.api_url.join("info")?).send().await?;
+
+        if !response.status().is_success() {
+            return Err(api_error(response).await);
+        }
+
+        let mut info: TeiInfo = response.json().await?;
+
+        // `/info` doesn't report the dimension, so embed something to find it out
+        let embeddings = self.send(&["test".to_string()]).await?;
+        info.embed_length = embeddings.first().map(|embedding| embedding.len());
+        info.tokenizer = Some(Tokenizer::for_huggingface(&info.model_id));
+
+        Ok(info)
+    }
+
+    /// The model the server was started with
+    pub async fn model_id(&self) -> Result<String> {
+        Ok(self.info().await?.model_id.clone())
+    }
+
+    async fn embed_batch(&self, batch: Vec<String>) -> Result<Vec<Embedding>> {
+        self.config
+            .retry
+            .run(
+                "TEI embedding request",
+                || self.send(&batch),
+                classify_error,
+            )
+            .await
+            .map_err(|(e, _)| e)
+    }
+
+    async fn send(&self, batch: &[String]) -> Result<Vec<Embedding>> {
+        // The probe embedding `fetch_info` sends goes out before the tokenizer is known
+        let tokenizer = self.info.get().and_then(|info| info.tokenizer.as_ref());
+        self.config.throttle(tokenizer.unwrap_or(&Tokenizer::Approximate), batch).await;
+
+        let response = self
+            .client
+            .post(self.api_url.join("embed")?)
+            .json(&TeiEmbedRequest {
+                inputs: batch,
+                truncate: true,
+            })
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(api_error(response).await);
+        }
+
+        Ok(response.json().await?)
+    }
+}
+
+#[async_trait]
+impl EmbeddingClient for TeiEmbeddingClient {
+    async fn embed(&self, chunks: &[CodeChunk]) -> Result<Vec<Embedding>> {
+        let info = self.info().await?;
+        let texts: Vec<String> = chunks.iter().map(|chunk| chunk.content.clone()).collect();
+
+        // Keep each request under the server's token budget so it isn't rejected
+        let tokenizer = info.tokenizer.as_ref().unwrap_or(&Tokenizer::Approximate);
+        let batches = tokenizer.batches(
+            &texts,
+            String::as_str,
+            info.max_client_batch_size.unwrap_or(DEFAULT_BATCH_SIZE),
+            info.max_batch_tokens.unwrap_or(usize::MAX),
+        );
+
+        // `buffered` yields results in submission order, keeping embeddings aligned with chunks
+        let batches: Vec<Vec<Embedding>> =
+            stream::iter(batches.into_iter().map(<[String]>::to_vec))
+                .map(|batch| self.embed_batch(batch))
+                .buffered(self.config.concurrency())
+                .try_collect()
+                .await?;
+
+        Ok(batches.into_iter().flatten().collect())
+    }
+
+    async fn context_length(&self) -> Result<usize> {
+        Ok(self.info().await?.max_input_length.unwrap_or(DEFAULT_MAX_INPUT_LENGTH))
+    }
+
+    async fn embed_length(&self) -> Result<usize> {
+        self.info()
+            .await?
+            .embed_length
+            .ok_or(Missing(String::from("Embedding length not found")))
+    }
+
+    /// Estimates until `/info` has been fetched
+    fn tokenizer(&self) -> Tokenizer {
+        self.info
+            .get()
+            .and_then(|info| info.tokenizer.clone())
+            .unwrap_or(Tokenizer::Approximate)
+    }
+}
@@ -0,0 +1,236 @@
+use std::{fmt, sync::Arc};
+
+use hf_hub::api::sync::Api;
+use tiktoken_rs::CoreBPE;
+use tracing::{debug, warn};
+
+use crate::{chunking::CodeChunk, prelude::*};
+
+// Rough average for code when the model's tokenizer isn't available
+const BYTES_PER_TOKEN: usize = 4;
+
+#[derive(Clone)]
+pub enum Tokenizer {
+    Bpe(Arc<CoreBPE>),
+    /// A model's own `tokenizer.json` from the HuggingFace hub
+    HuggingFace(Arc<tokenizers::Tokenizer>),
+    Approximate,
+}
+
+impl fmt::Debug for Tokenizer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Bpe(_) => f.write_str("Bpe"),
+            Self::HuggingFace(_) => f.write_str("HuggingFace"),
+            Self::Approximate => f.write_str("Approximate"),
+        }
+    }
+}
+
+impl Tokenizer {
+    /// Use tiktoken's encoding for the model, falling back to an estimate for unknown models
+    pub fn for_openai(model: &str) -> Self {
+        tiktoken_rs::get_bpe_from_model(model)
+            .map(|bpe| Self::Bpe(Arc::new(bpe)))
+            .unwrap_or(Self::Approximate)
+    }
+
+    /// Use the `tokenizer.json` of `repo` on the HuggingFace hub, falling back to an estimate
+    /// when it can't be fetched. Ollama model names are mapped to the repos they're built from
+    pub fn for_huggingface(repo: &str) -> Self {
+        let repo = hub_repo(repo);
+
+        match load_hub_tokenizer(repo) {
+            Ok(tokenizer) => {
+                debug!("Using the {repo} tokenizer");
+                Self::HuggingFace(Arc::new(tokenizer))
+            },
+            Err(e) => {
+                warn!("Unable to load the {repo} tokenizer, estimating token counts: {e}");
+                Self::Approximate
+            },
+        }
+    }
+
+    pub fn count(&self, text: &str) -> usize {
+        match self {
+            Self::Bpe(bpe) => bpe.encode_ordinary(text).len(),
+            Self::HuggingFace(tokenizer) => match tokenizer.encode(text, false) {
+                Ok(encoding) => encoding.len(),
+                Err(_) => Self::Approximate.count(text),
+            },
+            Self::Approximate => text.len().div_ceil(BYTES_PER_TOKEN),
+        }
+    }
+
+    /// Cut `text` down to at most `max_tokens` tokens
+    pub fn truncate(&self, text: &str, max_tokens: usize) -> String {
+        if let Self::Bpe(bpe) = self {
+            let tokens = bpe.encode_ordinary(text);
+
+            if tokens.len() <= max_tokens {
+                return text.to_string();
+            }
+
+            // Decoding can fail when the cut lands inside a multi-byte character
+            if let Ok(truncated) = bpe.decode(tokens[..max_tokens].to_vec()) {
+                return truncated;
+            }
+        }
+
+        if let Self::HuggingFace(tokenizer) = self {
+            let Ok(encoding) = tokenizer.encode(text, false) else {
+                return Self::Approximate.truncate(text, max_tokens);
+            };
+
+            if encoding.len() <= max_tokens {
+                return text.to_string();
+            }
+
+            // Offsets are in bytes, so the text up to the last kept token is an exact prefix
+            let end = encoding.get_offsets()[..max_tokens]
+                .iter()
+                .map(|&(_, end)| end)
+                .max()
+                .unwrap_or_default();
+
+            if text.is_char_boundary(end) {
+                return text[..end].to_string();
+            }
+        }
+
+        let mut end = std::cmp::min(max_tokens * BYTES_PER_TOKEN, text.len());
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+
+        text[..end].to_string()
+    }
+
+    /// Cut `text` into consecutive pieces of at most `max_tokens` tokens each
+    pub fn split(&self, text: &str, max_tokens: usize) -> Vec<String> {
+        let mut pieces = Vec::new();
+        let mut rest = text;
+
+        while !rest.is_empty() {
+            let mut piece = self.truncate(rest, max_tokens.max(1));
+
+            // BPE decoding isn't guaranteed to give back an exact prefix
+            if piece.is_empty() || !rest.starts_with(&piece) {
+                piece = Self::Approximate.truncate(rest, max_tokens.max(1));
+            }
+
+            rest = &rest[piece.len()..];
+            pieces.push(piece);
+        }
+
+        pieces
+    }
+
+    /// Split `items` into consecutive batches of at most `max_items` entries and `max_tokens`
+    /// tokens. An item larger than `max_tokens` gets a batch to itself
+    pub fn batches<'a, T>(
+        &self,
+        items: &'a [T],
+        text: impl Fn(&T) -> &str,
+        max_items: usize,
+        max_tokens: usize,
+    ) -> Vec<&'a [T]> {
+        let mut batches = Vec::new();
+        let mut start = 0;
+        let mut tokens = 0;
+
+        for (i, item) in items.iter().enumerate() {
+            let item_tokens = self.count(text(item)).min(max_tokens);
+
+            if i > start && (i - start >= max_items || tokens + item_tokens > max_tokens) {
+                batches.push(&items[start..i]);
+                start = i;
+                tokens = 0;
+            }
+
+            tokens += item_tokens;
+        }
+
+        if start < items.len() {
+            batches.push(&items[start..]);
+        }
+
+        batches
+    }
+
+    /// Content of `chunk`, truncated with a warning if it doesn't fit the context window
+    pub fn fit(&self, chunk: &CodeChunk, max_tokens: usize) -> String {
+        let tokens = self.count(&chunk.content);
+
+        if tokens <= max_tokens {
+            return chunk.content.clone();
+        }
+
+        warn!(
+            "Truncating chunk {}:{} from {tokens} to {max_tokens} tokens to fit the context window",
+            chunk.path.display(),
+            chunk.start_line
+        );
+        self.truncate(&chunk.content, max_tokens)
+    }
+}
+
+/// HuggingFace repo of `model`, for Ollama's names of models published there
+fn hub_repo(model: &str) -> &str {
+    // Drop Ollama's `:latest` style tag
+    let name = model.split(':').next().unwrap_or(model);
+
+    match name {
+        "nomic-embed-text" => "nomic-ai/nomic-embed-text-v1.5",
+        "mxbai-embed-large" => "mixedbread-ai/mxbai-embed-large-v1",
+        "all-minilm" => "sentence-transformers/all-MiniLM-L6-v2",
+        "bge-m3" => "BAAI/bge-m3",
+        "bge-large" => "BAAI/bge-large-en-v1.5",
+        "snowflake-arctic-embed" => "Snowflake/snowflake-arctic-embed-l",
+        "snowflake-arctic-embed2" => "Snowflake/snowflake-arctic-embed-l-v2.0",
+        "granite-embedding" => "ibm-granite/granite-embedding-278m-multilingual",
+        _ => name,
+    }
+}
+
+/// Fetch `repo`'s `tokenizer.json` into the hub cache and load it without the truncation and
+/// padding it was saved with, so counts are of the whole text
+fn load_hub_tokenizer(repo: &str) -> Result<tokenizers::Tokenizer> {
+    if !repo.contains('/') {
+        return Err(InvalidArgument(f!("{repo} isn't a HuggingFace repo")));
+    }
+
+    let tokenizer_error =
+        |e: Box<dyn std::error::Error + Send + Sync>| Error::Embedding(e.to_string());
+    let path = Api::new()
+        .and_then(|api| api.model(repo.to_string()).get("tokenizer.json"))
+        .map_err(|e| Error::Embedding(e.to_string()))?;
+    let mut tokenizer = tokenizers::Tokenizer::from_file(path).map_err(tokenizer_error)?;
+    tokenizer.with_truncation(None).map_err(tokenizer_error)?;
+    tokenizer.with_padding(None);
+
+    Ok(tokenizer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ollama_names_map_to_hub_repos() {
+        assert_eq!(
+            hub_repo("nomic-embed-text:latest"),
+            "nomic-ai/nomic-embed-text-v1.5"
+        );
+        assert_eq!(hub_repo("BAAI/bge-small-en-v1.5"), "BAAI/bge-small-en-v1.5");
+    }
+
+    #[test]
+    fn unknown_models_are_estimated() {
+        let tokenizer = Tokenizer::for_huggingface("some-local-model");
+
+        assert!(matches!(tokenizer, Tokenizer::Approximate));
+        assert_eq!(tokenizer.count("12345678"), 2);
+    }
+}
@@ -0,0 +1,150 @@
+use async_trait::async_trait;
+use futures::{StreamExt, TryStreamExt, stream};
+use reqwest::Client as ReqwestClient;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    Embedding,
+    client::{EmbeddingClient, EmbeddingConfig},
+    retry::{api_error, classify_error},
+    tokenizer::Tokenizer,
+};
+use crate::{chunking::CodeChunk, prelude::*};
+
+const VOYAGE_API_URL: &str = "https://api.voyageai.com/v1/embeddings";
+// Per-request limits from Voyage's API reference
+const MAX_BATCH_INPUTS: usize = 128;
+const MAX_BATCH_TOKENS: usize = 120_000;
+
+#[derive(Debug, Clone)]
+pub struct VoyageEmbeddingClient {
+    client: ReqwestClient,
+    api_key: String,
+    model: String,
+    config: EmbeddingConfig,
+    tokenizer: Tokenizer,
+}
+
+#[derive(Serialize)]
+struct VoyageEmbeddingRequest<'a> {
+    model: &'a str,
+    input: &'a [String],
+    input_type: &'static str,
+}
+
+#[derive(Deserialize)]
+struct VoyageEmbeddingResponse {
+    data: Vec<VoyageEmbeddingData>,
+}
+
+#[derive(Deserialize)]
+struct VoyageEmbeddingData {
+    embedding: Vec<f32>,
+    index: usize,
+}
+
+impl VoyageEmbeddingClient {
+    pub fn new(api_key: &str, model: &str, config: EmbeddingConfig) -> Result<Self> {
+        let client = config.http_client()?;
+
+        Ok(Self {
+            client,
+            api_key: api_key.to_string(),
+            model: model.to_string(),
+            config,
+            tokenizer: Tokenizer::for_huggingface(&f!("voyageai/{model}")),
+        })
+    }
+
+    fn max_tokens(&self) -> usize {
+        match self.model.as_str() {
+            "voyage-code-2" => 16_000,
+            _ => 32_000,
+        }
+    }
+
+    async fn embed_batch(&self, batch: Vec<String>) -> Result<Vec<Embedding>> {
+        self.config
+            .retry
+            .run(
+                "Voyage embedding request",
+                || self.send(&batch),
+                classify_error,
+            )
+            .await
+            .map_err(|(e, _)| e)
+    }
+
+    async fn send(&self, batch: &[String]) -> Result<Vec<Embedding>> {
+        self.config.throttle(&self.tokenizer, batch).await;
+
+        let response = self
+            .client
+            .post(VOYAGE_API_URL)
+            .bearer_auth(&self.api_key)
+            .json(&VoyageEmbeddingRequest {
+                model: &self.model,
+                input: batch,
+                input_type: "document",
+            })
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(api_error(response).await);
+        }
+
+        let mut embedding_response: VoyageEmbeddingResponse = response.json().await?;
+        embedding_response.data.sort_by_key(|data| data.index);
+
+        Ok(embedding_response.data.into_iter().map(|data| data.embedding).collect())
+    }
+}
+
+#[async_trait]
+impl EmbeddingClient for VoyageEmbeddingClient {
+    async fn embed(&self, chunks: &[CodeChunk]) -> Result<Vec<Embedding>> {
+        let texts: Vec<String> = chunks
+            .iter()
+            .map(|chunk| self.tokenizer.fit(chunk, self.max_tokens()))
+            .collect();
+
+        let batches =
+            self.tokenizer
+                .batches(&texts, String::as_str, MAX_BATCH_INPUTS, MAX_BATCH_TOKENS);
+
+        // `buffered` yields results in submission order, keeping embeddings aligned with chunks
+        let batches: Vec<Vec<Embedding>> =
+            stream::iter(batches.into_iter().map(<[String]>::to_vec))
+                .map(|batch| self.embed_batch(batch))
+                .buffered(self.config.concurrency())
+                .try_collect()
+                .await?;
+
+        Ok(batches.into_iter().flatten().collect())
+    }
+
+    async fn context_length(&self) -> Result<usize> {
+        Ok(self.max_tokens())
+    }
+
+    async fn embed_length(&self) -> Result<usize> {
+        match self.model.as_str() {
+            "voyage-code-3" | "voyage-3" | "voyage-3-large" => Ok(1024),
+            "voyage-3-lite" => Ok(512),
+            "voyage-code-2" => Ok(1536),
+            _ => {
+                let embeddings = self.send(&["test".to_string()]).await?;
+
+                embeddings
+                    .first()
+                    .map(|embedding| embedding.len())
+                    .ok_or(Error::Embedding("Empty embedding response".to_string()))
+            },
+        }
+    }
+
+    fn tokenizer(&self) -> Tokenizer {
+        self.tokenizer.clone()
+    }
+}
@@ -17,24 +17,67 @@ pub enum Error {
     #[error("Scan failed")]
     ScanFailed,
 
+    #[error("{errors} files failed during the scan, more than the {limit} allowed")]
+    TooManyErrors { errors: usize, limit: usize },
+
+    #[error(
+        "{collection} is being scanned by process {holder}. If no scan is running, {} was left \
+         by one that crashed, scan again with --force",
+        .path.display()
+    )]
+    ScanLocked {
+        collection: String,
+        holder: String,
+        path: PathBuf,
+    },
+
     #[error("Failed to read file: {0}")]
     FileRead(#[from] std::io::Error),
 
     #[error("Failed to generate embeddings: {0}")]
     Embedding(String),
 
+    #[error("Embedding API responded with {status}: {message}")]
+    EmbeddingApi {
+        status: u16,
+        retry_after: Option<std::time::Duration>,
+        message: String,
+    },
+
+    #[error("Estimated cost ${estimated:.4} is over the --max-cost limit of ${limit:.4}")]
+    CostLimitExceeded { estimated: f64, limit: f64 },
+
     #[error(transparent)]
     Storage(#[from] QdrantError),
 
+    #[error("{operation} failed after {attempts} attempts: {source}")]
+    StorageRetriesExhausted {
+        operation: String,
+        attempts: usize,
+        source: QdrantError,
+    },
+
     #[error(transparent)]
     Json(#[from] serde_json::Error),
 
+    #[error("Embedding cache error: {0}")]
+    Cache(#[from] sled::Error),
+
     #[error("Tree-sitter error: {0}")]
     TreeSitter(#[from] tree_sitter::LanguageError),
 
     #[error(transparent)]
     Request(#[from] reqwest::Error),
 
+    #[error(
+        "{provider} failed its health check: {source}. Check that the server is reachable, \
+             the API key is valid and the model exists"
+    )]
+    HealthCheck {
+        provider: String,
+        source: Box<Error>,
+    },
+
     #[error("Missing {0}")]
     Missing(String),
 
@@ -44,6 +87,31 @@ pub enum Error {
     #[error(transparent)]
     UrlParse(#[from] url::ParseError),
 
+    #[error("Watch error: {0}")]
+    Watch(#[from] notify::Error),
+
+    #[error("Git error: {0}")]
+    Git(#[from] git2::Error),
+
+    #[error("Invalid file pattern: {0}")]
+    Pattern(#[from] ignore::Error),
+
+    #[error("Copied {copied} of the {expected} points of {collection}, which was kept")]
+    IncompleteCopy {
+        collection: String,
+        copied: u64,
+        expected: u64,
+    },
+
     #[error("Unable to serialize payload: {0}")]
     Payload(String),
+
+    #[error("Parquet error: {0}")]
+    Parquet(#[from] parquet::errors::ParquetError),
+
+    #[error("Arrow error: {0}")]
+    Arrow(#[from] arrow_schema::ArrowError),
+
+    #[error("Failed to read archive: {0}")]
+    Archive(#[from] zip::result::ZipError),
 }
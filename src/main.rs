@@ -27,6 +27,8 @@ async fn main() -> Result<()> {
     tracing_subscriber::fmt()
         .with_max_level(log_level)
         .with_timer(tracing_subscriber::fmt::time::time())
+        // Keep stdout free for machine-readable output like `scan --json`
+        .with_writer(std::io::stderr)
         .init();
 
     let args = Args::parse();
@@ -34,5 +36,11 @@ async fn main() -> Result<()> {
     match args.command {
         Commands::Scan(cmd) => cmd.execute().await,
         Commands::Query(cmd) => cmd.execute().await,
+        Commands::Export(cmd) => cmd.execute().await,
+        Commands::Import(cmd) => cmd.execute().await,
+        Commands::Chunks(cmd) => cmd.execute().await,
+        Commands::Watch(cmd) => cmd.execute().await,
+        Commands::Daemon(cmd) => cmd.execute().await,
+        Commands::Collections(cmd) => cmd.execute().await,
     }
 }
@@ -0,0 +1,56 @@
+/// How much of a file is looked at to tell whether it's binary
+const SNIFF_BYTES: usize = 8 * 1024;
+
+/// Text rarely has more control characters than this share of its bytes
+const MAX_CONTROL_RATIO: f64 = 0.3;
+
+/// Bits of entropy per byte above which content is compressed, encrypted or otherwise not text
+const MAX_TEXT_ENTROPY: f64 = 7.5;
+
+/// Why the file looks binary judging by its first bytes, or `None` when it looks like text
+pub fn binary_reason(content: &[u8]) -> Option<&'static str> {
+    let head = &content[..SNIFF_BYTES.min(content.len())];
+
+    if head.is_empty() {
+        return None;
+    }
+
+    // No text encoding we read puts null bytes in text
+    if head.contains(&0) {
+        return Some("binary, contains null bytes");
+    }
+
+    let control = head
+        .iter()
+        .filter(|&&byte| (byte < 0x20 && !b"\t\n\r\x0c\x1b".contains(&byte)) || byte == 0x7f)
+        .count();
+
+    if control as f64 / head.len() as f64 > MAX_CONTROL_RATIO {
+        return Some("binary, mostly control characters");
+    }
+
+    if entropy(head) > MAX_TEXT_ENTROPY {
+        return Some("binary, compressed or encrypted");
+    }
+
+    None
+}
+
+/// Shannon entropy of `bytes` in bits per byte
+fn entropy(bytes: &[u8]) -> f64 {
+    let mut counts = [0usize; 256];
+    for &byte in bytes {
+        counts[byte as usize] += 1;
+    }
+
+    let len = bytes.len() as f64;
+
+    counts
+        .iter()
+        .filter(|&&count| count > 0)
+        .map(|&count| {
+            let p = count as f64 / len;
+            -p * p.log2()
+        })
+        .sum()
+}
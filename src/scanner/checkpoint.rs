@@ -0,0 +1,89 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::prelude::*;
+
+/// A file whose chunks are all stored, as of the content it had when it was parsed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompletedFile {
+    pub path: PathBuf,
+    pub hash: String,
+}
+
+/// Files a scan has finished storing, appended one JSON line at a time so an interrupted scan
+/// can pick up where it stopped. Chunks that were embedded but not stored are sent again, and
+/// come back from the embedding cache
+pub struct Checkpoint {
+    path: PathBuf,
+    file: File,
+    completed: HashMap<PathBuf, String>,
+}
+
+impl Checkpoint {
+    /// Open the checkpoint at `path`, keeping the files it lists when `resume` is set and
+    /// starting over otherwise
+    pub fn open(path: &Path, resume: bool) -> Result<Self> {
+        let mut completed = HashMap::new();
+
+        if resume {
+            match File::open(path) {
+                Ok(file) => {
+                    for line in BufReader::new(file).lines() {
+                        // The last line may be cut short by the interruption
+                        match serde_json::from_str::<CompletedFile>(&line?) {
+                            Ok(file) => completed.insert(file.path, file.hash),
+                            Err(_) => continue,
+                        };
+                    }
+
+                    info!("Resuming scan, {} files already stored", completed.len());
+                },
+                Err(_) => warn!("No interrupted scan to resume, starting over"),
+            }
+        }
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        // Resuming appends, so the files stored before stay listed in case this scan is
+        // interrupted too
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        if !resume {
+            file.set_len(0)?;
+        }
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            file,
+            completed,
+        })
+    }
+
+    /// Files stored by the interrupted scan, with their hash at the time
+    pub fn take_completed(&mut self) -> HashMap<PathBuf, String> {
+        std::mem::take(&mut self.completed)
+    }
+
+    /// Record `files` as stored
+    pub fn record(&mut self, files: &[CompletedFile]) -> Result<()> {
+        for file in files {
+            writeln!(self.file, "{}", serde_json::to_string(file)?)?;
+        }
+
+        Ok(self.file.flush()?)
+    }
+
+    /// The scan finished, so there's nothing left to resume
+    pub fn finish(self) -> Result<()> {
+        drop(self.file);
+        Ok(fs::remove_file(&self.path)?)
+    }
+}
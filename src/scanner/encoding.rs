@@ -0,0 +1,36 @@
+use chardetng::EncodingDetector;
+use encoding_rs::{Encoding, UTF_8};
+
+/// Whether `bytes` start with a byte order mark, which also makes UTF-16 text full of null bytes
+pub fn has_bom(bytes: &[u8]) -> bool {
+    Encoding::for_bom(bytes).is_some()
+}
+
+/// Decode a source file to UTF-8, going by its byte order mark, then by whether it's valid UTF-8,
+/// then by guessing from its bytes. Returns the text and the name of the encoding when it wasn't
+/// UTF-8
+pub fn decode(bytes: Vec<u8>) -> (String, Option<&'static str>) {
+    let encoding = match Encoding::for_bom(&bytes) {
+        Some((encoding, _)) => encoding,
+        None => match String::from_utf8(bytes) {
+            Ok(text) => return (text, None),
+            Err(e) => return decode_guessed(e.into_bytes()),
+        },
+    };
+
+    let (text, _) = encoding.decode_with_bom_removal(&bytes);
+    (
+        text.into_owned(),
+        (encoding != UTF_8).then(|| encoding.name()),
+    )
+}
+
+/// Legacy sources like Latin-1 or Shift-JIS have no marker, so their encoding is guessed
+fn decode_guessed(bytes: Vec<u8>) -> (String, Option<&'static str>) {
+    let mut detector = EncodingDetector::new();
+    detector.feed(&bytes, true);
+    let encoding = detector.guess(None, true);
+
+    let (text, _, _) = encoding.decode(&bytes);
+    (text.into_owned(), Some(encoding.name()))
+}
@@ -0,0 +1,128 @@
+use std::{collections::HashMap, path::Path};
+
+use tracing::debug;
+use tree_sitter::Parser;
+
+use super::filters::ChunkFilters;
+use crate::{
+    chunking::{ChunkStrategy, CodeChunk, component_blocks, notebook_cells},
+    prelude::*,
+    utils::parsers::SupportedParsers,
+};
+
+/// Parses files and cuts them into chunks, without embedding or storing anything
+pub struct FileParser {
+    parser: Parser,
+    chunker: Box<dyn ChunkStrategy>,
+    filters: ChunkFilters,
+}
+
+impl FileParser {
+    pub fn new(chunker: Box<dyn ChunkStrategy>) -> Self {
+        Self {
+            parser: Parser::new(),
+            chunker,
+            filters: ChunkFilters::default(),
+        }
+    }
+
+    /// Run `filters` on the chunks of every file parsed
+    pub fn with_filters(mut self, filters: ChunkFilters) -> Self {
+        self.filters = filters;
+        self
+    }
+
+    pub fn parse_file(
+        &mut self,
+        path: &Path,
+        content: &str,
+        language: &SupportedParsers,
+    ) -> Result<Vec<CodeChunk>> {
+        let chunks = self.chunk_file(path, content, language)?;
+
+        Ok(self.filters.apply(chunks))
+    }
+
+    fn chunk_file(
+        &mut self,
+        path: &Path,
+        content: &str,
+        language: &SupportedParsers,
+    ) -> Result<Vec<CodeChunk>> {
+        if let SupportedParsers::Svelte | SupportedParsers::Jupyter = language {
+            return self.parse_component(path, content, language);
+        }
+
+        self.parser.set_language(&language.language())?;
+
+        let tree = self.parser.parse(content, None).ok_or(ParsingFailed(path.to_path_buf()))?;
+
+        let chunks = self.chunker.chunk(&tree, content, path, language);
+        debug!("Extracted {} chunks from {path:?}", chunks.len());
+        Ok(chunks)
+    }
+
+    /// Chunk each block of a single-file component, or each cell of a notebook, with its own
+    /// parser, under one chunk for the file itself
+    fn parse_component(
+        &mut self,
+        path: &Path,
+        content: &str,
+        language: &SupportedParsers,
+    ) -> Result<Vec<CodeChunk>> {
+        let blocks = match language {
+            SupportedParsers::Jupyter => notebook_cells(content)?,
+            _ => component_blocks(content),
+        };
+        let name = path.file_stem().unwrap_or_default().to_string_lossy();
+
+        let outline: Vec<String> = blocks
+            .iter()
+            .map(|block| f!("{} {} ({})", block.label(), block.role, block.language))
+            .collect();
+
+        let mut component = CodeChunk {
+            content: f!("{language} {name}\n{}", outline.join("\n")),
+            node_type: f!("component:{name}"),
+            start_line: 0,
+            end_line: content.lines().count().saturating_sub(1),
+            start_byte: 0,
+            end_byte: content.len(),
+            path: path.to_path_buf(),
+            language: language.to_string(),
+            symbol: Some(name.to_string()),
+            ..Default::default()
+        };
+        component.hash_content();
+
+        let component_id = component.id();
+        let mut chunks = vec![component];
+
+        for block in blocks {
+            let mut block_chunks = self.chunk_file(path, &block.source, &block.language)?;
+
+            // Every block has its own file summary, and notebook cells often define the same
+            // names, so tell the chunks apart by block
+            let label = block.label();
+            let mut renamed = HashMap::new();
+            for chunk in &mut block_chunks {
+                let old_id = chunk.id();
+                chunk.node_type = f!("{label}:{}", chunk.node_type);
+                chunk.cell = block.cell;
+                renamed.insert(old_id, chunk.id());
+            }
+
+            for chunk in &mut block_chunks {
+                chunk.parent_id = match chunk.parent_id {
+                    Some(id) => Some(renamed.get(&id).copied().unwrap_or(id)),
+                    None => Some(component_id),
+                };
+            }
+
+            chunks.extend(block_chunks);
+        }
+
+        debug!("Extracted {} chunks from component {path:?}", chunks.len());
+        Ok(chunks)
+    }
+}
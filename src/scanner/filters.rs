@@ -0,0 +1,149 @@
+use std::{fs, path::Path};
+
+use regex::Regex;
+use serde::Deserialize;
+use tracing::debug;
+
+use crate::{chunking::CodeChunk, prelude::*, utils::credentials::config_file};
+
+/// Runs on every chunk between chunking and embedding, to drop it, rewrite its content or tag it.
+/// Closures taking and returning a chunk are filters too
+pub trait ChunkFilter: Send + Sync {
+    /// The chunk to embed in place of `chunk`, `None` to leave it out
+    fn filter(&self, chunk: CodeChunk) -> Option<CodeChunk>;
+}
+
+impl<F> ChunkFilter for F
+where
+    F: Fn(CodeChunk) -> Option<CodeChunk> + Send + Sync,
+{
+    fn filter(&self, chunk: CodeChunk) -> Option<CodeChunk> {
+        self(chunk)
+    }
+}
+
+/// The parts of the config file filters are read from
+#[derive(Debug, Default, Deserialize)]
+struct ConfigFile {
+    #[serde(default)]
+    filters: Vec<FilterRule>,
+}
+
+/// A `[[filters]]` entry of the config file
+#[derive(Debug, Deserialize)]
+struct FilterRule {
+    /// Regex matched against the chunk's content
+    pattern: String,
+    /// Only chunks in these languages are filtered, every chunk when empty
+    #[serde(default)]
+    languages: Vec<String>,
+    #[serde(flatten)]
+    action: FilterAction,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "action", rename_all = "lowercase")]
+enum FilterAction {
+    /// Leave out chunks that match
+    Drop,
+    /// Replace every match, `$1` and `${name}` standing for its groups
+    Replace { replacement: String },
+    /// Tag chunks that match
+    Tag { tag: String },
+}
+
+/// A filter from the config file
+struct PatternFilter {
+    pattern: Regex,
+    languages: Vec<String>,
+    action: FilterAction,
+}
+
+impl ChunkFilter for PatternFilter {
+    fn filter(&self, mut chunk: CodeChunk) -> Option<CodeChunk> {
+        if !self.languages.is_empty() && !self.languages.contains(&chunk.language) {
+            return Some(chunk);
+        }
+
+        match &self.action {
+            FilterAction::Drop if self.pattern.is_match(&chunk.content) => return None,
+            FilterAction::Replace { replacement } => {
+                chunk.content = self.pattern.replace_all(&chunk.content, replacement).into_owned();
+            },
+            FilterAction::Tag { tag } if self.pattern.is_match(&chunk.content) => {
+                chunk.tags.push(tag.clone());
+            },
+            _ => {},
+        }
+
+        Some(chunk)
+    }
+}
+
+/// Filters run on each file's chunks in order, a chunk dropped by one never reaching the next
+#[derive(Default)]
+pub struct ChunkFilters {
+    filters: Vec<Box<dyn ChunkFilter>>,
+}
+
+impl ChunkFilters {
+    /// Load the `[[filters]]` of `path`, or of the user config file when `path` is `None`. A
+    /// missing default config file means no filters
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        let Some(path) = config_file(path) else {
+            return Ok(Self::default());
+        };
+
+        let content = fs::read_to_string(&path)?;
+        let config: ConfigFile = toml::from_str(&content)
+            .map_err(|e| InvalidArgument(f!("Invalid config file {}: {e}", path.display())))?;
+
+        let mut filters = Self::default();
+        for rule in config.filters {
+            let pattern = Regex::new(&rule.pattern).map_err(|e| {
+                InvalidArgument(f!("Invalid filter pattern {:?}: {e}", rule.pattern))
+            })?;
+
+            filters = filters.with(PatternFilter {
+                pattern,
+                languages: rule.languages,
+                action: rule.action,
+            });
+        }
+
+        debug!(
+            "Loaded {} chunk filters from {}",
+            filters.filters.len(),
+            path.display()
+        );
+
+        Ok(filters)
+    }
+
+    /// Run `filter` after the ones already added
+    pub fn with(mut self, filter: impl ChunkFilter + 'static) -> Self {
+        self.filters.push(Box::new(filter));
+        self
+    }
+
+    /// Run every filter on `chunks`, hashing rewritten content again
+    pub fn apply(&self, chunks: Vec<CodeChunk>) -> Vec<CodeChunk> {
+        if self.filters.is_empty() {
+            return chunks;
+        }
+
+        chunks
+            .into_iter()
+            .filter_map(|chunk| {
+                let mut chunk =
+                    self.filters.iter().try_fold(chunk, |chunk, filter| filter.filter(chunk))?;
+
+                chunk.hash_content();
+                chunk.tags.sort();
+                chunk.tags.dedup();
+
+                Some(chunk)
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,69 @@
+use std::path::Path;
+
+/// Lockfiles, which are generated and say nothing about the code
+const LOCKFILES: &[&str] = &[
+    "package-lock.json",
+    "npm-shrinkwrap.json",
+    "pnpm-lock.yaml",
+    "composer.lock",
+    "Cargo.lock",
+    "poetry.lock",
+    "Gemfile.lock",
+    "go.sum",
+];
+
+/// File name endings of minified bundles and code generator output
+const GENERATED_SUFFIXES: &[&str] = &[
+    ".min.js",
+    ".min.css",
+    ".pb.go",
+    "_pb2.py",
+    "_pb2_grpc.py",
+    "_pb2.pyi",
+    ".pb.ts",
+    "_pb.js",
+    "_pb.d.ts",
+    ".g.dart",
+    ".freezed.dart",
+    ".designer.cs",
+];
+
+/// Markers generators leave at the top of their output
+const GENERATED_MARKERS: &[&str] =
+    &["@generated", "DO NOT EDIT", "Code generated", "auto-generated", "autogenerated"];
+
+/// How far into a file generator markers are looked for
+const MARKER_SEARCH_BYTES: usize = 1024;
+
+/// Lines longer than this only come out of minifiers and generators
+const MAX_LINE_LENGTH: usize = 1000;
+
+/// Why the file looks generated or minified, or `None` when it looks hand written
+pub fn generated_reason(path: &Path, content: &str) -> Option<&'static str> {
+    let name = path.file_name()?.to_string_lossy();
+
+    if LOCKFILES.contains(&&*name) {
+        return Some("lockfile");
+    }
+
+    if GENERATED_SUFFIXES.iter().any(|suffix| name.ends_with(suffix))
+        || name.contains(".generated.")
+    {
+        return Some("generated file name");
+    }
+
+    let mut head_end = MARKER_SEARCH_BYTES.min(content.len());
+    while !content.is_char_boundary(head_end) {
+        head_end -= 1;
+    }
+
+    if GENERATED_MARKERS.iter().any(|marker| content[..head_end].contains(marker)) {
+        return Some("generated code marker");
+    }
+
+    if content.lines().any(|line| line.len() > MAX_LINE_LENGTH) {
+        return Some("minified");
+    }
+
+    None
+}
@@ -1,7 +1,27 @@
+mod binary;
+mod checkpoint;
+mod encoding;
+mod file_parser;
+mod filters;
+mod generated;
+mod packages;
+mod progress;
 mod results;
 #[allow(clippy::module_inception)]
 mod scanner;
 
+pub use checkpoint::Checkpoint;
+pub use file_parser::FileParser;
 #[allow(unused_imports)]
-pub use results::ScanResults;
-pub use scanner::{CodebaseScanner, ScannerConfig};
+pub use filters::{ChunkFilter, ChunkFilters};
+#[allow(unused_imports)]
+pub use progress::ProgressEvent;
+#[allow(unused_imports)]
+pub use results::{
+    ChunkedFile, DryRunResults, ErrorStage, FailedChunk, FileError, PlannedFile, ScanResults,
+    SkippedFile, StageTimings,
+};
+pub use scanner::{
+    CodebaseScanner, DEFAULT_MAX_FILE_SIZE, DEFAULT_SKIP_DIRS, ScannerConfig, chunk_codebase,
+    dry_run,
+};
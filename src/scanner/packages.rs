@@ -0,0 +1,71 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Reads the package name out of a manifest's content
+type NameReader = fn(&str) -> Option<String>;
+
+/// Manifests naming the package a directory holds, with how to read the name out of each
+const MANIFESTS: &[(&str, NameReader)] = &[
+    ("Cargo.toml", cargo_package),
+    ("package.json", npm_package),
+    ("go.mod", go_module),
+];
+
+/// Finds the crate, npm package or Go module a file belongs to in a monorepo: the one named by
+/// the nearest manifest above it, up to the repository root. Cargo workspace roots without a
+/// `[package]` of their own are looked past, npm, yarn and pnpm workspaces need nothing special
+#[derive(Default)]
+pub struct Packages {
+    /// Package owning the files directly in each directory looked at
+    dirs: HashMap<PathBuf, Option<String>>,
+}
+
+impl Packages {
+    /// Package owning the file at `path`, if any
+    pub fn package_of(&mut self, path: &Path) -> Option<String> {
+        path.parent().and_then(|dir| self.package_in(dir))
+    }
+
+    fn package_in(&mut self, dir: &Path) -> Option<String> {
+        if let Some(package) = self.dirs.get(dir) {
+            return package.clone();
+        }
+
+        let package = match manifest_package(dir) {
+            Some(package) => Some(package),
+            // Packages don't reach above the repository
+            None if dir.join(".git").exists() => None,
+            None => dir.parent().and_then(|parent| self.package_in(parent)),
+        };
+
+        self.dirs.insert(dir.to_path_buf(), package.clone());
+        package
+    }
+}
+
+/// Name of the package whose manifest is in `dir`
+fn manifest_package(dir: &Path) -> Option<String> {
+    MANIFESTS.iter().find_map(|(manifest, name)| {
+        fs::read_to_string(dir.join(manifest)).ok().and_then(|text| name(&text))
+    })
+}
+
+fn cargo_package(manifest: &str) -> Option<String> {
+    let manifest: toml::Table = toml::from_str(manifest).ok()?;
+    manifest.get("package")?.get("name")?.as_str().map(str::to_string)
+}
+
+fn npm_package(manifest: &str) -> Option<String> {
+    let manifest: serde_json::Value = serde_json::from_str(manifest).ok()?;
+    manifest.get("name")?.as_str().map(str::to_string)
+}
+
+fn go_module(manifest: &str) -> Option<String> {
+    manifest
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("module "))
+        .map(|module| module.trim().trim_matches('"').to_string())
+}
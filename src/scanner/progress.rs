@@ -0,0 +1,110 @@
+use std::{path::PathBuf, time::Instant};
+
+use serde::Serialize;
+use tokio::sync::mpsc::UnboundedSender;
+
+/// Updates from each stage of a scan, rendered by the CLI as progress bars or NDJSON
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum ProgressEvent {
+    Parsed {
+        files: usize,
+        chunks: usize,
+        current_file: PathBuf,
+    },
+    ParseFinished {
+        files: usize,
+        files_unchanged: usize,
+        chunks: usize,
+        elapsed_seconds: f64,
+    },
+    /// Chunks arrive while the codebase is still being parsed, so `total` grows with them
+    EmbedStarted {
+        total: usize,
+    },
+    Embedded {
+        done: usize,
+        failed: usize,
+        total: usize,
+        chunks_per_second: f64,
+        eta_seconds: Option<f64>,
+        current_file: PathBuf,
+    },
+    EmbedFinished {
+        done: usize,
+        failed: usize,
+        elapsed_seconds: f64,
+    },
+    Stored {
+        stored: usize,
+    },
+    /// Everything is stored and stale points are removed
+    ScanFinished {
+        stored: usize,
+        elapsed_seconds: f64,
+    },
+}
+
+/// Turns stage counts into events, dropping them if nobody is listening
+pub(super) struct ProgressTracker {
+    sender: Option<UnboundedSender<ProgressEvent>>,
+    started: Instant,
+    total: usize,
+}
+
+impl ProgressTracker {
+    pub fn new(sender: Option<UnboundedSender<ProgressEvent>>) -> Self {
+        Self {
+            sender,
+            started: Instant::now(),
+            total: 0,
+        }
+    }
+
+    /// Count chunks that came in from parsing, starting the clock on the first ones
+    pub fn add_total(&mut self, chunks: usize) {
+        if self.total == 0 {
+            self.started = Instant::now();
+            self.send(ProgressEvent::EmbedStarted { total: chunks });
+        }
+
+        self.total += chunks;
+    }
+
+    pub fn send(&self, event: ProgressEvent) {
+        if let Some(sender) = &self.sender {
+            // The receiver going away only means nobody wants progress anymore
+            let _ = sender.send(event);
+        }
+    }
+
+    pub fn update(&self, done: usize, failed: usize, current_file: PathBuf) {
+        let elapsed = self.started.elapsed().as_secs_f64();
+        let finished = done + failed;
+        let chunks_per_second = if elapsed > 0.0 {
+            finished as f64 / elapsed
+        } else {
+            0.0
+        };
+
+        let eta_seconds = (chunks_per_second > 0.0)
+            .then(|| self.total.saturating_sub(finished) as f64 / chunks_per_second);
+
+        self.send(ProgressEvent::Embedded {
+            done,
+            failed,
+            total: self.total,
+            chunks_per_second,
+            eta_seconds,
+            current_file,
+        });
+    }
+
+    pub fn finish(&self, done: usize, failed: usize) {
+        self.send(ProgressEvent::EmbedFinished {
+            done,
+            failed,
+            elapsed_seconds: self.started.elapsed().as_secs_f64(),
+        });
+    }
+}
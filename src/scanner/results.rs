@@ -1,4 +1,117 @@
+use std::{path::PathBuf, time::Duration};
+
+use serde::Serialize;
+use strum::Display;
+
+use crate::chunking::CodeChunk;
+
 pub struct ScanResults {
     pub chunks_processed: usize,
+    /// Files skipped because they're stored as they are
+    pub files_unchanged: usize,
+    /// Files stored under a path they moved away from, whose points were pointed at the new one
+    pub files_moved: usize,
     pub embeddings_generated: usize,
+    pub failed_chunks: Vec<FailedChunk>,
+    /// Files left out because of what they are, e.g. too large
+    pub skipped_files: Vec<SkippedFile>,
+    /// Files that couldn't be walked, read or parsed
+    pub file_errors: Vec<FileError>,
+    pub timings: StageTimings,
+}
+
+impl ScanResults {
+    /// Every file that failed, with the chunks that failed to embed listed by their file
+    pub fn errors(&self) -> Vec<FileError> {
+        let failed = self.failed_chunks.iter().map(|failed| FileError {
+            path: failed.path.clone(),
+            stage: ErrorStage::Embed,
+            reason: format!(
+                "lines {}-{}: {}",
+                failed.start_line, failed.end_line, failed.reason
+            ),
+        });
+
+        self.file_errors.iter().cloned().chain(failed).collect()
+    }
+}
+
+/// What a scan would index, found by walking and chunking without embedding anything
+#[derive(Debug, Default, Serialize)]
+pub struct DryRunResults {
+    pub files: Vec<PlannedFile>,
+    pub skipped_files: Vec<SkippedFile>,
+    pub file_errors: Vec<FileError>,
+}
+
+/// A file's chunks, before they're embedded
+#[derive(Debug)]
+pub struct ChunkedFile {
+    pub path: PathBuf,
+    pub language: String,
+    /// Size of the file's text, after transcoding
+    pub bytes: usize,
+    pub chunks: Vec<CodeChunk>,
+}
+
+/// A file a scan would index
+#[derive(Debug, Clone, Serialize)]
+pub struct PlannedFile {
+    pub path: PathBuf,
+    pub language: String,
+    /// Size of the file's text, after transcoding
+    pub bytes: usize,
+    pub chunks: usize,
+}
+
+/// Wall-clock time spent in each stage of a scan
+#[derive(Debug, Default)]
+pub struct StageTimings {
+    pub parse: Duration,
+    pub embed: Duration,
+    pub store: Duration,
+}
+
+/// A file the scan left out, and why
+#[derive(Debug, Clone, Serialize)]
+pub struct SkippedFile {
+    pub path: PathBuf,
+    pub reason: String,
+}
+
+/// A file the scan couldn't index, the stage it failed in and why
+#[derive(Debug, Clone, Serialize)]
+pub struct FileError {
+    pub path: PathBuf,
+    pub stage: ErrorStage,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Display)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum ErrorStage {
+    Walk,
+    Read,
+    Parse,
+    Embed,
+}
+
+/// A chunk that couldn't be embedded and was left out of the index
+pub struct FailedChunk {
+    pub path: PathBuf,
+    pub start_line: usize,
+    pub end_line: usize,
+    pub reason: String,
+}
+
+impl FailedChunk {
+    pub fn new(chunk: &CodeChunk, reason: &str) -> Self {
+        Self {
+            path: chunk.path.clone(),
+            start_line: chunk.start_line,
+            end_line: chunk.end_line,
+            reason: reason.to_string(),
+        }
+    }
 }
@@ -1,21 +1,114 @@
-use std::{fs, path::Path};
+use std::{
+    collections::{HashMap, HashSet},
+    ffi::OsString,
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
 
-use tracing::{info, warn};
-use tree_sitter::Parser;
-use walkdir::{DirEntry, WalkDir};
+use ignore::{DirEntry, WalkBuilder, overrides::OverrideBuilder};
+use tokio::sync::{
+    OwnedSemaphorePermit, Semaphore,
+    mpsc::{self, Receiver, Sender, UnboundedSender},
+};
+use tracing::{debug, info, warn};
 
-use super::results::ScanResults;
+use super::{
+    binary::binary_reason,
+    checkpoint::{Checkpoint, CompletedFile},
+    encoding::{decode, has_bom},
+    file_parser::FileParser,
+    filters::ChunkFilters,
+    generated::generated_reason,
+    packages::Packages,
+    progress::{ProgressEvent, ProgressTracker},
+    results::{
+        ChunkedFile, DryRunResults, ErrorStage, FailedChunk, FileError, PlannedFile, ScanResults,
+        SkippedFile, StageTimings,
+    },
+};
 use crate::{
-    chunking::{CodeChunk, extract_chunks},
-    embedding::EmbeddingClient,
+    chunking::{ChunkStrategy, CodeChunk, is_test_path, sha256_hex},
+    embedding::{CostEstimator, Embedding, EmbeddingClient, is_input_error},
     prelude::*,
-    storage::Storage,
-    utils::parsers::SupportedParsers,
+    storage::{IndexedFile, Storage},
+    utils::{common_ancestor, git::Blamer, parsers::SupportedParsers},
 };
 
+/// Chunks sent to the embedding client at a time, so one bad chunk only fails its own group
+const EMBED_GROUP_SIZE: usize = 256;
+
+/// Groups waiting between two pipeline stages. Parsing stops once this many are waiting to be
+/// embedded, so memory stays flat however large the codebase is
+const PIPELINE_DEPTH: usize = 4;
+
+/// Memory an embedding is counted as taking against `--max-memory`, a 1024-dimension vector
+const EMBEDDING_BYTES: usize = 1024 * size_of::<f32>();
+
+/// Files larger than this are skipped unless told otherwise, they're data or generated
+pub const DEFAULT_MAX_FILE_SIZE: u64 = 1024 * 1024;
+
+/// Directories skipped unless the skip list is changed: version control databases, which are
+/// still walked into with hidden files included, and build output and installed dependencies,
+/// which aren't always ignored
+pub const DEFAULT_SKIP_DIRS: &[&str] = &[
+    ".git",
+    ".hg",
+    ".svn",
+    "node_modules",
+    "target",
+    "dist",
+    "build",
+    ".venv",
+    "vendor",
+    "__pycache__",
+];
+
+/// Per-directory ignore file for paths that are tracked but shouldn't be indexed
+const IGNORE_FILE: &str = ".sherpaignore";
+
 pub struct ScannerConfig {
-    pub chunk_size_limit: Option<usize>,
-    pub overlap_percentage: Option<usize>,
+    pub chunker: Box<dyn ChunkStrategy>,
+    /// Estimate the cost of embedding with a paid provider before sending anything
+    pub cost_estimator: Option<CostEstimator>,
+    /// Scan generated code, minified bundles and lockfiles instead of skipping them
+    pub include_generated: bool,
+    /// Globs a file has to match to be scanned, every file when empty
+    pub include: Vec<String>,
+    /// Globs of files and directories to leave out
+    pub exclude: Vec<String>,
+    /// Skip files whose content hasn't changed since they were last stored
+    pub incremental: bool,
+    /// Only scan these files, keeping everything else as stored
+    pub changed: Option<HashSet<PathBuf>>,
+    /// Where stored files are recorded, to resume the scan if it's interrupted
+    pub checkpoint: Option<Checkpoint>,
+    /// Files larger than this many bytes are skipped without being read
+    pub max_file_size: u64,
+    /// Descend into symlinked directories, which are skipped otherwise
+    pub follow_symlinks: bool,
+    /// Scan hidden files and directories, the ones whose name starts with a dot
+    pub hidden: bool,
+    /// Names of directories never descended into, hidden or not. Kept as they are on disk, so
+    /// names that aren't valid UTF-8 can be skipped too
+    pub skip_dirs: Vec<OsString>,
+    /// Only scan these files, the ones git tracks
+    pub tracked: Option<HashSet<PathBuf>>,
+    /// Descend at most this many directories below each root
+    pub max_depth: Option<usize>,
+    /// Walk into git repositories nested below a root that aren't its submodules, e.g. unrelated
+    /// clones, which are skipped otherwise
+    pub nested_repos: bool,
+    /// Walk into submodules, which are skipped otherwise
+    pub submodules: bool,
+    /// Tag chunks with the author of most of their lines and when they last changed
+    pub blame: bool,
+    /// Bytes of chunks and embeddings the pipeline holds at once, parsing waits for stored groups
+    /// to make room
+    pub max_memory: Option<u64>,
+    /// Run on each file's chunks before they're embedded
+    pub filters: ChunkFilters,
 }
 
 pub struct CodebaseScanner<E, S>
@@ -23,10 +116,130 @@ where
     E: EmbeddingClient,
     S: Storage,
 {
-    parser: Parser,
+    files: FileParser,
     embedding_client: E,
     storage: S,
-    config: ScannerConfig,
+    cost_estimator: Option<CostEstimator>,
+    selection: FileSelection,
+    checkpoint: Option<Checkpoint>,
+    memory_budget: Option<MemoryBudget>,
+    progress: Option<UnboundedSender<ProgressEvent>>,
+}
+
+/// Which files of the codebase are parsed
+struct FileSelection {
+    include_generated: bool,
+    include: Vec<String>,
+    exclude: Vec<String>,
+    incremental: bool,
+    changed: Option<HashSet<PathBuf>>,
+    /// Files an interrupted scan already stored, with their hash at the time
+    completed: HashMap<PathBuf, String>,
+    max_file_size: u64,
+    follow_symlinks: bool,
+    hidden: bool,
+    skip_dirs: Vec<OsString>,
+    tracked: Option<HashSet<PathBuf>>,
+    max_depth: Option<usize>,
+    nested_repos: bool,
+    submodules: bool,
+    blame: bool,
+    /// Stored files that are gone from disk by their hash, each claimed by the first file found
+    /// with its content
+    vanished: Mutex<HashMap<String, PathBuf>>,
+}
+
+/// What a scan does with a file it walked past
+enum FileAction<'a> {
+    Skip,
+    /// Left out for a reason worth reporting
+    Skipped(String),
+    /// Couldn't be read
+    Failed(String),
+    /// Stored as it is, keeping these points
+    Unchanged(&'a [u64]),
+    /// Stored as it is under a path that's gone, these points move to the new one
+    Moved(&'a [u64]),
+    Parse {
+        parser: SupportedParsers,
+        content: String,
+        hash: String,
+        /// Encoding the file was transcoded from
+        encoding: Option<&'static str>,
+    },
+}
+
+/// Chunks passed between pipeline stages, with the files whose last chunk is among them
+#[derive(Default)]
+struct ChunkGroup {
+    chunks: Vec<CodeChunk>,
+    /// Filled in by the embed stage
+    embeddings: Vec<Embedding>,
+    files: Vec<CompletedFile>,
+    /// Share of the memory budget, given back once the group is stored and dropped
+    reserved: Option<OwnedSemaphorePermit>,
+}
+
+/// Memory the chunks and embeddings in the pipeline may take, in KiB permits
+struct MemoryBudget {
+    permits: Arc<Semaphore>,
+    total: u32,
+}
+
+impl MemoryBudget {
+    fn new(bytes: u64) -> Self {
+        let total = (bytes / 1024).clamp(1, u32::MAX as u64) as u32;
+
+        Self {
+            permits: Arc::new(Semaphore::new(total as usize)),
+            total,
+        }
+    }
+
+    /// Wait until there's room for `chunks` and their embeddings, holding it until the returned
+    /// permit is dropped. A group larger than the whole budget waits for the pipeline to empty
+    async fn reserve(&self, chunks: &[CodeChunk]) -> Option<OwnedSemaphorePermit> {
+        let bytes: usize = chunks.iter().map(|chunk| chunk.content.len() + EMBEDDING_BYTES).sum();
+        let kib = (bytes.div_ceil(1024) as u64).clamp(1, self.total as u64) as u32;
+
+        self.permits.clone().acquire_many_owned(kib).await.ok()
+    }
+}
+
+/// What the parse stage of the pipeline did
+#[derive(Default)]
+struct ParsedFiles {
+    files: usize,
+    chunks: usize,
+    files_unchanged: usize,
+    /// Points of unchanged files, kept as they are
+    unchanged: HashSet<u64>,
+    /// Files stored under another path, whose points only need the new one
+    moved: Vec<MovedFile>,
+    skipped: Vec<SkippedFile>,
+    errors: Vec<FileError>,
+    elapsed: Duration,
+}
+
+/// A file found under a new path with the content it was stored with under an old one
+struct MovedFile {
+    path: PathBuf,
+    ids: Vec<u64>,
+    package: Option<String>,
+}
+
+/// What the embed stage of the pipeline did
+#[derive(Default)]
+struct EmbeddedChunks {
+    failed: Vec<FailedChunk>,
+    elapsed: Duration,
+}
+
+/// What the store stage of the pipeline did
+#[derive(Default)]
+struct StoredChunks {
+    ids: HashSet<u64>,
+    elapsed: Duration,
 }
 
 impl<E, S> CodebaseScanner<E, S>
@@ -34,87 +247,1129 @@ where
     E: EmbeddingClient,
     S: Storage,
 {
-    pub fn new(embedding_client: E, storage: S, config: ScannerConfig) -> Self {
+    pub fn new(embedding_client: E, storage: S, mut config: ScannerConfig) -> Self {
+        let completed =
+            config.checkpoint.as_mut().map(Checkpoint::take_completed).unwrap_or_default();
+
+        let selection = FileSelection::new(&config, completed);
+
         Self {
-            parser: Parser::new(),
+            files: FileParser::new(config.chunker).with_filters(config.filters),
             embedding_client,
             storage,
-            config,
+            cost_estimator: config.cost_estimator,
+            selection,
+            checkpoint: config.checkpoint,
+            memory_budget: config.max_memory.map(MemoryBudget::new),
+            progress: None,
         }
     }
 
-    pub async fn scan_codebase(&mut self, root: &Path) -> Result<ScanResults> {
-        let mut chunks = Vec::new();
+    /// Send embedding progress to `sender` while scanning, or stop sending it with `None`
+    pub fn set_progress(&mut self, sender: Option<UnboundedSender<ProgressEvent>>) {
+        self.progress = sender;
+    }
 
-        for entry in WalkDir::new(root)
-            .into_iter()
-            .filter_entry(is_wanted_directory)
-            .filter_map(|e| e.ok())
+    /// Only look at `changed` files in the scans that follow, or at every file with `None`
+    pub fn set_changed(&mut self, changed: Option<HashSet<PathBuf>>) {
+        self.selection.changed = changed;
+    }
+
+    /// Parse, embed and store the codebases under `roots` as a pipeline, each stage working on a
+    /// group of chunks while the one before it prepares the next. All roots go through one walk,
+    /// so files under each of them are kept when stale points are removed
+    pub async fn scan_codebase(&mut self, roots: &[PathBuf]) -> Result<ScanResults> {
+        let indexed_loaded = self.selection.incremental
+            || self.selection.changed.is_some()
+            || !self.selection.completed.is_empty();
+        let indexed = match indexed_loaded {
+            true => self.storage.indexed_files().await?,
+            false => HashMap::new(),
+        };
+
+        if let Some(estimator) = self.cost_estimator.clone() {
+            self.check_cost(roots, &estimator, &indexed)?;
+        }
+
+        // After the cost estimate, which would claim the moves otherwise
+        if self.selection.incremental {
+            self.selection.vanished = Mutex::new(vanished_files(&indexed));
+        }
+
+        let Self {
+            files,
+            embedding_client,
+            storage,
+            selection,
+            checkpoint,
+            memory_budget,
+            progress,
+            ..
+        } = self;
+
+        let scan_started = Instant::now();
+        let walk = selection.walk(roots)?;
+        let (chunk_sender, chunk_receiver) = mpsc::channel(PIPELINE_DEPTH);
+        let (embedded_sender, embedded_receiver) = mpsc::channel(PIPELINE_DEPTH);
+        let tracker = ProgressTracker::new(progress.clone());
+
+        let (parsed, embedded, stored) = tokio::join!(
+            parse_files(
+                walk,
+                files,
+                selection,
+                &indexed,
+                memory_budget.as_ref(),
+                chunk_sender,
+                &tracker
+            ),
+            embed_chunks(
+                embedding_client,
+                chunk_receiver,
+                embedded_sender,
+                ProgressTracker::new(progress.clone()),
+            ),
+            store_chunks(storage, embedded_receiver, checkpoint.as_mut(), &tracker),
+        );
+        let stored = stored?;
+        // The provider failing leaves everything stored as it was
+        let embedded = embedded?;
+
+        // Everything not written or kept by this scan belongs to deleted or changed files
+        let started = Instant::now();
+        move_files(storage, &parsed.moved).await?;
+        let mut keep: HashSet<u64> = parsed.unchanged.union(&stored.ids).copied().collect();
+
+        // Files that failed keep what an earlier scan stored for them until they scan cleanly
+        let failed: Vec<&Path> = embedded
+            .failed
+            .iter()
+            .map(|failed| failed.path.as_path())
+            .chain(parsed.errors.iter().map(|error| error.path.as_path()))
+            .collect();
+        if !failed.is_empty() {
+            let indexed = match indexed_loaded {
+                true => indexed,
+                false => storage.indexed_files().await?,
+            };
+            keep.extend(previously_stored(&indexed, &failed));
+        }
+
+        storage.remove_stale(&keep).await?;
+
+        if let Some(checkpoint) = checkpoint.take() {
+            checkpoint.finish()?;
+        }
+        // What an interrupted scan stored is all in the index now
+        selection.completed.clear();
+
+        tracker.send(ProgressEvent::ScanFinished {
+            stored: stored.ids.len(),
+            elapsed_seconds: scan_started.elapsed().as_secs_f64(),
+        });
+
+        Ok(ScanResults {
+            chunks_processed: parsed.chunks,
+            files_unchanged: parsed.files_unchanged,
+            files_moved: parsed.moved.len(),
+            embeddings_generated: stored.ids.len(),
+            failed_chunks: embedded.failed,
+            skipped_files: parsed.skipped,
+            file_errors: parsed.errors,
+            timings: StageTimings {
+                parse: parsed.elapsed,
+                embed: embedded.elapsed,
+                store: stored.elapsed + started.elapsed(),
+            },
+        })
+    }
+
+    /// Parse the codebase once without keeping the chunks, to estimate what embedding them costs
+    /// before anything is sent
+    fn check_cost(
+        &mut self,
+        roots: &[PathBuf],
+        estimator: &CostEstimator,
+        indexed: &HashMap<PathBuf, IndexedFile>,
+    ) -> Result<()> {
+        let mut estimate = estimator.estimate(&[]);
+        let mut group = Vec::new();
+
+        for entry in self.selection.walk(roots)?.filter_map(|e| e.ok()) {
+            let FileAction::Parse {
+                parser, content, ..
+            } = self.selection.action(entry.path(), indexed)
+            else {
+                continue;
+            };
+
+            if let Ok(chunks) = self.files.parse_file(entry.path(), &content, &parser) {
+                group.extend(chunks);
+            }
+
+            // Estimate the groups the embed stage will send
+            while group.len() >= EMBED_GROUP_SIZE {
+                estimate.add(estimator.estimate(&group[..EMBED_GROUP_SIZE]));
+                group.drain(..EMBED_GROUP_SIZE);
+            }
+        }
+
+        if !group.is_empty() {
+            estimate.add(estimator.estimate(&group));
+        }
+
+        estimator.check(estimate)?;
+        Ok(())
+    }
+}
+
+/// Walk and chunk the codebases under `roots` the way a scan would, handing each file's chunks
+/// to `on_file` instead of embedding them. Nothing is compared with the index, so every selected
+/// file is chunked. Returns the files left out and the ones that failed
+pub fn chunk_codebase(
+    config: ScannerConfig,
+    roots: &[PathBuf],
+    mut on_file: impl FnMut(ChunkedFile) -> Result<()>,
+) -> Result<(Vec<SkippedFile>, Vec<FileError>)> {
+    let selection = FileSelection::new(&config, HashMap::new());
+    let mut files = FileParser::new(config.chunker).with_filters(config.filters);
+    let indexed = HashMap::new();
+    let mut skipped = Vec::new();
+    let mut errors = Vec::new();
+    let mut tags = FileTags::new(selection.blame);
+    // Real paths of the files seen, so a file reached through several links is chunked once
+    let mut seen = HashSet::new();
+
+    for entry in selection.walk(roots)? {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                errors.push(FileError {
+                    path: error_path(&e),
+                    stage: ErrorStage::Walk,
+                    reason: e.to_string(),
+                });
+                continue;
+            },
+        };
+        let path = entry.path();
+
+        if selection.follow_symlinks
+            && !seen.insert(path.canonicalize().unwrap_or_else(|_| path.to_path_buf()))
         {
-            let path = entry.path();
+            continue;
+        }
 
-            if !path.is_file() {
+        let (parser, content, hash, encoding) = match selection.action(path, &indexed) {
+            FileAction::Skip | FileAction::Unchanged(_) | FileAction::Moved(_) => continue,
+            FileAction::Skipped(reason) => {
+                skipped.push(SkippedFile {
+                    path: path.to_path_buf(),
+                    reason,
+                });
+                continue;
+            },
+            FileAction::Failed(reason) => {
+                errors.push(FileError {
+                    path: path.to_path_buf(),
+                    stage: ErrorStage::Read,
+                    reason,
+                });
                 continue;
+            },
+            FileAction::Parse {
+                parser,
+                content,
+                hash,
+                encoding,
+            } => (parser, content, hash, encoding),
+        };
+
+        match files.parse_file(path, &content, &parser) {
+            Ok(chunks) => on_file(ChunkedFile {
+                path: path.to_path_buf(),
+                language: parser.to_string(),
+                bytes: content.len(),
+                chunks: tags.tag(path, &content, &hash, encoding, chunks),
+            })?,
+            Err(e) => errors.push(FileError {
+                path: path.to_path_buf(),
+                stage: ErrorStage::Parse,
+                reason: e.to_string(),
+            }),
+        }
+    }
+
+    Ok((skipped, errors))
+}
+
+/// Walk and chunk the codebases under `roots` the way a scan would, without embedding or storing
+/// anything. Nothing is compared with the index, so every selected file is listed
+pub fn dry_run(config: ScannerConfig, roots: &[PathBuf]) -> Result<DryRunResults> {
+    let mut planned = Vec::new();
+    let (skipped_files, file_errors) = chunk_codebase(config, roots, |file| {
+        planned.push(PlannedFile {
+            path: file.path,
+            language: file.language,
+            bytes: file.bytes,
+            chunks: file.chunks.len(),
+        });
+        Ok(())
+    })?;
+
+    Ok(DryRunResults {
+        files: planned,
+        skipped_files,
+        file_errors,
+    })
+}
+
+impl FileSelection {
+    /// Selection set up by `config`, treating the files in `completed` as stored when their hash
+    /// still matches
+    fn new(config: &ScannerConfig, completed: HashMap<PathBuf, String>) -> Self {
+        Self {
+            include_generated: config.include_generated,
+            include: config.include.clone(),
+            exclude: config.exclude.clone(),
+            incremental: config.incremental,
+            changed: config.changed.clone(),
+            completed,
+            max_file_size: config.max_file_size,
+            follow_symlinks: config.follow_symlinks,
+            hidden: config.hidden,
+            skip_dirs: config.skip_dirs.clone(),
+            tracked: config.tracked.clone(),
+            max_depth: config.max_depth,
+            nested_repos: config.nested_repos,
+            submodules: config.submodules,
+            blame: config.blame,
+            vanished: Mutex::default(),
+        }
+    }
+
+    /// Walk `roots` honoring `.gitignore`, `.git/info/exclude`, the global gitignore and
+    /// `.sherpaignore` files, also outside of git repositories. `include` and `exclude` globs take
+    /// precedence over all of them, matched relative to the directory holding every root.
+    /// Symlinked directories are only followed when asked, and then a link back up the tree is
+    /// reported instead of recursed into. Repositories nested below a root are left out unless
+    /// asked for
+    fn walk(&self, roots: &[PathBuf]) -> Result<ignore::Walk> {
+        let (first, rest) =
+            roots.split_first().ok_or(InvalidArgument(String::from("No path to scan")))?;
+        let mut overrides = OverrideBuilder::new(common_ancestor(roots));
+
+        for glob in &self.include {
+            overrides.add(glob)?;
+        }
+        for glob in &self.exclude {
+            overrides.add(&f!("!{glob}"))?;
+        }
+
+        let skip_dirs = self.skip_dirs.clone();
+        let (nested_repos, submodules) = (self.nested_repos, self.submodules);
+
+        let mut builder = WalkBuilder::new(first);
+        for root in rest {
+            builder.add(root);
+        }
+
+        Ok(builder
+            .hidden(!self.hidden)
+            .require_git(false)
+            .follow_links(self.follow_symlinks)
+            .max_depth(self.max_depth)
+            .add_custom_ignore_filename(IGNORE_FILE)
+            .overrides(overrides.build()?)
+            .filter_entry(move |entry| {
+                !is_skipped_dir(entry, &skip_dirs)
+                    && !is_skipped_repo(entry, nested_repos, submodules)
+            })
+            .build())
+    }
+
+    fn action<'a>(
+        &self,
+        path: &Path,
+        indexed: &'a HashMap<PathBuf, IndexedFile>,
+    ) -> FileAction<'a> {
+        if !path.is_file() || self.tracked.as_ref().is_some_and(|tracked| !tracked.contains(path)) {
+            return FileAction::Skip;
+        }
+
+        let Some(parser) = SupportedParsers::for_path(path) else {
+            return FileAction::Skip;
+        };
+
+        if self.changed.as_ref().is_some_and(|changed| !changed.contains(path)) {
+            return match indexed.get(path) {
+                Some(file) => FileAction::Unchanged(&file.ids),
+                None => FileAction::Skip,
+            };
+        }
+
+        let size = fs::metadata(path).map(|metadata| metadata.len()).unwrap_or_default();
+        if size > self.max_file_size {
+            info!("Skipping {} ({size} bytes)", path.display());
+            return FileAction::Skipped(f!(
+                "{size} bytes is over the {} byte limit",
+                self.max_file_size
+            ));
+        }
+
+        let bytes = match fs::read(path) {
+            Ok(bytes) => bytes,
+            Err(e) => return FileAction::Failed(e.to_string()),
+        };
+
+        // Sniff before decoding, so binaries with a source extension are left out quietly
+        if let Some(reason) = binary_reason(&bytes).filter(|_| !has_bom(&bytes)) {
+            info!("Skipping {} ({reason})", path.display());
+            return FileAction::Skipped(reason.to_string());
+        }
+
+        let (content, encoding) = decode(bytes);
+        if let Some(encoding) = encoding {
+            info!("Transcoding {} from {encoding}", path.display());
+        }
+
+        let generated = match self.include_generated {
+            true => None,
+            false => generated_reason(path, &content),
+        };
+        if let Some(reason) = generated {
+            info!("Skipping {} ({reason})", path.display());
+            return FileAction::Skipped(reason.to_string());
+        }
+
+        let hash = sha256_hex(&content);
+        let resumed = self.completed.get(path).is_some_and(|completed| *completed == hash);
+
+        let moved = match indexed.get(path) {
+            None if !resumed => {
+                self.claim_vanished(path, &parser, &hash).and_then(|from| indexed.get(&from))
+            },
+            _ => None,
+        };
+
+        match (indexed.get(path), moved) {
+            (Some(file), _) if resumed || (self.incremental && file.hash == hash) => {
+                FileAction::Unchanged(&file.ids)
+            },
+            // Stored by the interrupted scan without any chunks
+            (None, _) if resumed => FileAction::Skip,
+            (None, Some(file)) => FileAction::Moved(&file.ids),
+            _ => FileAction::Parse {
+                parser,
+                content,
+                hash,
+                encoding,
+            },
+        }
+    }
+
+    /// The vanished file `path` was moved from, going by its content. Moves that would change
+    /// the language or whether the file holds tests are parsed again instead
+    fn claim_vanished(
+        &self,
+        path: &Path,
+        parser: &SupportedParsers,
+        hash: &str,
+    ) -> Option<PathBuf> {
+        let mut vanished = self.vanished.lock().ok()?;
+        let from = vanished.get(hash)?;
+
+        let same_kind = SupportedParsers::for_path(from)
+            .is_some_and(|from| from.to_string() == parser.to_string())
+            && is_test_path(from) == is_test_path(path);
+
+        match same_kind {
+            true => vanished.remove(hash),
+            false => None,
+        }
+    }
+}
+
+/// Stored files that are no longer on disk, by the hash of their content
+fn vanished_files(indexed: &HashMap<PathBuf, IndexedFile>) -> HashMap<String, PathBuf> {
+    indexed
+        .iter()
+        .filter(|(path, file)| !file.hash.is_empty() && !path.exists())
+        .map(|(path, file)| (file.hash.clone(), path.clone()))
+        .collect()
+}
+
+/// Point the stored chunks of moved files at their new path, keeping their ids and vectors
+/// instead of embedding the same content again
+async fn move_files<S: Storage>(storage: &S, moved: &[MovedFile]) -> Result<()> {
+    if moved.is_empty() {
+        return Ok(());
+    }
+
+    let files: HashMap<u64, &MovedFile> = moved
+        .iter()
+        .flat_map(|file| file.ids.iter().map(move |&id| (id, file)))
+        .collect();
+    let ids: Vec<u64> = files.keys().copied().collect();
+
+    let mut points = storage.get_points(&ids).await?;
+    for point in &mut points {
+        if let Some(file) = files.get(&point.id) {
+            point.chunk.path = file.path.clone();
+            point.chunk.package = file.package.clone();
+        }
+    }
+
+    storage.import_points(&points).await
+}
+
+/// Parse stage: chunk every selected file, sending the chunks on in embedding groups
+async fn parse_files(
+    walk: ignore::Walk,
+    files: &mut FileParser,
+    selection: &FileSelection,
+    indexed: &HashMap<PathBuf, IndexedFile>,
+    budget: Option<&MemoryBudget>,
+    sender: Sender<ChunkGroup>,
+    progress: &ProgressTracker,
+) -> ParsedFiles {
+    let parse_started = Instant::now();
+    let mut parsed = ParsedFiles::default();
+    let mut group = Vec::new();
+    let mut tags = FileTags::new(selection.blame);
+    // Real paths of the files seen, so a file reached through several links is scanned once
+    let mut seen = HashSet::new();
+    // Parsed files with the offset in `group` their chunks end at
+    let mut finished = Vec::new();
+
+    for entry in walk {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                parsed.errors.push(FileError {
+                    path: error_path(&e),
+                    stage: ErrorStage::Walk,
+                    reason: e.to_string(),
+                });
+                continue;
+            },
+        };
+        let path = entry.path();
+        let started = Instant::now();
+
+        if selection.follow_symlinks
+            && !seen.insert(path.canonicalize().unwrap_or_else(|_| path.to_path_buf()))
+        {
+            continue;
+        }
+
+        let (parser, content, hash, encoding) = match selection.action(path, indexed) {
+            FileAction::Skip => continue,
+            FileAction::Skipped(reason) => {
+                parsed.skipped.push(SkippedFile {
+                    path: path.to_path_buf(),
+                    reason,
+                });
+                continue;
+            },
+            FileAction::Failed(reason) => {
+                parsed.errors.push(FileError {
+                    path: path.to_path_buf(),
+                    stage: ErrorStage::Read,
+                    reason,
+                });
+                continue;
+            },
+            FileAction::Unchanged(ids) => {
+                parsed.unchanged.extend(ids.iter().copied());
+                parsed.files_unchanged += 1;
+                continue;
+            },
+            FileAction::Moved(ids) => {
+                parsed.unchanged.extend(ids.iter().copied());
+                parsed.moved.push(MovedFile {
+                    path: path.to_path_buf(),
+                    ids: ids.to_vec(),
+                    package: tags.packages.package_of(path),
+                });
+                continue;
+            },
+            FileAction::Parse {
+                parser,
+                content,
+                hash,
+                encoding,
+            } => (parser, content, hash, encoding),
+        };
+
+        match files.parse_file(path, &content, &parser) {
+            Ok(chunks) => {
+                group.extend(tags.tag(path, &content, &hash, encoding, chunks));
+                finished.push((
+                    group.len(),
+                    CompletedFile {
+                        path: path.to_path_buf(),
+                        hash,
+                    },
+                ));
+            },
+            Err(e) => parsed.errors.push(FileError {
+                path: path.to_path_buf(),
+                stage: ErrorStage::Parse,
+                reason: e.to_string(),
+            }),
+        }
+
+        parsed.elapsed += started.elapsed();
+        parsed.files += 1;
+        progress.send(ProgressEvent::Parsed {
+            files: parsed.files,
+            chunks: parsed.chunks + group.len(),
+            current_file: path.to_path_buf(),
+        });
+
+        while group.len() >= EMBED_GROUP_SIZE {
+            let mut full = split_group(&mut group, &mut finished, EMBED_GROUP_SIZE);
+            parsed.chunks += full.chunks.len();
+
+            if let Some(budget) = budget {
+                full.reserved = budget.reserve(&full.chunks).await;
             }
 
-            if let Some(extension) = path.extension() {
-                if let Ok(parser) =
-                    serde_plain::from_str::<SupportedParsers>(&extension.to_string_lossy())
-                {
-                    match fs::read_to_string(path) {
-                        Ok(content) => match self.parse_file(path, &content, &parser) {
-                            Ok(file_chunks) => chunks.extend(file_chunks),
-                            Err(e) => warn!("Failed to parse {}: {}", path.display(), e),
-                        },
-                        Err(e) => warn!("Failed to read {}: {}", path.display(), e),
-                    }
-                }
+            // The later stages stopped, there's no one to parse for
+            if sender.send(full).await.is_err() {
+                return parsed;
             }
         }
 
-        // Generate embeddings
-        let embeddings = self.embedding_client.embed(&chunks).await?;
+        // Parsing doesn't wait on anything, so let the other stages have their turn
+        tokio::task::yield_now().await;
+    }
+
+    parsed.chunks += group.len();
+    progress.send(ProgressEvent::ParseFinished {
+        files: parsed.files,
+        files_unchanged: parsed.files_unchanged,
+        chunks: parsed.chunks,
+        elapsed_seconds: parse_started.elapsed().as_secs_f64(),
+    });
 
-        // Store the embeddings
-        self.storage.store_chunks(&chunks, &embeddings).await?;
+    if !group.is_empty() || !finished.is_empty() {
+        let size = group.len();
+        let mut last = split_group(&mut group, &mut finished, size);
 
-        Ok(ScanResults {
-            chunks_processed: chunks.len(),
-            embeddings_generated: embeddings.len(),
-        })
+        if let Some(budget) = budget {
+            last.reserved = budget.reserve(&last.chunks).await;
+        }
+
+        let _ = sender.send(last).await;
     }
 
-    fn parse_file(
+    parsed
+}
+
+/// What's known about a whole file, set on each of its chunks
+struct FileTags {
+    packages: Packages,
+    /// Set when chunks are tagged with who last changed them
+    blamer: Option<Blamer>,
+}
+
+impl FileTags {
+    fn new(blame: bool) -> Self {
+        Self {
+            packages: Packages::default(),
+            blamer: blame.then(Blamer::default),
+        }
+    }
+
+    /// Tag the `chunks` of the file at `path`, which holds `content` with the given hash and was
+    /// transcoded from `encoding`
+    fn tag(
         &mut self,
         path: &Path,
         content: &str,
-        language: &SupportedParsers,
-    ) -> Result<Vec<CodeChunk>> {
-        self.parser.set_language(&language.language())?;
-
-        let tree = self.parser.parse(content, None).ok_or(ParsingFailed(path.to_path_buf()))?;
-
-        let chunks = extract_chunks(
-            &tree,
-            content,
-            path,
-            language,
-            self.config.chunk_size_limit,
-            self.config.overlap_percentage,
-        );
-        info!("Extracted {} chunks from {path:?}", chunks.len());
-        Ok(chunks)
+        hash: &str,
+        encoding: Option<&str>,
+        chunks: Vec<CodeChunk>,
+    ) -> Vec<CodeChunk> {
+        let package = self.packages.package_of(path);
+        // Files outside a repository or not committed yet have no one to blame
+        let blame = self.blamer.as_mut().and_then(|blamer| {
+            blamer
+                .blame(path, content)
+                .inspect_err(|e| debug!("Not blaming {}: {e}", path.display()))
+                .ok()
+        });
+
+        chunks
+            .into_iter()
+            .map(|mut chunk| {
+                chunk.file_hash = hash.to_string();
+                chunk.encoding = encoding.map(str::to_string);
+                chunk.package = package.clone();
+
+                // Chunk lines count from 0, blamed ones from 1
+                if let Some(lines) = blame
+                    .as_ref()
+                    .and_then(|blame| blame.lines(chunk.start_line + 1, chunk.end_line + 1))
+                {
+                    chunk.author = Some(lines.author);
+                    chunk.last_modified = Some(lines.last_modified);
+                }
+
+                chunk
+            })
+            .collect()
     }
 }
 
-fn is_wanted_directory(entry: &DirEntry) -> bool {
-    if !entry.path().is_dir() {
-        return true; // Always include files
+/// Path a walk error is about, empty when it isn't about one
+fn error_path(error: &ignore::Error) -> PathBuf {
+    match error {
+        ignore::Error::WithPath { path, .. } => path.clone(),
+        ignore::Error::Loop { child, .. } => child.clone(),
+        ignore::Error::WithDepth { err, .. } | ignore::Error::WithLineNumber { err, .. } => {
+            error_path(err)
+        },
+        _ => PathBuf::new(),
+    }
+}
+
+/// Ids of the points stored for files at or under the `failed` paths, which can be directories
+/// the walk couldn't read. A walk error about no path in particular keeps every file
+fn previously_stored<'a>(
+    indexed: &'a HashMap<PathBuf, IndexedFile>,
+    failed: &'a [&Path],
+) -> impl Iterator<Item = u64> + 'a {
+    indexed
+        .iter()
+        .filter(|(path, _)| failed.iter().any(|failed| path.starts_with(failed)))
+        .flat_map(|(_, file)| file.ids.iter().copied())
+}
+
+/// Take the first `size` chunks as a group, along with the files that end among them
+fn split_group(
+    group: &mut Vec<CodeChunk>,
+    finished: &mut Vec<(usize, CompletedFile)>,
+    size: usize,
+) -> ChunkGroup {
+    let chunks = group.drain(..size).collect();
+    let ending = finished.iter().take_while(|(end, _)| *end <= size).count();
+    let files = finished.drain(..ending).map(|(_, file)| file).collect();
+
+    for (end, _) in finished.iter_mut() {
+        *end -= size;
+    }
+
+    ChunkGroup {
+        chunks,
+        files,
+        ..Default::default()
+    }
+}
+
+/// Embed stage: embed each group, retrying a group the provider rejected one chunk at a time so
+/// only the chunks that really fail are dropped. Stops at the first error of the provider itself
+async fn embed_chunks<E: EmbeddingClient>(
+    client: &E,
+    mut receiver: Receiver<ChunkGroup>,
+    sender: Sender<ChunkGroup>,
+    mut progress: ProgressTracker,
+) -> Result<EmbeddedChunks> {
+    let mut embedded = EmbeddedChunks::default();
+    let mut done = 0;
+    // Files with a chunk that failed aren't complete, so a resumed scan tries them again
+    let mut failed_files = HashSet::new();
+
+    while let Some(mut group) = receiver.recv().await {
+        if !group.chunks.is_empty() {
+            progress.add_total(group.chunks.len());
+            let started = Instant::now();
+            let failed_before = embedded.failed.len();
+
+            let (chunks, embeddings) =
+                embed_group(client, group.chunks, &mut embedded.failed).await?;
+            group.chunks = chunks;
+            group.embeddings = embeddings;
+
+            failed_files
+                .extend(embedded.failed[failed_before..].iter().map(|failed| failed.path.clone()));
+
+            embedded.elapsed += started.elapsed();
+            done += group.chunks.len();
+            progress.update(done, embedded.failed.len(), last_path(&group.chunks));
+        }
+
+        group.files.retain(|file| !failed_files.contains(&file.path));
+
+        if (!group.chunks.is_empty() || !group.files.is_empty())
+            && sender.send(group).await.is_err()
+        {
+            break;
+        }
+    }
+
+    progress.finish(done, embedded.failed.len());
+
+    Ok(embedded)
+}
+
+/// Embed `group`, one chunk at a time when the provider rejects it as a whole. Errors when the
+/// provider fails in a way sending fewer chunks won't help with, like being unreachable, refusing
+/// the API key or rate limiting past its retries
+async fn embed_group<E: EmbeddingClient>(
+    client: &E,
+    group: Vec<CodeChunk>,
+    failed: &mut Vec<FailedChunk>,
+) -> Result<(Vec<CodeChunk>, Vec<Embedding>)> {
+    match client.embed(&group).await {
+        Ok(embeddings) if embeddings.len() == group.len() => return Ok((group, embeddings)),
+        Ok(embeddings) => warn!(
+            "Got {} embeddings for {} chunks, retrying them one by one",
+            embeddings.len(),
+            group.len()
+        ),
+        Err(e) if is_input_error(&e) => warn!(
+            "Embedding {} chunks failed, retrying them one by one: {e}",
+            group.len()
+        ),
+        Err(e) => return Err(e),
+    }
+
+    let mut chunks = Vec::with_capacity(group.len());
+    let mut embeddings = Vec::with_capacity(group.len());
+
+    for chunk in group {
+        let result = client.embed(std::slice::from_ref(&chunk)).await;
+
+        match result.map(|mut embedding| embedding.pop()) {
+            Ok(Some(embedding)) => {
+                chunks.push(chunk);
+                embeddings.push(embedding);
+            },
+            Ok(None) => failed.push(FailedChunk::new(&chunk, "No embedding returned")),
+            Err(e) if is_input_error(&e) => failed.push(FailedChunk::new(&chunk, &e.to_string())),
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok((chunks, embeddings))
+}
+
+/// Store stage: upsert each embedded group as it arrives
+async fn store_chunks<S: Storage>(
+    storage: &S,
+    mut receiver: Receiver<ChunkGroup>,
+    mut checkpoint: Option<&mut Checkpoint>,
+    progress: &ProgressTracker,
+) -> Result<StoredChunks> {
+    let mut stored = StoredChunks::default();
+
+    while let Some(group) = receiver.recv().await {
+        let started = Instant::now();
+        if !group.chunks.is_empty() {
+            storage.store_chunks(&group.chunks, &group.embeddings).await?;
+        }
+        stored.elapsed += started.elapsed();
+
+        if let Some(checkpoint) = checkpoint.as_mut() {
+            checkpoint.record(&group.files)?;
+        }
+
+        stored.ids.extend(group.chunks.iter().map(CodeChunk::id));
+        progress.send(ProgressEvent::Stored {
+            stored: stored.ids.len(),
+        });
+    }
+
+    Ok(stored)
+}
+
+fn last_path(chunks: &[CodeChunk]) -> PathBuf {
+    chunks.last().map(|chunk| chunk.path.clone()).unwrap_or_default()
+}
+
+/// Whether `entry` is the root of a git repository below a scanned root that isn't wanted.
+/// Submodules have a `.git` file where other repositories have a directory
+fn is_skipped_repo(entry: &DirEntry, nested_repos: bool, submodules: bool) -> bool {
+    if entry.depth() == 0 || !entry.file_type().is_some_and(|file_type| file_type.is_dir()) {
+        return false;
+    }
+
+    match fs::metadata(entry.path().join(".git")) {
+        Ok(git) if git.is_file() => !submodules,
+        Ok(_) => !nested_repos,
+        Err(_) => false,
+    }
+}
+
+/// Whether `entry` is a directory on the skip list. Names are compared as they are on disk, so
+/// ones that aren't valid UTF-8 are matched too
+fn is_skipped_dir(entry: &DirEntry, skip_dirs: &[OsString]) -> bool {
+    entry.file_type().is_some_and(|file_type| file_type.is_dir())
+        && skip_dirs.iter().any(|name| name == entry.file_name())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use async_trait::async_trait;
+
+    use super::*;
+    use crate::{
+        chunking::{ChunkStrategyKind, ChunkerConfig},
+        embedding::Tokenizer,
+        storage::{CollectionInfo, StoredPoint},
+    };
+
+    /// Fails every request with the error `fail` makes, counting the requests
+    struct FailingClient {
+        fail: fn() -> Error,
+        calls: AtomicUsize,
+    }
+
+    impl FailingClient {
+        fn new(fail: fn() -> Error) -> Self {
+            Self {
+                fail,
+                calls: AtomicUsize::new(0),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl EmbeddingClient for FailingClient {
+        async fn embed(&self, _chunks: &[CodeChunk]) -> Result<Vec<Embedding>> {
+            self.calls.fetch_add(1, Ordering::Relaxed);
+            Err((self.fail)())
+        }
+
+        async fn context_length(&self) -> Result<usize> {
+            Ok(512)
+        }
+
+        async fn embed_length(&self) -> Result<usize> {
+            Ok(4)
+        }
+
+        fn tokenizer(&self) -> Tokenizer {
+            Tokenizer::Approximate
+        }
+    }
+
+    /// Points held in memory, for scans that don't need a Qdrant server. Clones share the points
+    #[derive(Clone, Default)]
+    struct MemoryStorage {
+        points: Arc<Mutex<HashMap<u64, StoredPoint>>>,
     }
 
-    entry
-        .file_name()
-        .to_str()
-        .map(|s| s != "target" && s != ".git" && s != "node_modules")
-        .unwrap_or(false)
+    impl MemoryStorage {
+        fn ids(&self) -> HashSet<u64> {
+            self.points.lock().unwrap().keys().copied().collect()
+        }
+    }
+
+    impl Storage for MemoryStorage {
+        async fn store_chunks(&self, chunks: &[CodeChunk], embeddings: &[Embedding]) -> Result<()> {
+            let points = chunks.iter().zip(embeddings).map(|(chunk, embedding)| StoredPoint {
+                id: chunk.id(),
+                chunk: chunk.clone(),
+                embedding: embedding.clone(),
+            });
+
+            self.import_points(&points.collect::<Vec<_>>()).await
+        }
+
+        async fn remove_stale(&self, keep: &HashSet<u64>) -> Result<()> {
+            self.points.lock().unwrap().retain(|id, _| keep.contains(id));
+            Ok(())
+        }
+
+        async fn indexed_files(&self) -> Result<HashMap<PathBuf, IndexedFile>> {
+            let mut files: HashMap<PathBuf, IndexedFile> = HashMap::new();
+
+            for point in self.points.lock().unwrap().values() {
+                let file = files.entry(point.chunk.path.clone()).or_default();
+                file.hash = point.chunk.file_hash.clone();
+                file.ids.push(point.id);
+            }
+
+            Ok(files)
+        }
+
+        async fn export_page(
+            &self,
+            _offset: Option<u64>,
+        ) -> Result<(Vec<StoredPoint>, Option<u64>)> {
+            Ok((
+                self.points.lock().unwrap().values().cloned().collect(),
+                None,
+            ))
+        }
+
+        async fn count_points(&self) -> Result<u64> {
+            Ok(self.points.lock().unwrap().len() as u64)
+        }
+
+        async fn get_points(&self, ids: &[u64]) -> Result<Vec<StoredPoint>> {
+            let points = self.points.lock().unwrap();
+            Ok(ids.iter().filter_map(|id| points.get(id).cloned()).collect())
+        }
+
+        async fn import_points(&self, points: &[StoredPoint]) -> Result<()> {
+            let mut stored = self.points.lock().unwrap();
+            stored.extend(points.iter().map(|point| (point.id, point.clone())));
+            Ok(())
+        }
+
+        async fn collections(&self) -> Result<Vec<String>> {
+            Ok(Vec::new())
+        }
+
+        async fn collection_info(&self) -> Result<CollectionInfo> {
+            Ok(CollectionInfo {
+                name: String::from("memory"),
+                vector_size: Some(4),
+                model: None,
+                points: self.points.lock().unwrap().len() as u64,
+            })
+        }
+
+        async fn delete_collection(&self) -> Result<()> {
+            self.points.lock().unwrap().clear();
+            Ok(())
+        }
+
+        async fn rename_collection(&self, _name: &str) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    fn api_error(status: u16) -> Error {
+        EmbeddingApi {
+            status,
+            retry_after: None,
+            message: String::new(),
+        }
+    }
+
+    fn group(files: usize) -> Vec<CodeChunk> {
+        (0..files)
+            .map(|i| CodeChunk {
+                content: f!("fn f{i}() {{}}"),
+                path: PathBuf::from(f!("src/f{i}.rs")),
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn rejected_groups_are_retried_one_chunk_at_a_time() {
+        let client = FailingClient::new(|| api_error(400));
+        let mut failed = Vec::new();
+
+        let (chunks, _) = embed_group(&client, group(3), &mut failed).await.unwrap();
+
+        assert!(chunks.is_empty());
+        assert_eq!(failed.len(), 3);
+        assert_eq!(client.calls.load(Ordering::Relaxed), 4);
+    }
+
+    #[tokio::test]
+    async fn provider_errors_fail_the_group_without_retrying_each_chunk() {
+        let client = FailingClient::new(|| api_error(401));
+        let mut failed = Vec::new();
+
+        assert!(embed_group(&client, group(3), &mut failed).await.is_err());
+        assert_eq!(client.calls.load(Ordering::Relaxed), 1);
+    }
+
+    /// A codebase of two files, and a storage holding what an earlier scan made of them
+    async fn indexed_codebase(name: &str) -> (PathBuf, MemoryStorage) {
+        let root = std::env::temp_dir().join(f!("code-sherpa-{name}-{}", std::process::id()));
+        fs::create_dir_all(&root).unwrap();
+
+        let storage = MemoryStorage::default();
+        let mut old = Vec::new();
+
+        for (i, file) in ["a.rs", "b.rs"].into_iter().enumerate() {
+            let path = root.join(file);
+            fs::write(
+                &path,
+                f!("fn changed_{i}() {{\n    println!(\"{i}\");\n}}\n"),
+            )
+            .unwrap();
+
+            old.push(CodeChunk {
+                content: f!("fn old_{i}() {{}}"),
+                path,
+                file_hash: String::from("stale"),
+                ..Default::default()
+            });
+        }
+
+        storage.store_chunks(&old, &[vec![0.0; 4], vec![0.0; 4]]).await.unwrap();
+        (root, storage)
+    }
+
+    fn scanner<E: EmbeddingClient>(
+        client: E,
+        storage: &MemoryStorage,
+    ) -> CodebaseScanner<E, MemoryStorage> {
+        let config = ScannerConfig {
+            chunker: ChunkStrategyKind::Ast.build(ChunkerConfig::default()),
+            cost_estimator: None,
+            include_generated: false,
+            include: Vec::new(),
+            exclude: Vec::new(),
+            // A full scan, which doesn't read what's indexed up front
+            incremental: false,
+            changed: None,
+            checkpoint: None,
+            max_file_size: DEFAULT_MAX_FILE_SIZE,
+            follow_symlinks: false,
+            hidden: false,
+            skip_dirs: Vec::new(),
+            tracked: None,
+            max_depth: None,
+            nested_repos: false,
+            submodules: false,
+            blame: false,
+            max_memory: None,
+            filters: ChunkFilters::default(),
+        };
+
+        CodebaseScanner::new(client, storage.clone(), config)
+    }
+
+    #[tokio::test]
+    async fn files_that_fail_to_embed_keep_their_old_points() {
+        let (root, storage) = indexed_codebase("rejected").await;
+        let before = storage.ids();
+
+        let results = scanner(FailingClient::new(|| api_error(400)), &storage)
+            .scan_codebase(std::slice::from_ref(&root))
+            .await
+            .unwrap();
+
+        assert!(!results.failed_chunks.is_empty());
+        assert_eq!(storage.ids(), before);
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[tokio::test]
+    async fn a_provider_outage_leaves_the_collection_alone() {
+        let (root, storage) = indexed_codebase("outage").await;
+        let before = storage.ids();
+
+        let scan = scanner(FailingClient::new(|| api_error(503)), &storage)
+            .scan_codebase(std::slice::from_ref(&root))
+            .await;
+
+        assert!(scan.is_err());
+        assert_eq!(storage.ids(), before);
+        fs::remove_dir_all(root).unwrap();
+    }
 }
@@ -1,9 +1,64 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+};
+
+use serde::Serialize;
+
+use super::{IndexedFile, StoredPoint};
 use crate::{chunking::CodeChunk, embedding::Embedding, error::Error};
 
+/// What's stored in a collection
+#[derive(Debug, Clone, Serialize)]
+pub struct CollectionInfo {
+    pub name: String,
+    /// Dimensions of the embeddings, `None` when the collection has no vector config we know
+    pub vector_size: Option<u64>,
+    /// Model the points were embedded with, recorded by scans
+    pub model: Option<String>,
+    pub points: u64,
+}
+
 pub trait Storage {
+    /// Upsert chunks with their embeddings, leaving other points alone
     async fn store_chunks(
         &self,
         chunks: &[CodeChunk],
         embeddings: &[Embedding],
     ) -> Result<(), Error>;
+
+    /// Delete every point except the ones in `keep`, once a scan has stored everything
+    async fn remove_stale(&self, keep: &HashSet<u64>) -> Result<(), Error>;
+
+    /// Every file in the collection with the hash it was indexed at
+    async fn indexed_files(&self) -> Result<HashMap<PathBuf, IndexedFile>, Error>;
+
+    /// Read back one page of points starting at `offset`, including their vectors, with the
+    /// offset of the next page. `None` once there are no more pages
+    async fn export_page(
+        &self,
+        offset: Option<u64>,
+    ) -> Result<(Vec<StoredPoint>, Option<u64>), Error>;
+
+    /// Exact number of points in the collection
+    async fn count_points(&self) -> Result<u64, Error>;
+
+    /// Points with the given ids, e.g. to expand a method hit to its parent's `parent_id`
+    async fn get_points(&self, ids: &[u64]) -> Result<Vec<StoredPoint>, Error>;
+
+    /// Write points as-is, keeping their ids and without removing anything already stored
+    async fn import_points(&self, points: &[StoredPoint]) -> Result<(), Error>;
+
+    /// Names of every collection next to this one
+    async fn collections(&self) -> Result<Vec<String>, Error>;
+
+    /// Vector size, embedding model and point count of the collection
+    async fn collection_info(&self) -> Result<CollectionInfo, Error>;
+
+    /// Delete the collection with every point in it
+    async fn delete_collection(&self) -> Result<(), Error>;
+
+    /// Copy every point into a new collection called `name` page by page, then delete this one
+    /// once both hold the same number of points
+    async fn rename_collection(&self, name: &str) -> Result<(), Error>;
 }
@@ -1,5 +1,9 @@
 mod client;
+mod point;
+mod point_file;
 mod qdrant;
 
-pub use client::Storage;
-pub use qdrant::QdrantStorage;
+pub use client::{CollectionInfo, Storage};
+pub use point::{IndexedFile, SearchHit, StoredPoint};
+pub use point_file::{PointFormat, PointReader, PointWriter};
+pub use qdrant::{QdrantStorage, StorageConfig, all_of, package_filter, test_filter};
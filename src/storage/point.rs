@@ -0,0 +1,25 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{chunking::CodeChunk, embedding::Embedding};
+
+/// A chunk together with its vector, as held by a storage backend
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredPoint {
+    pub id: u64,
+    pub chunk: CodeChunk,
+    pub embedding: Embedding,
+}
+
+/// A chunk found by a search, with how close it is to the query
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchHit {
+    pub score: f32,
+    pub chunk: CodeChunk,
+}
+
+/// A file as it was last indexed: the hash of its content and the points its chunks became
+#[derive(Debug, Clone, Default)]
+pub struct IndexedFile {
+    pub hash: String,
+    pub ids: Vec<u64>,
+}
@@ -0,0 +1,276 @@
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Lines, Write},
+    path::Path,
+    sync::Arc,
+};
+
+use arrow_array::{
+    ArrayRef, RecordBatch, StringArray, UInt64Array,
+    builder::{Float32Builder, ListBuilder},
+    cast::AsArray,
+    types::{Float32Type, UInt64Type},
+};
+use arrow_schema::{DataType, Field, Schema, SchemaRef};
+use clap::ValueEnum;
+use parquet::arrow::{
+    ArrowWriter,
+    arrow_reader::{ParquetRecordBatchReader, ParquetRecordBatchReaderBuilder},
+};
+
+use super::StoredPoint;
+use crate::prelude::*;
+
+/// Points read from an export at a time
+const PAGE_SIZE: usize = 256;
+
+/// How an export is laid out on disk
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum PointFormat {
+    /// One JSON point per line
+    Jsonl,
+    /// Columns of ids, chunks as JSON and embeddings
+    Parquet,
+}
+
+impl PointFormat {
+    /// Parquet for `.parquet` files, JSONL for anything else
+    pub fn of(path: &Path) -> Self {
+        match path.extension().is_some_and(|extension| extension == "parquet") {
+            true => Self::Parquet,
+            false => Self::Jsonl,
+        }
+    }
+}
+
+fn parquet_schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        Field::new("id", DataType::UInt64, false),
+        Field::new("chunk", DataType::Utf8, false),
+        Field::new(
+            "embedding",
+            DataType::List(Arc::new(Field::new("item", DataType::Float32, true))),
+            false,
+        ),
+    ]))
+}
+
+/// Writes points to an export file a page at a time
+pub enum PointWriter {
+    Jsonl(BufWriter<File>),
+    Parquet(Box<ArrowWriter<File>>),
+}
+
+impl PointWriter {
+    pub fn create(path: &Path, format: PointFormat) -> Result<Self> {
+        let file = File::create(path)?;
+
+        Ok(match format {
+            PointFormat::Jsonl => Self::Jsonl(BufWriter::new(file)),
+            PointFormat::Parquet => Self::Parquet(Box::new(ArrowWriter::try_new(
+                file,
+                parquet_schema(),
+                None,
+            )?)),
+        })
+    }
+
+    pub fn write(&mut self, points: &[StoredPoint]) -> Result<()> {
+        match self {
+            Self::Jsonl(writer) => {
+                for point in points {
+                    serde_json::to_writer(&mut *writer, point)?;
+                    writer.write_all(b"\n")?;
+                }
+            },
+            Self::Parquet(writer) => writer.write(&to_batch(points)?)?,
+        }
+
+        Ok(())
+    }
+
+    /// Flush what's buffered, and for Parquet write the footer the file can't be read without
+    pub fn finish(self) -> Result<()> {
+        match self {
+            Self::Jsonl(mut writer) => writer.flush()?,
+            Self::Parquet(writer) => {
+                writer.close()?;
+            },
+        }
+
+        Ok(())
+    }
+}
+
+/// Reads points back from an export file a page at a time
+pub enum PointReader {
+    Jsonl(Lines<BufReader<File>>),
+    Parquet(ParquetRecordBatchReader),
+}
+
+impl PointReader {
+    pub fn open(path: &Path, format: PointFormat) -> Result<Self> {
+        let file = File::open(path)?;
+
+        Ok(match format {
+            PointFormat::Jsonl => Self::Jsonl(BufReader::new(file).lines()),
+            PointFormat::Parquet => Self::Parquet(
+                ParquetRecordBatchReaderBuilder::try_new(file)?
+                    .with_batch_size(PAGE_SIZE)
+                    .build()?,
+            ),
+        })
+    }
+}
+
+impl Iterator for PointReader {
+    type Item = Result<Vec<StoredPoint>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            Self::Jsonl(lines) => {
+                let mut points = Vec::new();
+
+                for line in lines.by_ref() {
+                    let line = match line {
+                        Ok(line) => line,
+                        Err(error) => return Some(Err(error.into())),
+                    };
+
+                    if line.trim().is_empty() {
+                        continue;
+                    }
+
+                    match serde_json::from_str::<StoredPoint>(&line) {
+                        Ok(point) => points.push(point),
+                        Err(error) => return Some(Err(error.into())),
+                    }
+
+                    if points.len() == PAGE_SIZE {
+                        break;
+                    }
+                }
+
+                (!points.is_empty()).then_some(Ok(points))
+            },
+            Self::Parquet(batches) => batches.next().map(|batch| from_batch(&batch?)),
+        }
+    }
+}
+
+fn to_batch(points: &[StoredPoint]) -> Result<RecordBatch> {
+    let ids = UInt64Array::from_iter_values(points.iter().map(|point| point.id));
+    let chunks = points
+        .iter()
+        .map(|point| serde_json::to_string(&point.chunk))
+        .collect::<serde_json::Result<Vec<_>>>()?;
+
+    let mut embeddings = ListBuilder::new(Float32Builder::new());
+    for point in points {
+        embeddings.values().append_slice(&point.embedding);
+        embeddings.append(true);
+    }
+
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(ids),
+        Arc::new(StringArray::from(chunks)),
+        Arc::new(embeddings.finish()),
+    ];
+
+    Ok(RecordBatch::try_new(parquet_schema(), columns)?)
+}
+
+fn from_batch(batch: &RecordBatch) -> Result<Vec<StoredPoint>> {
+    let column = |name: &str| {
+        batch
+            .column_by_name(name)
+            .ok_or(Payload(f!("Parquet export has no `{name}` column")))
+    };
+
+    let ids = column("id")?.as_primitive_opt::<UInt64Type>();
+    let chunks = column("chunk")?.as_string_opt::<i32>();
+    let embeddings = column("embedding")?.as_list_opt::<i32>();
+    let (Some(ids), Some(chunks), Some(embeddings)) = (ids, chunks, embeddings) else {
+        return Err(Payload(
+            "Parquet export has columns of the wrong type".to_string(),
+        ));
+    };
+
+    (0..batch.num_rows())
+        .map(|row| {
+            let embedding = embeddings.value(row);
+            let embedding = embedding.as_primitive_opt::<Float32Type>().ok_or(Payload(
+                "Parquet embeddings aren't 32-bit floats".to_string(),
+            ))?;
+
+            Ok(StoredPoint {
+                id: ids.value(row),
+                chunk: serde_json::from_str(chunks.value(row))?,
+                embedding: embedding.values().to_vec(),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunking::CodeChunk;
+
+    fn points(count: usize) -> Vec<StoredPoint> {
+        (0..count)
+            .map(|i| StoredPoint {
+                id: i as u64,
+                chunk: CodeChunk {
+                    content: f!("fn f{i}() {{}}"),
+                    start_byte: i,
+                    ..Default::default()
+                },
+                embedding: vec![i as f32, 0.5, -1.0],
+            })
+            .collect()
+    }
+
+    fn round_trip(format: PointFormat) -> Vec<StoredPoint> {
+        let path = std::env::temp_dir().join(f!("code-sherpa-{format:?}-{}", std::process::id()));
+
+        let mut writer = PointWriter::create(&path, format).unwrap();
+        // Written over several pages, and read back in others
+        for page in points(600).chunks(100) {
+            writer.write(page).unwrap();
+        }
+        writer.finish().unwrap();
+
+        let pages = PointReader::open(&path, format).unwrap().collect::<Result<Vec<_>>>().unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        assert!(pages.iter().all(|page| page.len() <= PAGE_SIZE));
+        pages.into_iter().flatten().collect()
+    }
+
+    #[test]
+    fn points_survive_a_round_trip() {
+        for format in [PointFormat::Jsonl, PointFormat::Parquet] {
+            let read = round_trip(format);
+
+            assert_eq!(read.len(), 600);
+            for (read, written) in read.iter().zip(points(600)) {
+                assert_eq!(read.id, written.id);
+                assert_eq!(read.chunk.content, written.chunk.content);
+                assert_eq!(read.embedding, written.embedding);
+            }
+        }
+    }
+
+    #[test]
+    fn the_format_follows_the_extension() {
+        assert_eq!(
+            PointFormat::of(Path::new("index.parquet")),
+            PointFormat::Parquet
+        );
+        assert_eq!(
+            PointFormat::of(Path::new("index.jsonl")),
+            PointFormat::Jsonl
+        );
+    }
+}
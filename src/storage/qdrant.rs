@@ -1,27 +1,66 @@
 use std::{
     collections::{HashMap, HashSet},
-    hash::{DefaultHasher, Hash, Hasher},
+    future::Future,
+    path::PathBuf,
 };
 
+use futures::{StreamExt, TryStreamExt, stream};
 use qdrant_client::{
-    Qdrant,
+    Qdrant, QdrantError,
     qdrant::{
-        CreateCollectionBuilder, DeletePointsBuilder, Distance, PointId, PointStruct,
-        PointsIdsList, ScrollPointsBuilder, UpsertPointsBuilder, Value, VectorParams,
+        Condition, CountPointsBuilder, CreateCollectionBuilder, DeletePointsBuilder, Distance,
+        Filter, GetPointsBuilder, PointId, PointStruct, PointsIdsList, RetrievedPoint,
+        ScrollPointsBuilder, SearchPointsBuilder, UpsertPointsBuilder, Value, VectorParams,
         VectorParamsMap, Vectors, VectorsConfig, point_id::PointIdOptions,
-        points_selector::PointsSelectorOneOf, vectors_config::Config,
+        points_selector::PointsSelectorOneOf, vectors_config::Config, vectors_output,
     },
 };
 use serde::{Deserialize, Serialize};
+use tonic::Code;
+use tracing::info;
 
-use super::client::Storage;
-use crate::{chunking::CodeChunk, embedding::Embedding, prelude::*};
+use super::{
+    IndexedFile, SearchHit, StoredPoint,
+    client::{CollectionInfo, Storage},
+};
+use crate::{
+    chunking::CodeChunk,
+    embedding::Embedding,
+    prelude::*,
+    utils::retry::{Backoff, RetryPolicy},
+};
+
+const SCROLL_PAGE_SIZE: u32 = 256;
+const UPSERT_BATCH_SIZE: usize = 100;
+const DEFAULT_UPSERT_PARALLELISM: usize = 4;
+
+pub struct StorageConfig {
+    /// Number of upsert batches in flight at once
+    pub upsert_parallelism: Option<usize>,
+    /// Wait for Qdrant to apply each upsert before acknowledging it
+    pub wait: bool,
+    /// Backoff used for transient Qdrant failures
+    pub retry: RetryPolicy,
+    /// Embedding model recorded on every point stored, e.g. `openai:text-embedding-3-small`
+    pub model: Option<String>,
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        Self {
+            upsert_parallelism: None,
+            wait: true,
+            retry: RetryPolicy::default(),
+            model: None,
+        }
+    }
+}
 
 pub struct QdrantStorage {
     client: Qdrant,
     collection_name: String,
     vector_name: String,
-    embedding_size: usize,
+    config: StorageConfig,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -31,28 +70,90 @@ struct ChunkMetadata {
     start_line: usize,
     end_line: usize,
     language: String,
+    #[serde(default)]
+    parent_id: Option<u64>,
+    #[serde(default)]
+    start_byte: usize,
+    #[serde(default)]
+    end_byte: usize,
+    #[serde(default)]
+    content_hash: String,
+    #[serde(default)]
+    file_hash: String,
+    #[serde(default)]
+    symbol: Option<String>,
+    #[serde(default)]
+    parent_symbol: Option<String>,
+    #[serde(default)]
+    cell: Option<usize>,
+    #[serde(default)]
+    is_test: bool,
+    #[serde(default)]
+    license: Option<String>,
+    #[serde(default)]
+    encoding: Option<String>,
+    #[serde(default)]
+    package: Option<String>,
+    #[serde(default)]
+    author: Option<String>,
+    #[serde(default)]
+    last_modified: Option<i64>,
+    #[serde(default)]
+    tags: Vec<String>,
 }
 
 impl QdrantStorage {
-    pub async fn new(url: &str, collection_name: &str, embedding_size: usize) -> Result<Self> {
+    pub async fn new(
+        url: &str,
+        collection_name: &str,
+        embedding_size: usize,
+        config: StorageConfig,
+    ) -> Result<Self> {
+        let storage = Self::connect(url, collection_name, config)?;
+
+        // Ensure collection exists
+        storage.ensure_collection(embedding_size).await?;
+
+        Ok(storage)
+    }
+
+    /// Connect to an existing collection without creating it
+    pub async fn open(url: &str, collection_name: &str) -> Result<Self> {
+        let storage = Self::connect(url, collection_name, StorageConfig::default())?;
+
+        let exists = storage
+            .with_retry("collection_exists", || {
+                storage.client.collection_exists(collection_name)
+            })
+            .await?;
+
+        if !exists {
+            return Err(Missing(f!("collection {collection_name}")));
+        }
+
+        Ok(storage)
+    }
+
+    /// Connect to the server without opening a collection, to list the ones there
+    pub fn server(url: &str) -> Result<Self> {
+        Self::connect(url, "", StorageConfig::default())
+    }
+
+    fn connect(url: &str, collection_name: &str, config: StorageConfig) -> Result<Self> {
         let client = Qdrant::from_url(url).skip_compatibility_check().build().map_err(Storage)?;
 
-        let storage = Self {
+        Ok(Self {
             client,
             collection_name: collection_name.to_string(),
             vector_name: "code".to_string(),
-            embedding_size,
-        };
-
-        // Ensure collection exists
-        storage.ensure_collection().await?;
-
-        Ok(storage)
+            config,
+        })
     }
 
-    async fn ensure_collection(&self) -> Result<()> {
+    async fn ensure_collection(&self, embedding_size: usize) -> Result<()> {
         // Check if collection exists
-        let collections = self.client.list_collections().await?;
+        let collections =
+            self.with_retry("list_collections", || self.client.list_collections()).await?;
 
         // Need the collection name to be the project root
         let exists = collections.collections.iter().any(|c| c.name == self.collection_name);
@@ -63,25 +164,222 @@ impl QdrantStorage {
             vector_params.insert(
                 self.vector_name.clone(),
                 VectorParams {
-                    size: self.embedding_size as u64,
+                    size: embedding_size as u64,
                     distance: Distance::Cosine.into(),
                     ..Default::default()
                 },
             );
 
-            self.client
-                .create_collection(
-                    CreateCollectionBuilder::new(self.collection_name.clone())
-                        .vectors_config(VectorsConfig {
-                            config: Some(Config::ParamsMap(VectorParamsMap { map: vector_params })),
-                        })
-                        .build(),
-                )
-                .await?;
+            let request = CreateCollectionBuilder::new(self.collection_name.clone())
+                .vectors_config(VectorsConfig {
+                    config: Some(Config::ParamsMap(VectorParamsMap { map: vector_params })),
+                })
+                .build();
+
+            self.with_retry("create_collection", || {
+                self.client.create_collection(request.clone())
+            })
+            .await?;
+        } else {
+            self.check_embedding_size(embedding_size).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Refuse to write embeddings into a collection created for a different dimension
+    async fn check_embedding_size(&self, embedding_size: usize) -> Result<()> {
+        let size = self.collection_info().await?.vector_size;
+
+        match size {
+            Some(size) if size != embedding_size as u64 => Err(InvalidArgument(f!(
+                "Collection {} stores {size} dimension embeddings but the model produces \
+                 {embedding_size}",
+                self.collection_name
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    /// Size of the vectors in the collection's config
+    fn vector_size(&self, info: qdrant_client::qdrant::CollectionInfo) -> Option<u64> {
+        info.config
+            .and_then(|config| config.params)
+            .and_then(|params| params.vectors_config)
+            .and_then(|vectors_config| vectors_config.config)
+            .and_then(|config| match config {
+                Config::ParamsMap(params) => params.map.get(&self.vector_name).map(|p| p.size),
+                Config::Params(params) => Some(params.size),
+            })
+    }
+
+    /// Run a Qdrant call, retrying timeouts and server-side failures with backoff
+    async fn with_retry<T, F, Fut>(&self, operation: &str, call: F) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = std::result::Result<T, QdrantError>>,
+    {
+        self.config.retry.run(operation, call, classify_error).await.map_err(
+            |(source, attempts)| match attempts {
+                1 => Storage(source),
+                _ => StorageRetriesExhausted {
+                    operation: operation.to_string(),
+                    attempts,
+                    source,
+                },
+            },
+        )
+    }
+
+    fn build_point(
+        &self,
+        id: u64,
+        chunk: &CodeChunk,
+        embedding: &Embedding,
+    ) -> Result<PointStruct> {
+        let mut payload = HashMap::new();
+
+        payload.insert("content".to_string(), Value::from(chunk.content.clone()));
+        // Outside the metadata string so searches can filter on it
+        payload.insert("is_test".to_string(), Value::from(chunk.is_test));
+        if let Some(model) = &self.config.model {
+            payload.insert("model".to_string(), Value::from(model.clone()));
+        }
+        if let Some(package) = &chunk.package {
+            payload.insert("package".to_string(), Value::from(package.clone()));
+        }
+        if let Some(last_modified) = chunk.last_modified {
+            payload.insert("last_modified".to_string(), Value::from(last_modified));
+        }
+        if !chunk.tags.is_empty() {
+            payload.insert("tags".to_string(), Value::from(chunk.tags.clone()));
+        }
+
+        let metadata = ChunkMetadata {
+            path: chunk.path.to_string_lossy().to_string(),
+            node_type: chunk.node_type.clone(),
+            start_line: chunk.start_line,
+            end_line: chunk.end_line,
+            language: chunk.language.clone(),
+            parent_id: chunk.parent_id,
+            start_byte: chunk.start_byte,
+            end_byte: chunk.end_byte,
+            content_hash: chunk.content_hash.clone(),
+            file_hash: chunk.file_hash.clone(),
+            symbol: chunk.symbol.clone(),
+            parent_symbol: chunk.parent_symbol.clone(),
+            cell: chunk.cell,
+            is_test: chunk.is_test,
+            license: chunk.license.clone(),
+            encoding: chunk.encoding.clone(),
+            package: chunk.package.clone(),
+            author: chunk.author.clone(),
+            last_modified: chunk.last_modified,
+            tags: chunk.tags.clone(),
+        };
+
+        let metadata_json = serde_json::to_string(&metadata)?;
+
+        payload.insert("metadata".to_string(), Value::from(metadata_json));
+
+        let mut vectors = HashMap::new();
+        vectors.insert(self.vector_name.clone(), embedding.clone());
+
+        Ok(PointStruct::new(
+            PointId::from(id),
+            Vectors::from(vectors),
+            payload,
+        ))
+    }
+
+    fn parse_point(&self, point: RetrievedPoint) -> Result<StoredPoint> {
+        let id = match point.id {
+            Some(PointId {
+                point_id_options: Some(PointIdOptions::Num(n)),
+            }) => n,
+            _ => return Err(Payload("Point has no numeric id".to_string())),
+        };
+
+        let chunk = parse_chunk(id, &point.payload)?;
+
+        let embedding = match point.vectors.and_then(|v| v.vectors_options) {
+            Some(vectors_output::VectorsOptions::Vectors(mut named)) => {
+                named.vectors.remove(&self.vector_name).map(|vector| vector.data)
+            },
+            Some(vectors_output::VectorsOptions::Vector(vector)) => Some(vector.data),
+            None => None,
         }
+        .ok_or(Payload(f!(
+            "Point {id} has no `{}` vector",
+            self.vector_name
+        )))?;
+
+        Ok(StoredPoint {
+            id,
+            chunk,
+            embedding,
+        })
+    }
+
+    async fn upsert_points(&self, points: Vec<PointStruct>) -> Result<()> {
+        let parallelism = self.config.upsert_parallelism.unwrap_or(DEFAULT_UPSERT_PARALLELISM);
+
+        stream::iter(points.chunks(UPSERT_BATCH_SIZE))
+            .map(|batch| {
+                self.with_retry("upsert_points", move || {
+                    self.client.upsert_points(
+                        UpsertPointsBuilder::new(&self.collection_name, batch.to_vec())
+                            .wait(self.config.wait),
+                    )
+                })
+            })
+            .buffer_unordered(parallelism.max(1))
+            .try_collect::<Vec<_>>()
+            .await?;
 
         Ok(())
     }
+
+    /// The `limit` chunks closest to `embedding` that pass `filter`, closest first
+    pub async fn search(
+        &self,
+        embedding: Embedding,
+        limit: u64,
+        filter: Option<Filter>,
+    ) -> Result<Vec<SearchHit>> {
+        let mut request = SearchPointsBuilder::new(&self.collection_name, embedding, limit)
+            .vector_name(self.vector_name.clone())
+            .with_payload(true);
+
+        if let Some(filter) = filter {
+            request = request.filter(filter);
+        }
+
+        let request = request.build();
+        let response = self
+            .with_retry("search_points", || {
+                self.client.search_points(request.clone())
+            })
+            .await?;
+
+        response
+            .result
+            .into_iter()
+            .map(|point| {
+                let id = match point.id {
+                    Some(PointId {
+                        point_id_options: Some(PointIdOptions::Num(n)),
+                    }) => n,
+                    _ => return Err(Payload("Point has no numeric id".to_string())),
+                };
+
+                Ok(SearchHit {
+                    score: point.score,
+                    chunk: parse_chunk(id, &point.payload)?,
+                })
+            })
+            .collect()
+    }
 }
 
 impl Storage for QdrantStorage {
@@ -90,85 +388,354 @@ impl Storage for QdrantStorage {
             return Err(Payload("Chunks and embeddings count mismatch".to_string()));
         }
 
-        // 1. Get all existing IDs in the database
-        let search_result = self
-            .client
-            .scroll(ScrollPointsBuilder::new(self.collection_name.clone()))
-            .await
-            .map_err(Storage)?;
-        let mut existing_ids: HashSet<u64> = search_result
+        let mut points_to_upsert = Vec::new();
+
+        for (chunk, embedding) in chunks.iter().zip(embeddings.iter()) {
+            // Reproducible ID so I'm able to upsert chunks
+            // TODO: Move this to the chunker trait
+            points_to_upsert.push(self.build_point(chunk.id(), chunk, embedding)?);
+        }
+
+        self.upsert_points(points_to_upsert).await
+    }
+
+    async fn remove_stale(&self, keep: &HashSet<u64>) -> Result<()> {
+        let mut stale_points = Vec::new();
+        let mut offset: Option<PointId> = None;
+
+        loop {
+            let mut request = ScrollPointsBuilder::new(self.collection_name.clone())
+                .limit(SCROLL_PAGE_SIZE)
+                .with_payload(false)
+                .with_vectors(false);
+
+            if let Some(offset) = offset.take() {
+                request = request.offset(offset);
+            }
+
+            let request = request.build();
+            let response =
+                self.with_retry("scroll", || self.client.scroll(request.clone())).await?;
+
+            stale_points.extend(
+                response.result.into_iter().filter_map(|point| match point.id {
+                    Some(PointId {
+                        point_id_options: Some(PointIdOptions::Num(n)),
+                    }) if !keep.contains(&n) => Some(n),
+                    _ => None,
+                }),
+            );
+
+            match response.next_page_offset {
+                Some(next) => offset = Some(next),
+                None => break,
+            }
+        }
+
+        if !stale_points.is_empty() {
+            info!("Removing {} stale points", stale_points.len());
+
+            for batch in stale_points.chunks(UPSERT_BATCH_SIZE) {
+                self.with_retry("delete_points", || {
+                    self.client.delete_points(
+                        DeletePointsBuilder::new(&self.collection_name).points(
+                            PointsSelectorOneOf::Points(PointsIdsList::from(batch.to_vec())),
+                        ),
+                    )
+                })
+                .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn indexed_files(&self) -> Result<HashMap<PathBuf, IndexedFile>> {
+        let mut files: HashMap<PathBuf, IndexedFile> = HashMap::new();
+        let mut offset: Option<PointId> = None;
+
+        loop {
+            let mut request = ScrollPointsBuilder::new(self.collection_name.clone())
+                .limit(SCROLL_PAGE_SIZE)
+                .with_payload(true)
+                .with_vectors(false);
+
+            if let Some(offset) = offset.take() {
+                request = request.offset(offset);
+            }
+
+            let request = request.build();
+            let response =
+                self.with_retry("scroll", || self.client.scroll(request.clone())).await?;
+
+            for point in response.result {
+                let Some(PointId {
+                    point_id_options: Some(PointIdOptions::Num(id)),
+                }) = point.id
+                else {
+                    continue;
+                };
+                let Some(metadata) = point.payload.get("metadata").and_then(|value| value.as_str())
+                else {
+                    continue;
+                };
+                let metadata: ChunkMetadata = serde_json::from_str(metadata)?;
+
+                // Chunks indexed at different versions of a file can't vouch for any of them
+                let file = files.entry(metadata.path.into()).or_default();
+                if file.ids.is_empty() {
+                    file.hash = metadata.file_hash;
+                } else if file.hash != metadata.file_hash {
+                    file.hash.clear();
+                }
+                file.ids.push(id);
+            }
+
+            match response.next_page_offset {
+                Some(next) => offset = Some(next),
+                None => break,
+            }
+        }
+
+        Ok(files)
+    }
+
+    async fn export_page(&self, offset: Option<u64>) -> Result<(Vec<StoredPoint>, Option<u64>)> {
+        let mut request = ScrollPointsBuilder::new(self.collection_name.clone())
+            .limit(SCROLL_PAGE_SIZE)
+            .with_payload(true)
+            .with_vectors(true);
+
+        if let Some(offset) = offset {
+            request = request.offset(PointId::from(offset));
+        }
+
+        let request = request.build();
+        let response = self.with_retry("scroll", || self.client.scroll(request.clone())).await?;
+
+        let points = response
             .result
             .into_iter()
-            .filter_map(|point| match point.id {
-                Some(PointId {
-                    point_id_options: Some(PointIdOptions::Num(n)),
-                }) => Some(n),
-                _ => None,
+            .map(|point| self.parse_point(point))
+            .collect::<Result<Vec<_>>>()?;
+        let next = match response.next_page_offset {
+            Some(PointId {
+                point_id_options: Some(PointIdOptions::Num(n)),
+            }) => Some(n),
+            Some(_) => return Err(Payload("Next page starts at a non-numeric id".to_string())),
+            None => None,
+        };
+
+        Ok((points, next))
+    }
+
+    async fn count_points(&self) -> Result<u64> {
+        let request = CountPointsBuilder::new(&self.collection_name).exact(true).build();
+        let response = self.with_retry("count", || self.client.count(request.clone())).await?;
+
+        Ok(response.result.map(|result| result.count).unwrap_or_default())
+    }
+
+    async fn get_points(&self, ids: &[u64]) -> Result<Vec<StoredPoint>> {
+        let ids: Vec<PointId> = ids.iter().copied().map(PointId::from).collect();
+        let request = GetPointsBuilder::new(&self.collection_name, ids)
+            .with_payload(true)
+            .with_vectors(true)
+            .build();
+
+        let response = self
+            .with_retry("get_points", || self.client.get_points(request.clone()))
+            .await?;
+
+        response.result.into_iter().map(|point| self.parse_point(point)).collect()
+    }
+
+    async fn import_points(&self, points: &[StoredPoint]) -> Result<()> {
+        let points = points
+            .iter()
+            .map(|point| self.build_point(point.id, &point.chunk, &point.embedding))
+            .collect::<Result<Vec<_>>>()?;
+
+        self.upsert_points(points).await
+    }
+
+    async fn collections(&self) -> Result<Vec<String>> {
+        let response =
+            self.with_retry("list_collections", || self.client.list_collections()).await?;
+
+        let mut names: Vec<String> =
+            response.collections.into_iter().map(|collection| collection.name).collect();
+        names.sort();
+
+        Ok(names)
+    }
+
+    async fn collection_info(&self) -> Result<CollectionInfo> {
+        let response = self
+            .with_retry("collection_info", || {
+                self.client.collection_info(&self.collection_name)
             })
-            .collect();
+            .await?;
+        let info = response.result.ok_or(Missing(f!("collection {}", self.collection_name)))?;
+        let points = info.points_count.unwrap_or_default();
 
-        // 2. Batch upsert points and remove seen IDs
-        let mut points_to_upsert = Vec::new();
+        // Every point of a scan records the same model, so any one of them will do
+        let request = ScrollPointsBuilder::new(self.collection_name.clone())
+            .limit(1)
+            .with_payload(true)
+            .with_vectors(false)
+            .build();
+        let sample = self.with_retry("scroll", || self.client.scroll(request.clone())).await?;
+        let model = sample
+            .result
+            .first()
+            .and_then(|point| point.payload.get("model"))
+            .and_then(|value| value.as_str())
+            .cloned();
 
-        for (chunk, embedding) in chunks.iter().zip(embeddings.iter()) {
-            let mut payload = HashMap::new();
+        Ok(CollectionInfo {
+            name: self.collection_name.clone(),
+            vector_size: self.vector_size(info),
+            model,
+            points,
+        })
+    }
 
-            payload.insert("content".to_string(), Value::from(chunk.content.clone()));
+    async fn delete_collection(&self) -> Result<()> {
+        self.with_retry("delete_collection", || {
+            self.client.delete_collection(&self.collection_name)
+        })
+        .await?;
 
-            let metadata = ChunkMetadata {
-                path: chunk.path.to_string_lossy().to_string(),
-                node_type: chunk.node_type.clone(),
-                start_line: chunk.start_line,
-                end_line: chunk.end_line,
-                language: chunk.language.clone(),
-            };
+        Ok(())
+    }
 
-            let metadata_json = serde_json::to_string(&metadata)?;
+    async fn rename_collection(&self, name: &str) -> Result<()> {
+        if self.collections().await?.iter().any(|collection| collection == name) {
+            return Err(InvalidArgument(f!("Collection {name} already exists")));
+        }
 
-            payload.insert("metadata".to_string(), Value::from(metadata_json));
+        // Qdrant can't rename a collection, so its points move to a new one
+        let info = self.collection_info().await?;
+        let embedding_size = info.vector_size.ok_or(Missing(f!(
+            "vector size of collection {}",
+            self.collection_name
+        )))?;
+        let target = Self {
+            client: Qdrant::new(self.client.config.clone()).map_err(Storage)?,
+            collection_name: name.to_string(),
+            vector_name: self.vector_name.clone(),
+            config: StorageConfig {
+                model: info.model,
+                ..Default::default()
+            },
+        };
 
-            let mut vectors = HashMap::new();
-            vectors.insert(self.vector_name.clone(), embedding.clone());
+        target.ensure_collection(embedding_size as usize).await?;
 
-            // Reproducible ID so I'm able to upsert chunks
-            // TODO: Move this to the chunker trait
-            let chunk_id = {
-                let key = format!("{}:{}", chunk.path.display(), &chunk.node_type);
-                let mut hasher = DefaultHasher::new();
-                key.hash(&mut hasher);
-                hasher.finish()
-            };
-
-            existing_ids.remove(&chunk_id);
-
-            points_to_upsert.push(PointStruct::new(
-                PointId::from(chunk_id),
-                Vectors::from(vectors),
-                payload,
-            ));
-        }
-
-        for batch in points_to_upsert.chunks(100) {
-            self.client
-                .upsert_points(UpsertPointsBuilder::new(&self.collection_name, batch).wait(true))
-                .await
-                .map_err(Storage)?;
-        }
-
-        // 3. Delete remaining IDs (stale points)
-        if !existing_ids.is_empty() {
-            let stale_points: Vec<u64> = existing_ids.into_iter().collect();
-
-            for batch in stale_points.chunks(100) {
-                self.client
-                    .delete_points(DeletePointsBuilder::new(&self.collection_name).points(
-                        PointsSelectorOneOf::Points(PointsIdsList::from(batch.to_vec())),
-                    ))
-                    .await
-                    .map_err(Storage)?;
+        // A page at a time, so large collections never have to fit in memory
+        let mut copied = 0;
+        let mut offset = None;
+        loop {
+            let (points, next) = self.export_page(offset).await?;
+            target.import_points(&points).await?;
+            copied += points.len() as u64;
+
+            match next {
+                Some(next) => offset = Some(next),
+                None => break,
             }
         }
 
-        Ok(())
+        // The source is only deleted once every one of its points made it across
+        let expected = self.count_points().await?;
+        let stored = target.count_points().await?;
+        if copied != expected || stored != expected {
+            return Err(IncompleteCopy {
+                collection: self.collection_name.clone(),
+                copied: stored.min(copied),
+                expected,
+            });
+        }
+
+        info!(
+            "Copied {copied} points from {} to {name}",
+            self.collection_name
+        );
+
+        self.delete_collection().await
+    }
+}
+
+/// The chunk stored in a point's payload
+fn parse_chunk(id: u64, payload: &HashMap<String, Value>) -> Result<CodeChunk> {
+    let text = |key: &str| {
+        payload
+            .get(key)
+            .and_then(|value| value.as_str())
+            .cloned()
+            .ok_or(Payload(f!("Point {id} is missing `{key}`")))
+    };
+
+    let content = text("content")?;
+    let metadata: ChunkMetadata = serde_json::from_str(&text("metadata")?)?;
+
+    Ok(CodeChunk {
+        content,
+        node_type: metadata.node_type,
+        start_line: metadata.start_line,
+        end_line: metadata.end_line,
+        path: metadata.path.into(),
+        language: metadata.language,
+        parent_id: metadata.parent_id,
+        start_byte: metadata.start_byte,
+        end_byte: metadata.end_byte,
+        content_hash: metadata.content_hash,
+        file_hash: metadata.file_hash,
+        symbol: metadata.symbol,
+        parent_symbol: metadata.parent_symbol,
+        cell: metadata.cell,
+        is_test: metadata.is_test,
+        license: metadata.license,
+        encoding: metadata.encoding,
+        package: metadata.package,
+        author: metadata.author,
+        last_modified: metadata.last_modified,
+        tags: metadata.tags,
+    })
+}
+
+/// Search filter leaving test chunks out, or `None` when they're wanted
+pub fn test_filter(include_tests: bool) -> Option<Filter> {
+    (!include_tests).then(|| Filter::must_not([Condition::matches("is_test", true)]))
+}
+
+/// Search filter keeping only chunks of `package`, or `None` to search every package
+pub fn package_filter(package: Option<&str>) -> Option<Filter> {
+    package.map(|package| Filter::must([Condition::matches("package", package.to_string())]))
+}
+
+/// Search filter passing only the chunks every one of `filters` passes, or `None` when none are
+/// given
+pub fn all_of(filters: impl IntoIterator<Item = Option<Filter>>) -> Option<Filter> {
+    let conditions: Vec<Condition> = filters.into_iter().flatten().map(Condition::from).collect();
+
+    (!conditions.is_empty()).then(|| Filter::must(conditions))
+}
+
+/// Timeouts, overload and server-side failures are worth retrying, anything else is not
+fn classify_error(error: &QdrantError) -> Backoff {
+    match error {
+        QdrantError::ResponseError { status } => match status.code() {
+            Code::Unavailable
+            | Code::DeadlineExceeded
+            | Code::ResourceExhausted
+            | Code::Aborted
+            | Code::Internal
+            | Code::Unknown => Backoff::Retry,
+            _ => Backoff::Stop,
+        },
+        QdrantError::Io(_) => Backoff::Retry,
+        _ => Backoff::Stop,
     }
 }
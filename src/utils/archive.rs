@@ -0,0 +1,72 @@
+use std::{
+    fs::{self, File},
+    path::Path,
+};
+
+use flate2::read::GzDecoder;
+use tar::Archive;
+use tracing::info;
+use zip::ZipArchive;
+
+use crate::prelude::*;
+
+/// Archive formats `--archive` reads, by the extensions they're recognized by
+const FORMATS: &[(&str, Format)] = &[
+    (".tar.gz", Format::TarGz),
+    (".tgz", Format::TarGz),
+    (".tar", Format::Tar),
+    (".zip", Format::Zip),
+];
+
+#[derive(Debug, Clone, Copy)]
+enum Format {
+    Tar,
+    TarGz,
+    Zip,
+}
+
+/// File name of `archive` without its archive extension, `src` for `src.tar.gz`
+pub fn archive_name(archive: &Path) -> String {
+    let name = archive
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    FORMATS
+        .iter()
+        .find_map(|(extension, _)| name.strip_suffix(extension))
+        .filter(|stem| !stem.is_empty())
+        .map(str::to_string)
+        .unwrap_or(name)
+}
+
+/// Unpack `archive` into `dir`, replacing what an earlier unpack left there so files removed
+/// from the archive don't linger
+pub fn extract(archive: &Path, dir: &Path) -> Result<()> {
+    let name = archive.to_string_lossy().to_lowercase();
+    let format = FORMATS
+        .iter()
+        .find(|(extension, _)| name.ends_with(extension))
+        .map(|(_, format)| *format)
+        .ok_or(InvalidArgument(f!(
+            "{} isn't a .tar, .tar.gz, .tgz or .zip archive",
+            archive.display()
+        )))?;
+
+    info!("Extracting {} into {}", archive.display(), dir.display());
+
+    if dir.exists() {
+        fs::remove_dir_all(dir)?;
+    }
+    fs::create_dir_all(dir)?;
+
+    let file = File::open(archive)?;
+
+    match format {
+        Format::Tar => Archive::new(file).unpack(dir)?,
+        Format::TarGz => Archive::new(GzDecoder::new(file)).unpack(dir)?,
+        Format::Zip => ZipArchive::new(file)?.extract(dir)?,
+    }
+
+    Ok(())
+}
@@ -0,0 +1,84 @@
+use std::{
+    collections::HashMap,
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+use tracing::{debug, warn};
+
+use crate::prelude::*;
+
+const KEYRING_SERVICE: &str = "code-sherpa";
+
+/// The parts of the config file credentials are read from
+#[derive(Debug, Default, Deserialize)]
+struct ConfigFile {
+    #[serde(default)]
+    api_keys: HashMap<String, String>,
+}
+
+/// Where provider API keys are looked up, in order: env var, config file, OS keyring
+#[derive(Debug, Default)]
+pub struct Credentials {
+    api_keys: HashMap<String, String>,
+}
+
+impl Credentials {
+    /// Load the `[api_keys]` table of `path`, or of the user config file when `path` is `None`.
+    /// A missing default config file is not an error
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        let Some(path) = config_file(path) else {
+            return Ok(Self::default());
+        };
+
+        let content = fs::read_to_string(&path)?;
+        let config: ConfigFile = toml::from_str(&content)
+            .map_err(|e| InvalidArgument(f!("Invalid config file {}: {e}", path.display())))?;
+
+        debug!("Loaded credentials from {}", path.display());
+
+        Ok(Self {
+            api_keys: config.api_keys,
+        })
+    }
+
+    /// API key for `provider`, with `env_var` taking precedence over the config file and keyring
+    pub fn api_key(&self, provider: &str, env_var: &str) -> Option<String> {
+        env::var(env_var)
+            .ok()
+            .or_else(|| self.api_keys.get(provider).cloned())
+            .or_else(|| keyring_api_key(provider))
+    }
+}
+
+/// `path`, or the user config file when `path` is `None` and it exists
+pub fn config_file(path: Option<&Path>) -> Option<PathBuf> {
+    match path {
+        Some(path) => Some(path.to_path_buf()),
+        None => dirs::config_dir()
+            .map(|dir| dir.join("code-sherpa").join("config.toml"))
+            .filter(|path| path.exists()),
+    }
+}
+
+/// Read `provider`'s key from the OS keyring, stored under the `code-sherpa` service. On Linux
+/// this needs the `secret-service` feature, without it no key is ever found
+fn keyring_api_key(provider: &str) -> Option<String> {
+    let entry = match keyring::Entry::new(KEYRING_SERVICE, provider) {
+        Ok(entry) => entry,
+        Err(e) => {
+            warn!("Unable to open the OS keyring: {e}");
+            return None;
+        },
+    };
+
+    match entry.get_password() {
+        Ok(password) => Some(password),
+        Err(keyring::Error::NoEntry) => None,
+        Err(e) => {
+            warn!("Unable to read {provider} API key from the OS keyring: {e}");
+            None
+        },
+    }
+}
@@ -0,0 +1,276 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use git2::{DiffOptions, FetchOptions, Repository, ResetType, build::RepoBuilder};
+use tracing::info;
+use url::Url;
+
+use crate::prelude::*;
+
+/// Files under `root` added, modified or renamed since `reference`, including uncommitted and
+/// untracked changes. Paths are joined onto `root` the way the scanner walks them
+pub fn changed_since(root: &Path, reference: &str) -> Result<HashSet<PathBuf>> {
+    let repository = Repository::discover(root)?;
+    let workdir = repository
+        .workdir()
+        .ok_or(InvalidArgument(f!(
+            "{} is a bare repository",
+            root.display()
+        )))?
+        .canonicalize()?;
+    let root_dir = root.canonicalize()?;
+
+    let tree = repository.revparse_single(reference)?.peel_to_tree()?;
+    let diff = repository.diff_tree_to_workdir_with_index(
+        Some(&tree),
+        Some(DiffOptions::new().include_untracked(true).recurse_untracked_dirs(true)),
+    )?;
+
+    // Deleted files aren't walked, so their points go stale and are removed with the rest
+    let changed = diff
+        .deltas()
+        .filter_map(|delta| delta.new_file().path().map(|path| workdir.join(path)))
+        .filter(|path| path.exists())
+        .filter_map(|path| path.strip_prefix(&root_dir).ok().map(|relative| root.join(relative)))
+        .collect();
+
+    Ok(changed)
+}
+
+/// Files under `root` in the git index, as `git ls-files` lists them. Paths are joined onto
+/// `root` the way the scanner walks them
+pub fn tracked_files(root: &Path) -> Result<HashSet<PathBuf>> {
+    let repository = Repository::discover(root)?;
+    let workdir = repository
+        .workdir()
+        .ok_or(InvalidArgument(f!(
+            "{} is a bare repository",
+            root.display()
+        )))?
+        .canonicalize()?;
+    let root_dir = root.canonicalize()?;
+
+    let tracked = repository
+        .index()?
+        .iter()
+        .map(|entry| workdir.join(String::from_utf8_lossy(&entry.path).as_ref()))
+        .filter_map(|path| path.strip_prefix(&root_dir).ok().map(|relative| root.join(relative)))
+        .collect();
+
+    Ok(tracked)
+}
+
+/// The commit checked out in the repository holding `root` and what its last fetch brought in,
+/// which change when it's fetched, pulled or checked out. `None` outside a repository
+pub fn fetch_state(root: &Path) -> Option<String> {
+    let repository = Repository::discover(root).ok()?;
+    let head = repository.head().ok()?.peel_to_commit().ok()?.id();
+    let fetched = fs::read_to_string(repository.path().join("FETCH_HEAD")).unwrap_or_default();
+
+    Some(f!("{head}\n{fetched}"))
+}
+
+/// Checked out submodules under `root` by name, with their path joined onto `root` the way the
+/// scanner walks them
+pub fn submodules(root: &Path) -> Result<Vec<(String, PathBuf)>> {
+    let repository = Repository::discover(root)?;
+    let workdir = repository
+        .workdir()
+        .ok_or(InvalidArgument(f!(
+            "{} is a bare repository",
+            root.display()
+        )))?
+        .canonicalize()?;
+    let root_dir = root.canonicalize()?;
+
+    let submodules = repository
+        .submodules()?
+        .iter()
+        .filter_map(|submodule| {
+            let dir = workdir.join(submodule.path());
+            let relative = dir.strip_prefix(&root_dir).ok()?;
+
+            // Submodules that were never initialized are empty directories
+            dir.join(".git").exists().then(|| {
+                (
+                    submodule.name().unwrap_or_default().to_string(),
+                    root.join(relative),
+                )
+            })
+        })
+        .collect();
+
+    Ok(submodules)
+}
+
+/// Id of the commit checked out in the repository holding `root`
+pub fn head_commit(root: &Path) -> Result<String> {
+    let repository = Repository::discover(root)?;
+    let commit = repository.head()?.peel_to_commit()?;
+
+    Ok(commit.id().to_string())
+}
+
+/// Commit recorded in `record` by the last scan, if there was one
+pub fn last_indexed_commit(record: &Path) -> Option<String> {
+    fs::read_to_string(record).ok().map(|commit| commit.trim().to_string())
+}
+
+/// Remember `commit` as the last one indexed, for the next `--changed-since`
+pub fn record_indexed_commit(record: &Path, commit: &str) -> Result<()> {
+    if let Some(parent) = record.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    Ok(fs::write(record, commit)?)
+}
+
+/// Name of the repository at `url`, its last path segment without `.git`
+pub fn repository_name(url: &Url) -> String {
+    url.path_segments()
+        .and_then(|mut segments| segments.rfind(|segment| !segment.is_empty()))
+        .map(|name| name.trim_end_matches(".git").to_string())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| String::from("code-sherpa"))
+}
+
+/// Shallow-clone the repository at `url` into `dir`, or when an earlier clone is there, fetch the
+/// remote's latest commit and check it out. Reusing the clone keeps paths stable so the next scan
+/// only reindexes what changed
+pub fn shallow_clone(url: &Url, dir: &Path) -> Result<()> {
+    let mut fetch_options = FetchOptions::new();
+    fetch_options.depth(1);
+
+    if dir.join(".git").exists() {
+        info!("Fetching {url} into {}", dir.display());
+
+        let repository = Repository::open(dir)?;
+        repository
+            .find_remote("origin")?
+            .fetch(&["HEAD"], Some(&mut fetch_options), None)?;
+
+        let fetched = repository.find_reference("FETCH_HEAD")?.peel_to_commit()?;
+        repository.reset(fetched.as_object(), ResetType::Hard, None)?;
+
+        return Ok(());
+    }
+
+    info!("Cloning {url} into {}", dir.display());
+
+    if let Some(parent) = dir.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    RepoBuilder::new().fetch_options(fetch_options).clone(url.as_str(), dir)?;
+
+    Ok(())
+}
+
+/// Who last changed each line of a file, and when
+pub struct FileBlame {
+    hunks: Vec<BlameHunk>,
+}
+
+struct BlameHunk {
+    /// 1-based line the hunk starts at
+    start: usize,
+    lines: usize,
+    author: String,
+    /// Commit time in seconds since the Unix epoch
+    time: i64,
+}
+
+/// The author of most of a range of lines, and the last time any of them changed
+pub struct LineBlame {
+    pub author: String,
+    pub last_modified: i64,
+}
+
+impl FileBlame {
+    /// Blame for the 1-based lines `start` to `end`, both included. `None` when none of them are
+    /// committed yet
+    pub fn lines(&self, start: usize, end: usize) -> Option<LineBlame> {
+        let mut authors: HashMap<&str, usize> = HashMap::new();
+        let mut last_modified = None;
+
+        for hunk in &self.hunks {
+            let from = hunk.start.max(start);
+            let to = (hunk.start + hunk.lines).saturating_sub(1).min(end);
+
+            if from > to {
+                continue;
+            }
+
+            *authors.entry(&hunk.author).or_default() += to - from + 1;
+            last_modified = last_modified.max(Some(hunk.time));
+        }
+
+        // Ties go to the same author every time
+        let (author, _) = authors.into_iter().max_by_key(|&(author, lines)| (lines, author))?;
+
+        Some(LineBlame {
+            author: author.to_string(),
+            last_modified: last_modified?,
+        })
+    }
+}
+
+/// Blames files one after another, keeping the repository of the last one open
+#[derive(Default)]
+pub struct Blamer {
+    repository: Option<(PathBuf, Repository)>,
+}
+
+impl Blamer {
+    /// Blame the file at `path` as it is now, `content` being what it holds. Lines changed since
+    /// the last commit aren't blamed on anyone
+    pub fn blame(&mut self, path: &Path, content: &str) -> Result<FileBlame> {
+        let file = path.canonicalize()?;
+
+        // Files of the same repository reuse the one opened for the last file
+        let cached = self.repository.take().filter(|(workdir, _)| file.starts_with(workdir));
+        let (workdir, repository) = match cached {
+            Some(cached) => self.repository.insert(cached),
+            None => {
+                let repository = Repository::discover(&file)?;
+                let workdir = repository
+                    .workdir()
+                    .ok_or(InvalidArgument(f!(
+                        "{} is in a bare repository",
+                        path.display()
+                    )))?
+                    .canonicalize()?;
+
+                self.repository.insert((workdir, repository))
+            },
+        };
+        let relative = file
+            .strip_prefix(workdir)
+            .map_err(|_| InvalidArgument(f!("{} is outside its repository", path.display())))?;
+
+        let committed = repository.blame_file(relative, None)?;
+        // Blame the content as read, which may differ from the committed file
+        let blame = committed.blame_buffer(content.as_bytes())?;
+        let hunks = blame
+            .iter()
+            .filter(|hunk| !hunk.final_commit_id().is_zero())
+            .map(|hunk| {
+                let signature = hunk.final_signature();
+                // Names and emails that aren't valid UTF-8 fall back to the other, then to nothing
+                let author = signature.name().or(signature.email()).unwrap_or_default();
+
+                BlameHunk {
+                    start: hunk.final_start_line(),
+                    lines: hunk.lines_in_hunk(),
+                    author: author.to_string(),
+                    time: signature.when().seconds(),
+                }
+            })
+            .collect();
+
+        Ok(FileBlame { hunks })
+    }
+}
@@ -0,0 +1,55 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{ErrorKind, Write},
+    path::{Path, PathBuf},
+    process,
+};
+
+use tracing::warn;
+
+use crate::prelude::*;
+
+/// Lockfile held while a collection is scanned, so two scans can't interleave their upserts and
+/// remove each other's points as stale. It holds the scanning process' id and is removed once
+/// dropped
+pub struct ScanLock {
+    path: PathBuf,
+}
+
+impl ScanLock {
+    /// Take the lock on `collection` at `path`, or take it over from the scan holding it with
+    /// `force`, e.g. when that scan crashed and left it behind
+    pub fn acquire(path: &Path, collection: &str, force: bool) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let mut file = match OpenOptions::new().write(true).create_new(true).open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == ErrorKind::AlreadyExists && force => {
+                warn!("Taking over the lock on {collection} at {}", path.display());
+                File::create(path)?
+            },
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                return Err(ScanLocked {
+                    collection: collection.to_string(),
+                    holder: fs::read_to_string(path).unwrap_or_default().trim().to_string(),
+                    path: path.to_path_buf(),
+                });
+            },
+            Err(e) => return Err(e.into()),
+        };
+
+        writeln!(file, "{}", process::id())?;
+
+        Ok(Self {
+            path: path.to_path_buf(),
+        })
+    }
+}
+
+impl Drop for ScanLock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
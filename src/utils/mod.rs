@@ -1,6 +1,11 @@
+pub mod archive;
+pub mod credentials;
+pub mod git;
+pub mod lock;
 pub mod parsers;
+pub mod retry;
 
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use tracing::debug;
 
@@ -20,3 +25,18 @@ pub fn path_to_collection_name(path: &Path) -> String {
         .map(|s| s.to_string())
         .unwrap_or_else(|| "code-sherpa".to_string())
 }
+
+/// Deepest directory holding all of `paths`, compared component by component as given
+pub fn common_ancestor(paths: &[PathBuf]) -> PathBuf {
+    let Some((first, rest)) = paths.split_first() else {
+        return PathBuf::new();
+    };
+
+    let mut ancestor: Vec<_> = first.components().collect();
+    for path in rest {
+        let shared = ancestor.iter().zip(path.components()).take_while(|(a, b)| *a == b).count();
+        ancestor.truncate(shared);
+    }
+
+    ancestor.iter().collect()
+}
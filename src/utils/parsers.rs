@@ -1,3 +1,9 @@
+use std::{
+    fs::File,
+    io::{BufRead, BufReader},
+    path::Path,
+};
+
 use serde::{Deserialize, Serialize};
 use strum::Display;
 use tree_sitter::Language;
@@ -22,6 +28,71 @@ pub enum SupportedParsers {
     #[serde(rename = "tsx")]
     #[allow(clippy::upper_case_acronyms)]
     TSX,
+
+    #[serde(rename = "rb", alias = "rake", alias = "gemspec", alias = "ru")]
+    Ruby,
+
+    #[serde(rename = "php")]
+    Php,
+
+    #[serde(rename = "ex", alias = "exs")]
+    Elixir,
+
+    #[serde(rename = "hs")]
+    Haskell,
+
+    #[serde(rename = "dart")]
+    Dart,
+
+    #[serde(rename = "sh", alias = "bash")]
+    Bash,
+
+    #[serde(rename = "sql")]
+    Sql,
+
+    #[serde(rename = "tf", alias = "hcl", alias = "tfvars")]
+    Hcl,
+
+    #[serde(rename = "proto")]
+    Proto,
+
+    #[serde(rename = "graphql", alias = "gql")]
+    GraphQL,
+
+    #[serde(rename = "yaml", alias = "yml")]
+    Yaml,
+
+    #[serde(rename = "json")]
+    Json,
+
+    #[serde(rename = "toml")]
+    Toml,
+
+    #[serde(rename = "md", alias = "markdown")]
+    Markdown,
+
+    #[serde(rename = "html", alias = "htm")]
+    Html,
+
+    #[serde(rename = "css")]
+    Css,
+
+    #[serde(rename = "scss")]
+    Scss,
+
+    /// Split into blocks, each parsed with its own grammar
+    #[serde(rename = "svelte")]
+    Svelte,
+
+    /// Code cells are parsed as Python and markdown cells as Markdown
+    #[serde(rename = "ipynb")]
+    Jupyter,
+
+    #[serde(rename = "r", alias = "R")]
+    R,
+
+    #[serde(rename = "mk", alias = "mak")]
+    Make,
 }
 
 impl SupportedParsers {
@@ -29,10 +100,98 @@ impl SupportedParsers {
         match self {
             Self::Rust => tree_sitter_rust::LANGUAGE.into(),
             Self::Go => tree_sitter_go::LANGUAGE.into(),
-            Self::Python => tree_sitter_python::LANGUAGE.into(),
+            Self::Python | Self::Jupyter => tree_sitter_python::LANGUAGE.into(),
             Self::JavaScript => tree_sitter_javascript::LANGUAGE.into(),
             Self::TypeScript => tree_sitter_typescript::LANGUAGE_TYPESCRIPT.into(),
             Self::TSX => tree_sitter_typescript::LANGUAGE_TSX.into(),
+            Self::Ruby => tree_sitter_ruby::LANGUAGE.into(),
+            Self::Php => tree_sitter_php::LANGUAGE_PHP.into(),
+            Self::Elixir => tree_sitter_elixir::LANGUAGE.into(),
+            Self::Haskell => tree_sitter_haskell::LANGUAGE.into(),
+            Self::Dart => tree_sitter_dart::language(),
+            Self::Bash => tree_sitter_bash::LANGUAGE.into(),
+            Self::Sql => tree_sitter_sequel::LANGUAGE.into(),
+            Self::Hcl => tree_sitter_hcl::LANGUAGE.into(),
+            Self::Proto => tree_sitter_proto::LANGUAGE.into(),
+            Self::GraphQL => tree_sitter_graphql::LANGUAGE.into(),
+            Self::Yaml => tree_sitter_yaml::LANGUAGE.into(),
+            Self::Json => tree_sitter_json::LANGUAGE.into(),
+            Self::Toml => tree_sitter_toml_ng::LANGUAGE.into(),
+            Self::Markdown => tree_sitter_md::LANGUAGE.into(),
+            Self::R => tree_sitter_r::LANGUAGE.into(),
+            Self::Make => tree_sitter_make::LANGUAGE.into(),
+            Self::Html | Self::Svelte => tree_sitter_html::LANGUAGE.into(),
+            Self::Css => tree_sitter_css::LANGUAGE.into(),
+            Self::Scss => tree_sitter_scss::language(),
+        }
+    }
+
+    /// The parser for a file, from its name or extension or, for extensionless files, the
+    /// shebang or opening of its content
+    pub fn for_path(path: &Path) -> Option<Self> {
+        if let Some(parser) = Self::from_file_name(&path.file_name()?.to_string_lossy()) {
+            return Some(parser);
+        }
+
+        match path.extension() {
+            Some(extension) => serde_plain::from_str(&extension.to_string_lossy()).ok(),
+            None => {
+                let mut first_line = String::new();
+                BufReader::new(File::open(path).ok()?).read_line(&mut first_line).ok()?;
+
+                Self::from_shebang(&first_line).or_else(|| Self::from_content(&first_line))
+            },
+        }
+    }
+
+    /// Well-known files named without an extension
+    fn from_file_name(name: &str) -> Option<Self> {
+        match name {
+            // Just recipes are laid out like make rules
+            "Makefile" | "makefile" | "GNUmakefile" | "Justfile" | "justfile" => Some(Self::Make),
+            "Gemfile" | "Rakefile" | "Guardfile" | "Podfile" | "Vagrantfile" | "Brewfile"
+            | "Capfile" => Some(Self::Ruby),
+            ".bashrc" | ".bash_profile" | ".profile" => Some(Self::Bash),
+            _ => None,
+        }
+    }
+
+    /// The parser for a `#!` line's interpreter, looking past `/usr/bin/env`
+    fn from_shebang(line: &str) -> Option<Self> {
+        let mut words = line.strip_prefix("#!")?.split_whitespace();
+        let mut interpreter = words.next()?.rsplit('/').next()?;
+
+        if interpreter == "env" {
+            interpreter = words.find(|word| !word.starts_with('-'))?;
+        }
+
+        match interpreter {
+            "sh" | "bash" | "dash" | "zsh" | "ksh" => Some(Self::Bash),
+            python if python.starts_with("python") => Some(Self::Python),
+            "node" | "nodejs" | "deno" | "bun" => Some(Self::JavaScript),
+            "ts-node" | "tsx" => Some(Self::TypeScript),
+            ruby if ruby.starts_with("ruby") => Some(Self::Ruby),
+            "php" => Some(Self::Php),
+            "elixir" => Some(Self::Elixir),
+            "runghc" | "runhaskell" => Some(Self::Haskell),
+            "Rscript" => Some(Self::R),
+            "make" => Some(Self::Make),
+            _ => None,
+        }
+    }
+
+    /// Languages recognizable from how a file starts
+    fn from_content(first_line: &str) -> Option<Self> {
+        let first_line = first_line.trim_start();
+
+        if first_line.starts_with("<?php") {
+            Some(Self::Php)
+        } else if first_line.to_ascii_lowercase().starts_with("<!doctype html")
+            || first_line.starts_with("<html")
+        {
+            Some(Self::Html)
+        } else {
+            None
         }
     }
 }
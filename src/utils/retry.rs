@@ -0,0 +1,83 @@
+use std::{future::Future, time::Duration};
+
+use rand::Rng;
+use tracing::warn;
+
+/// What to do after a failed attempt
+pub enum Backoff {
+    /// The error is permanent, give up immediately
+    Stop,
+    /// Retry after the policy's exponential delay
+    Retry,
+    /// Retry after a delay requested by the server, capped at the policy's `max_delay`
+    RetryAfter(Duration),
+}
+
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    pub max_retries: usize,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 5,
+            base_delay: Duration::from_millis(250),
+            max_delay: Duration::from_secs(10),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Exponential delay before retry number `attempt` (starting at 0), with jitter
+    pub fn delay(&self, attempt: usize) -> Duration {
+        let exponential = self.base_delay.saturating_mul(1 << attempt.min(16));
+        let capped = exponential.min(self.max_delay);
+
+        capped.mul_f64(rand::rng().random_range(0.5..=1.0))
+    }
+
+    /// Run `operation` until it succeeds, `classify` says to stop, or retries run out.
+    ///
+    /// On failure, returns the last error along with the number of attempts made.
+    pub async fn run<T, E, F, Fut>(
+        &self,
+        name: &str,
+        mut operation: F,
+        classify: impl Fn(&E) -> Backoff,
+    ) -> std::result::Result<T, (E, usize)>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = std::result::Result<T, E>>,
+        E: std::fmt::Display,
+    {
+        let mut attempt = 0;
+
+        loop {
+            let error = match operation().await {
+                Ok(value) => return Ok(value),
+                Err(e) => e,
+            };
+
+            if attempt >= self.max_retries {
+                return Err((error, attempt + 1));
+            }
+
+            let delay = match classify(&error) {
+                Backoff::Stop => return Err((error, attempt + 1)),
+                Backoff::Retry => self.delay(attempt),
+                // Servers can ask for waits far longer than the policy allows
+                Backoff::RetryAfter(delay) => delay.min(self.max_delay),
+            };
+
+            warn!(
+                "{name} failed ({error}), retrying in {}ms",
+                delay.as_millis()
+            );
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+}